@@ -0,0 +1,52 @@
+//! The `#[cpu_time]` attribute of the `cpu-time` crate
+//!
+//! Use it through the `macros` feature of `cpu-time`, which re-exports it
+//! as `cpu_time::cpu_time`.
+
+extern crate proc_macro;
+extern crate proc_macro2;
+extern crate quote;
+extern crate syn;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Error, ItemFn};
+
+/// Measures the thread CPU time of every call of the function and reports
+/// it with `cpu_time::report()`
+///
+/// The label is the path of the function, e.g. `my_crate::parser::parse`.
+/// The time is reported when the function returns, also early or by a
+/// panic.
+#[proc_macro_attribute]
+pub fn cpu_time(args: TokenStream, item: TokenStream) -> TokenStream {
+    let args = proc_macro2::TokenStream::from(args);
+    if !args.is_empty() {
+        return Error::new_spanned(args, "`#[cpu_time]` takes no arguments")
+            .to_compile_error()
+            .into();
+    }
+    let ItemFn {
+        attrs,
+        vis,
+        sig,
+        block,
+    } = parse_macro_input!(item as ItemFn);
+    if let Some(asyncness) = sig.asyncness {
+        // The scope would be held across `.await`s, measuring whatever the
+        // thread runs meanwhile
+        return Error::new_spanned(asyncness, "`#[cpu_time]` can't measure async functions")
+            .to_compile_error()
+            .into();
+    }
+    let name = sig.ident.to_string();
+    quote!(
+        #(#attrs)*
+        #vis #sig {
+            let _cpu_time_scope =
+                ::cpu_time::CpuScope::new(concat!(module_path!(), "::", #name));
+            #block
+        }
+    )
+    .into()
+}
@@ -0,0 +1,24 @@
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use {ProcessTime, ThreadTime};
+
+// Timestamps are generated from nanoseconds, so they can also be serialized
+impl<'a> Arbitrary<'a> for ProcessTime {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<ProcessTime> {
+        u64::arbitrary(u).map(ProcessTime::from_nanos)
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        u64::size_hint(depth)
+    }
+}
+
+impl<'a> Arbitrary<'a> for ThreadTime {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<ThreadTime> {
+        u64::arbitrary(u).map(ThreadTime::from_nanos)
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        u64::size_hint(depth)
+    }
+}
@@ -0,0 +1,121 @@
+use std::io;
+use std::process::Command;
+use std::time::Duration;
+
+use ChildExt;
+use SplitTime;
+
+/// CPU times of repeated runs of a command, returned by `bench_command()`
+///
+/// The statistics are over the total (user plus system) CPU time of each
+/// run and zero if there were no runs.
+#[derive(Clone, PartialEq, Eq, Debug, Hash, Default)]
+pub struct CommandBench {
+    runs: Vec<SplitTime>,
+}
+
+impl CommandBench {
+    /// Returns the user and system time of each run, in order
+    pub fn runs(&self) -> &[SplitTime] {
+        &self.runs
+    }
+    /// Returns the mean CPU time of a run
+    pub fn mean(&self) -> Duration {
+        self.mean_split().total()
+    }
+    /// Returns the mean user and system time of a run
+    pub fn mean_split(&self) -> SplitTime {
+        if self.runs.is_empty() {
+            return SplitTime::default();
+        }
+        let count = self.runs.len() as u128;
+        let user = self
+            .runs
+            .iter()
+            .map(|run| run.user.as_nanos())
+            .sum::<u128>();
+        let system = self
+            .runs
+            .iter()
+            .map(|run| run.system.as_nanos())
+            .sum::<u128>();
+        SplitTime {
+            user: from_nanos(user / count),
+            system: from_nanos(system / count),
+        }
+    }
+    /// Returns the median CPU time of a run, the mean of the two middle
+    /// ones for an even number of runs
+    pub fn median(&self) -> Duration {
+        let mut totals = self.totals().collect::<Vec<_>>();
+        totals.sort();
+        match totals.len() {
+            0 => Duration::new(0, 0),
+            len if len % 2 == 1 => totals[len / 2],
+            len => from_nanos((totals[len / 2 - 1].as_nanos() + totals[len / 2].as_nanos()) / 2),
+        }
+    }
+    /// Returns the sample standard deviation of the CPU time of a run, zero
+    /// for fewer than two runs
+    pub fn stddev(&self) -> Duration {
+        if self.runs.len() < 2 {
+            return Duration::new(0, 0);
+        }
+        let mean = self.mean().as_secs_f64();
+        let squares = self
+            .totals()
+            .map(|total| (total.as_secs_f64() - mean).powi(2))
+            .sum::<f64>();
+        Duration::from_secs_f64((squares / (self.runs.len() - 1) as f64).sqrt())
+    }
+    /// Returns the smallest CPU time of a run
+    pub fn min(&self) -> Duration {
+        self.totals().min().unwrap_or_else(|| Duration::new(0, 0))
+    }
+    /// Returns the largest CPU time of a run
+    pub fn max(&self) -> Duration {
+        self.totals().max().unwrap_or_else(|| Duration::new(0, 0))
+    }
+    fn totals<'a>(&'a self) -> impl Iterator<Item = Duration> + 'a {
+        self.runs.iter().map(|run| run.total())
+    }
+}
+
+fn from_nanos(nanos: u128) -> Duration {
+    Duration::new(
+        (nanos / 1_000_000_000) as u64,
+        (nanos % 1_000_000_000) as u32,
+    )
+}
+
+/// Runs `command` `runs` times and returns the CPU time of each run
+///
+/// A minimal `hyperfine`, measuring CPU instead of wall-clock time, which
+/// is much less disturbed by other load on the machine:
+///
+/// ```rust,no_run
+/// use std::process::{Command, Stdio};
+///
+/// let mut command = Command::new("gzip");
+/// command.args(["-9", "-c", "big.log"]).stdout(Stdio::null());
+/// let bench = cpu_time::bench_command(&mut command, 10).unwrap();
+/// println!("{:?} ± {:?}", bench.mean(), bench.stddev());
+/// ```
+///
+/// The runs are one after another, each waited for with
+/// `ChildExt::wait_with_cpu_time()`, so the times include those of the
+/// processes the command starts and waits for (not on Windows). Returns an
+/// error if the command can't be started or exits unsuccessfully.
+pub fn bench_command(command: &mut Command, runs: usize) -> io::Result<CommandBench> {
+    let mut bench = CommandBench {
+        runs: Vec::with_capacity(runs),
+    };
+    for _ in 0..runs {
+        let (status, split) = command.spawn()?.wait_with_cpu_time()?;
+        if !status.success() {
+            return Err(io::Error::other(format!("command failed: {}", status)));
+        }
+        bench.runs.push(split);
+    }
+    Ok(bench)
+}
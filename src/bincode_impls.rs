@@ -0,0 +1,38 @@
+use bincode::de::{Decode, Decoder};
+use bincode::enc::{Encode, Encoder};
+use bincode::error::{DecodeError, EncodeError};
+
+use nanos::to_nanos;
+use {ProcessTime, ThreadTime};
+
+/// Encoded as the number of nanoseconds of CPU time
+impl Encode for ProcessTime {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        to_nanos(self.duration()).encode(encoder)
+    }
+}
+
+/// The pid of a `ProcessTime::now_for()` timestamp isn't encoded, so the
+/// decoded timestamp belongs to the current process
+impl<Context> Decode<Context> for ProcessTime {
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<ProcessTime, DecodeError> {
+        u64::decode(decoder).map(ProcessTime::from_nanos)
+    }
+}
+
+impl_borrow_decode!(ProcessTime);
+
+/// Encoded as the number of nanoseconds of CPU time
+impl Encode for ThreadTime {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        to_nanos(self.duration()).encode(encoder)
+    }
+}
+
+impl<Context> Decode<Context> for ThreadTime {
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<ThreadTime, DecodeError> {
+        u64::decode(decoder).map(ThreadTime::from_nanos)
+    }
+}
+
+impl_borrow_decode!(ThreadTime);
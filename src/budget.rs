@@ -0,0 +1,117 @@
+#[cfg(feature = "std")]
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+use ThreadTime;
+
+/// Error of `run_with_cpu_budget()` when the closure used more CPU time
+/// than allowed
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash, Default)]
+pub struct BudgetExceeded {
+    /// The CPU time the closure was allowed
+    pub budget: Duration,
+    /// The CPU time it used until it returned or gave up
+    pub used: Duration,
+}
+
+impl fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "CPU budget of {:?} exceeded, used {:?}",
+            self.budget, self.used
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for BudgetExceeded {}
+
+/// The CPU budget of a `run_with_cpu_budget()` closure, for checking it
+/// along the way
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub struct CpuBudget {
+    start: ThreadTime,
+    budget: Duration,
+}
+
+impl CpuBudget {
+    /// Returns the CPU time the closure was allowed
+    pub fn budget(&self) -> Duration {
+        self.budget
+    }
+    /// Returns the CPU time the closure used so far
+    pub fn used(&self) -> Duration {
+        self.start.elapsed()
+    }
+    /// Returns the CPU time left, zero once the budget is used up
+    pub fn remaining(&self) -> Duration {
+        self.budget
+            .checked_sub(self.used())
+            .unwrap_or_else(|| Duration::new(0, 0))
+    }
+    /// Returns `true` if the closure used more than its budget
+    pub fn exceeded(&self) -> bool {
+        self.used() > self.budget
+    }
+    /// Returns an error if the closure used more than its budget, to
+    /// bail out with `?`
+    pub fn check(&self) -> Result<(), BudgetExceeded> {
+        let used = self.used();
+        if used > self.budget {
+            return Err(BudgetExceeded {
+                budget: self.budget,
+                used,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Runs `f` and returns its result if it used at most `budget` of CPU
+/// time
+///
+/// The time is that of the current thread, which `f` runs on. A closure
+/// can't be stopped from the outside, so long-running ones should call
+/// `check()` or `exceeded()` of the `CpuBudget` they get in between, e.g.
+/// once per iteration:
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+///
+/// # fn step(_: u64) -> u64 { 0 }
+/// let result = cpu_time::run_with_cpu_budget(Duration::from_secs(1), |budget| {
+///     let mut best = 0;
+///     for n in 0.. {
+///         if budget.exceeded() {
+///             break;
+///         }
+///         best = best.max(step(n));
+///     }
+///     best
+/// });
+/// match result {
+///     Ok(best) => println!("{}", best),
+///     Err(error) => println!("gave up after {:?}", error.used),
+/// }
+/// ```
+///
+/// Returns `BudgetExceeded` with the CPU time used if `f` went over the
+/// budget, even if it returned normally, in which case its result is
+/// dropped.
+///
+/// # Panics
+///
+/// If the thread CPU time can't be read, like `ThreadTime::now()`.
+pub fn run_with_cpu_budget<T, F>(budget: Duration, f: F) -> Result<T, BudgetExceeded>
+where
+    F: FnOnce(&CpuBudget) -> T,
+{
+    let budget = CpuBudget {
+        start: ThreadTime::now(),
+        budget,
+    };
+    let result = f(&budget);
+    budget.check().map(|()| result)
+}
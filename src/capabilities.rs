@@ -0,0 +1,48 @@
+use {ProcessTime, ThreadTime};
+
+/// CPU clocks available in the current environment
+///
+/// Returned by `capabilities()`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub struct Capabilities {
+    process_time: bool,
+    thread_time: bool,
+    wall_clock: bool,
+}
+
+impl Capabilities {
+    /// Returns `true` if `ProcessTime::now()` works
+    pub fn process_time(&self) -> bool {
+        self.process_time
+    }
+    /// Returns `true` if `ThreadTime::now()` works
+    pub fn thread_time(&self) -> bool {
+        self.thread_time
+    }
+    /// Returns `true` if the timers approximate CPU time with wall-clock
+    /// time, so they also count time spent sleeping or waiting
+    ///
+    /// This is the case on Emscripten and in browsers.
+    pub fn wall_clock(&self) -> bool {
+        self.wall_clock
+    }
+}
+
+/// Probe which CPU clocks work in the current environment
+///
+/// Clocks are actually read, so this detects containers and VMs where the
+/// crate compiles fine but the kernel rejects CPU-time clocks at runtime.
+pub fn capabilities() -> Capabilities {
+    Capabilities {
+        process_time: ProcessTime::is_supported(),
+        thread_time: ThreadTime::is_supported(),
+        wall_clock: cfg!(any(
+            target_os = "emscripten",
+            all(
+                target_arch = "wasm32",
+                target_os = "unknown",
+                feature = "wasm-bindgen"
+            )
+        )),
+    }
+}
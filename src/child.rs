@@ -0,0 +1,292 @@
+use std::io;
+#[cfg(unix)]
+use std::io::ErrorKind;
+#[cfg(unix)]
+use std::mem;
+#[cfg(unix)]
+use std::os::unix::process::ExitStatusExt;
+#[cfg(windows)]
+use std::os::windows::io::AsRawHandle;
+use std::process::{Child, Command, ExitStatus};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(unix)]
+use libc::{c_int, pid_t, rusage, ECHILD, WNOHANG};
+
+#[cfg(windows)]
+use windows::process_split;
+#[cfg(windows)]
+use CpuTimeError;
+#[cfg(unix)]
+use ResourceUsage;
+use SplitTime;
+
+#[cfg(unix)]
+/// A child process reaped by `wait4()` or `try_wait4()`
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct ReapedChild {
+    /// Process id of the child
+    pub pid: u32,
+    /// How the child exited
+    pub status: ExitStatus,
+    /// Resources the child used, including those of its own reaped
+    /// children
+    pub usage: ResourceUsage,
+}
+
+#[cfg(unix)]
+/// Waits for child `pid` to exit, or for any child if `pid` is `None`
+///
+/// The building block for process supervisors that want the resource usage
+/// of each child they reap, which `Child::wait()` throws away:
+///
+/// ```rust,no_run
+/// let reaped = cpu_time::wait4(None).unwrap();
+/// println!(
+///     "{} exited with {}, using {:?} of CPU and {} bytes of memory",
+///     reaped.pid,
+///     reaped.status,
+///     reaped.usage.cpu().total(),
+///     reaped.usage.max_rss,
+/// );
+/// ```
+///
+/// Retries when interrupted by a signal. Returns an `ECHILD` error if there
+/// is no such child, e.g. because it was already waited for. After this a
+/// `Child` for the same process must not be waited for or killed anymore,
+/// see `ChildExt` for a safe way to do both.
+pub fn wait4(pid: Option<u32>) -> io::Result<ReapedChild> {
+    wait(pid, 0).map(|reaped| reaped.expect("wait4() returned without a child"))
+}
+
+#[cfg(unix)]
+/// Reaps child `pid`, or any child if `pid` is `None`, if it has exited
+///
+/// Same as `wait4()`, but returns `None` without blocking if the child is
+/// still running (`WNOHANG`).
+pub fn try_wait4(pid: Option<u32>) -> io::Result<Option<ReapedChild>> {
+    wait(pid, WNOHANG)
+}
+
+#[cfg(unix)]
+fn wait(pid: Option<u32>, options: c_int) -> io::Result<Option<ReapedChild>> {
+    let pid = match pid {
+        // 0 and negative pids would select process groups
+        Some(pid) if pid == 0 || pid > i32::MAX as u32 => {
+            return Err(io::Error::from_raw_os_error(ECHILD))
+        }
+        Some(pid) => pid as pid_t,
+        None => -1,
+    };
+    let mut status: c_int = 0;
+    let mut usage: rusage = unsafe { mem::zeroed() };
+    loop {
+        match unsafe { libc::wait4(pid, &mut status, options, &mut usage) } {
+            -1 => {
+                let error = io::Error::last_os_error();
+                if error.kind() != ErrorKind::Interrupted {
+                    return Err(error);
+                }
+            }
+            0 => return Ok(None),
+            pid => {
+                return Ok(Some(ReapedChild {
+                    pid: pid as u32,
+                    status: ExitStatus::from_raw(status),
+                    usage: ResourceUsage::from(usage),
+                }))
+            }
+        }
+    }
+}
+
+/// CPU time of a `std::process::Child`
+///
+/// For command runners that report the CPU time of each invocation without
+/// going through `/usr/bin/time`:
+///
+/// ```rust,no_run
+/// use std::process::Command;
+/// use cpu_time::ChildExt;
+///
+/// let child = Command::new("cargo").arg("build").spawn().unwrap();
+/// let (status, split) = child.wait_with_cpu_time().unwrap();
+/// println!("{} after {:?} of CPU", status, split.total());
+/// ```
+pub trait ChildExt {
+    /// Waits for the child to exit, returning its exit status and the user
+    /// and system time it used
+    ///
+    /// Like `Child::wait_with_output()` this takes the child and closes its
+    /// stdin first, so that it doesn't wait for input forever.
+    ///
+    /// On unix the time comes from `wait4()` and includes the child's own
+    /// reaped children. The child must not have been waited for already,
+    /// e.g. with `try_wait()`, otherwise an `ECHILD` error is returned.
+    ///
+    /// On Windows it comes from `GetProcessTimes` on the child's handle
+    /// after it exited, without the time of the processes it started.
+    fn wait_with_cpu_time(self) -> io::Result<(ExitStatus, SplitTime)>;
+    /// Returns the user and system time the child used so far
+    ///
+    /// `Child` keeps its process handle open, so unlike on unix this still
+    /// works after `Child::wait()`, giving the final times of the child.
+    /// Only available on Windows.
+    #[cfg(windows)]
+    fn cpu_time(&self) -> io::Result<SplitTime>;
+}
+
+#[cfg(unix)]
+impl ChildExt for Child {
+    fn wait_with_cpu_time(mut self) -> io::Result<(ExitStatus, SplitTime)> {
+        drop(self.stdin.take());
+        wait4(Some(self.id())).map(|reaped| (reaped.status, reaped.usage.cpu()))
+    }
+}
+
+#[cfg(windows)]
+impl ChildExt for Child {
+    fn wait_with_cpu_time(mut self) -> io::Result<(ExitStatus, SplitTime)> {
+        drop(self.stdin.take());
+        let status = self.wait()?;
+        self.cpu_time().map(|split| (status, split))
+    }
+    fn cpu_time(&self) -> io::Result<SplitTime> {
+        process_split(self.as_raw_handle() as _).map_err(|error| match error {
+            CpuTimeError::Os(code) => io::Error::from_raw_os_error(code),
+            error => io::Error::other(error),
+        })
+    }
+}
+
+/// A child of a `ChildSet` that exited and was reaped
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct FinishedChild {
+    /// Process id of the child
+    pub pid: u32,
+    /// How the child exited
+    pub status: ExitStatus,
+    /// User and system time the child used, on unix including its own
+    /// reaped children
+    pub cpu: SplitTime,
+}
+
+/// A set of running children, reaped as they exit
+///
+/// For parallel test runners and build orchestrators that start several
+/// processes and want their combined CPU time:
+///
+/// ```rust,no_run
+/// use std::process::Command;
+/// use cpu_time::ChildSet;
+///
+/// let mut children = ChildSet::new();
+/// for crate_dir in &["core", "cli", "web"] {
+///     children
+///         .spawn(Command::new("cargo").arg("test").current_dir(crate_dir))
+///         .unwrap();
+/// }
+/// while let Some(child) = children.reap().unwrap() {
+///     println!("{} finished: {}, {:?}", child.pid, child.status, child.cpu.total());
+/// }
+/// println!("together {:?} of CPU", children.total().total());
+/// ```
+///
+/// Only the children in the set are reaped, other children of the process
+/// are left alone. That's why waiting polls them, every 10 ms at most.
+#[derive(Debug, Default)]
+pub struct ChildSet {
+    running: Vec<Child>,
+    finished: Vec<FinishedChild>,
+}
+
+impl ChildSet {
+    /// Returns an empty set
+    pub fn new() -> ChildSet {
+        ChildSet::default()
+    }
+    /// Adds a spawned child, closing its stdin like `Child::wait()`
+    ///
+    /// The child must not have been waited for yet.
+    pub fn push(&mut self, mut child: Child) {
+        drop(child.stdin.take());
+        self.running.push(child);
+    }
+    /// Spawns `command` and adds the child, returning its process id
+    pub fn spawn(&mut self, command: &mut Command) -> io::Result<u32> {
+        let child = command.spawn()?;
+        let pid = child.id();
+        self.push(child);
+        Ok(pid)
+    }
+    /// Returns the number of children that haven't been reaped yet
+    pub fn running(&self) -> usize {
+        self.running.len()
+    }
+    /// Reaps a child that exited, if any, without blocking
+    pub fn try_reap(&mut self) -> io::Result<Option<FinishedChild>> {
+        for index in 0..self.running.len() {
+            if let Some(child) = try_reap(&mut self.running[index])? {
+                // Already reaped
+                #[allow(clippy::zombie_processes)]
+                self.running.swap_remove(index);
+                self.finished.push(child);
+                return Ok(Some(child));
+            }
+        }
+        Ok(None)
+    }
+    /// Waits for the next child to exit and reaps it
+    ///
+    /// Returns `None` once all children have been reaped.
+    pub fn reap(&mut self) -> io::Result<Option<FinishedChild>> {
+        let mut delay = Duration::from_millis(1);
+        while !self.running.is_empty() {
+            if let Some(child) = self.try_reap()? {
+                return Ok(Some(child));
+            }
+            thread::sleep(delay);
+            delay = (delay * 2).min(Duration::from_millis(10));
+        }
+        Ok(None)
+    }
+    /// Waits for all children to exit and returns their combined CPU time
+    pub fn wait_all(&mut self) -> io::Result<SplitTime> {
+        while self.reap()?.is_some() {}
+        Ok(self.total())
+    }
+    /// Returns the reaped children, in the order they were reaped
+    pub fn finished(&self) -> &[FinishedChild] {
+        &self.finished
+    }
+    /// Returns the CPU time of the reaped children added up
+    pub fn total(&self) -> SplitTime {
+        self.finished
+            .iter()
+            .fold(SplitTime::default(), |total, child| {
+                total.saturating_add(child.cpu)
+            })
+    }
+}
+
+#[cfg(unix)]
+fn try_reap(child: &mut Child) -> io::Result<Option<FinishedChild>> {
+    Ok(try_wait4(Some(child.id()))?.map(|reaped| FinishedChild {
+        pid: reaped.pid,
+        status: reaped.status,
+        cpu: reaped.usage.cpu(),
+    }))
+}
+
+#[cfg(windows)]
+fn try_reap(child: &mut Child) -> io::Result<Option<FinishedChild>> {
+    match child.try_wait()? {
+        Some(status) => Ok(Some(FinishedChild {
+            pid: child.id(),
+            status,
+            cpu: child.cpu_time()?,
+        })),
+        None => Ok(None),
+    }
+}
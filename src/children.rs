@@ -0,0 +1,106 @@
+use std::time::Duration;
+
+use rusage;
+use CpuTimeError;
+use SplitTime;
+
+/// CPU Time Used by The Children of The Process
+///
+/// This is an opaque type similar to `std::time::Instant`, read with
+/// `getrusage(RUSAGE_CHILDREN)`. It only counts children that have
+/// terminated *and* were waited for (e.g. with `Child::wait()`), including
+/// their own waited-for descendants. Running children aren't included, so
+/// take the second timestamp after reaping them:
+///
+/// ```rust,no_run
+/// use std::process::Command;
+/// use cpu_time::ChildrenTime;
+///
+/// let start = ChildrenTime::now();
+/// Command::new("cargo").arg("build").status().unwrap();
+/// println!("cargo used {:?} of CPU", start.elapsed());
+/// ```
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+pub struct ChildrenTime(Duration);
+
+impl ChildrenTime {
+    /// Zero CPU time, e.g. to initialize statics without reading the clock
+    pub const ZERO: ChildrenTime = ChildrenTime::from_duration(Duration::from_secs(0));
+
+    /// Timestamp of `duration` of CPU time used by children
+    ///
+    /// Meant for tests of code that stores and compares timestamps.
+    pub const fn from_duration(duration: Duration) -> ChildrenTime {
+        ChildrenTime(duration)
+    }
+    /// Get current CPU time used by the reaped children of the process
+    ///
+    /// # Panics
+    ///
+    /// If `getrusage()` fails. Use `try_now()` to handle this case.
+    pub fn now() -> ChildrenTime {
+        match ChildrenTime::try_now() {
+            Ok(time) => time,
+            Err(_) => panic!("Children CPU time is not supported"),
+        }
+    }
+    /// Get current CPU time used by the reaped children of the process
+    pub fn try_now() -> Result<ChildrenTime, CpuTimeError> {
+        ChildrenTime::try_now_split().map(|split| ChildrenTime(split.total()))
+    }
+    /// Get current CPU time used by the reaped children of the process,
+    /// split into user and system time
+    ///
+    /// # Panics
+    ///
+    /// If `getrusage()` fails. Use `try_now_split()` to handle this case.
+    pub fn now_split() -> SplitTime {
+        match ChildrenTime::try_now_split() {
+            Ok(split) => split,
+            Err(_) => panic!("Children CPU time is not supported"),
+        }
+    }
+    /// Get current CPU time used by the reaped children of the process,
+    /// split into user and system time
+    pub fn try_now_split() -> Result<SplitTime, CpuTimeError> {
+        rusage::children_split()
+            .map(|(user, system)| SplitTime { user, system })
+            .ok_or(CpuTimeError::Unsupported)
+    }
+    /// Returns the amount of CPU time used by children reaped from the
+    /// previous timestamp to now.
+    ///
+    /// A timestamp later than now gives zero.
+    pub fn elapsed(&self) -> Duration {
+        ChildrenTime::now().saturating_duration_since(*self)
+    }
+    /// Returns the amount of CPU time used by children reaped from the
+    /// previous timestamp to now.
+    ///
+    /// Unlike `elapsed()` this returns an error if the time can't be read.
+    /// It never panics, a timestamp later than now gives zero.
+    pub fn try_elapsed(&self) -> Result<Duration, CpuTimeError> {
+        ChildrenTime::try_now().map(|now| now.saturating_duration_since(*self))
+    }
+    /// Returns the amount of CPU time used by children reaped from the
+    /// previous timestamp.
+    pub fn duration_since(&self, timestamp: ChildrenTime) -> Duration {
+        self.0 - timestamp.0
+    }
+    /// Returns the amount of CPU time used by children reaped from the
+    /// previous timestamp, or zero if that timestamp is later than this one.
+    pub fn saturating_duration_since(&self, timestamp: ChildrenTime) -> Duration {
+        self.checked_duration_since(timestamp)
+            .unwrap_or_else(|| Duration::new(0, 0))
+    }
+    /// Returns the amount of CPU time used by children reaped from the
+    /// previous timestamp, or `None` if that timestamp is later than this
+    /// one.
+    pub fn checked_duration_since(&self, timestamp: ChildrenTime) -> Option<Duration> {
+        self.0.checked_sub(timestamp.0)
+    }
+    /// Returns the amount of CPU time used by all reaped children.
+    pub fn duration(&self) -> Duration {
+        self.0
+    }
+}
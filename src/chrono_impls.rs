@@ -0,0 +1,32 @@
+use std::convert::TryFrom;
+use std::fmt;
+
+use chrono::{Duration, OutOfRangeError};
+
+use CpuDuration;
+
+impl TryFrom<CpuDuration> for Duration {
+    type Error = OutOfRangeError;
+    /// Fails for durations above `chrono::Duration::MAX`, ~292 million years
+    fn try_from(duration: CpuDuration) -> Result<Duration, OutOfRangeError> {
+        Duration::from_std(duration.0)
+    }
+}
+
+impl TryFrom<Duration> for CpuDuration {
+    type Error = OutOfRangeError;
+    /// Fails for negative durations
+    fn try_from(duration: Duration) -> Result<CpuDuration, OutOfRangeError> {
+        duration.to_std().map(CpuDuration)
+    }
+}
+
+impl CpuDuration {
+    /// Formats the duration as ISO 8601, e.g. `PT1.234S`, like
+    /// `chrono::Duration` does
+    ///
+    /// Durations `chrono` can't represent are shown as its maximum.
+    pub fn iso8601(&self) -> impl fmt::Display {
+        Duration::from_std(self.0).unwrap_or(Duration::MAX)
+    }
+}
@@ -0,0 +1,47 @@
+use std::time::Duration;
+
+use {CpuTimeError, ProcessTime, ThreadTime};
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+impl sealed::Sealed for ProcessTime {}
+impl sealed::Sealed for ThreadTime {}
+
+/// The CPU-time clocks, `ProcessTime` and `ThreadTime`
+///
+/// Lets `CpuStopwatch` and friends work with either clock. Implemented only
+/// by the two timestamp types of this crate.
+pub trait CpuClock: Copy + sealed::Sealed {
+    /// Same as `ProcessTime::now()` or `ThreadTime::now()`
+    fn now() -> Self;
+    /// Same as `ProcessTime::try_now()` or `ThreadTime::try_now()`
+    fn try_now() -> Result<Self, CpuTimeError>;
+    /// Same as `saturating_duration_since()` of the timestamp
+    fn saturating_duration_since(&self, earlier: Self) -> Duration;
+}
+
+impl CpuClock for ProcessTime {
+    fn now() -> ProcessTime {
+        ProcessTime::now()
+    }
+    fn try_now() -> Result<ProcessTime, CpuTimeError> {
+        ProcessTime::try_now()
+    }
+    fn saturating_duration_since(&self, earlier: ProcessTime) -> Duration {
+        ProcessTime::saturating_duration_since(self, earlier)
+    }
+}
+
+impl CpuClock for ThreadTime {
+    fn now() -> ThreadTime {
+        ThreadTime::now()
+    }
+    fn try_now() -> Result<ThreadTime, CpuTimeError> {
+        ThreadTime::try_now()
+    }
+    fn saturating_duration_since(&self, earlier: ThreadTime) -> Duration {
+        ThreadTime::saturating_duration_since(self, earlier)
+    }
+}
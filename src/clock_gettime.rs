@@ -1,16 +1,78 @@
 use std::marker::PhantomData;
-use std::rc::Rc;
+#[cfg(feature = "libc")]
+use std::mem::size_of_val;
+#[cfg(all(
+    feature = "libc",
+    feature = "std",
+    any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "dragonfly"
+    )
+))]
+use std::os::unix::thread::JoinHandleExt;
+#[cfg(target_os = "nto")]
+use std::ptr;
+#[cfg(all(
+    feature = "libc",
+    feature = "std",
+    any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "dragonfly"
+    )
+))]
+use std::thread::JoinHandle;
 use std::time::Duration;
 
-use libc::{clock_gettime, timespec};
-use libc::{CLOCK_PROCESS_CPUTIME_ID, CLOCK_THREAD_CPUTIME_ID};
+#[cfg(all(
+    feature = "libc",
+    any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "dragonfly"
+    )
+))]
+use cpuclock;
+#[cfg(target_os = "haiku")]
+use haiku;
+#[cfg(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "tvos",
+    target_os = "watchos"
+))]
+use mach;
+#[cfg(all(feature = "libc", not(any(target_os = "haiku", target_os = "vxworks"))))]
+use rusage;
+
+use self::clocks::{process_clock, thread_clock};
+use nanos::to_nanos;
+#[cfg(all(
+    feature = "libc",
+    feature = "std",
+    any(target_os = "linux", target_os = "android")
+))]
+use procfs;
+use CpuTimeError;
+#[cfg(all(target_os = "linux", feature = "libc"))]
+use ProcessHandle;
+use Rounding;
+use SplitTime;
 
 /// CPU Time Used by The Whole Process
 ///
 /// This is an opaque type similar to `std::time::Instant`.
 /// Use `elapsed()` or `duration_since()` to get meaningful time deltas.
-#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
-pub struct ProcessTime(Duration);
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+pub struct ProcessTime(
+    Duration,
+    // pid of the measured process, 0 for the current one
+    u32,
+);
 
 /// CPU Time Used by The Current Thread
 ///
@@ -20,68 +82,917 @@ pub struct ProcessTime(Duration);
 /// This type is non-thread-shareable (!Sync, !Send) because otherwise it's
 /// to easy to mess up times from different threads. However, you can freely
 /// send Duration's returned by `elapsed()` and `duration_since()`.
-#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
 pub struct ThreadTime(
     Duration,
     // makes type non-sync and non-send
-    PhantomData<Rc<()>>,
+    PhantomData<*const ()>,
 );
 
+#[cfg(not(any(
+    all(
+        feature = "raw-syscalls",
+        target_os = "linux",
+        any(
+            target_arch = "x86_64",
+            target_arch = "aarch64",
+            target_arch = "riscv64"
+        )
+    ),
+    all(
+        feature = "rustix",
+        not(any(target_os = "netbsd", target_os = "illumos", target_os = "solaris"))
+    )
+)))]
+mod clocks {
+    use std::time::Duration;
+
+    #[cfg(not(target_os = "macos"))]
+    use libc::clock_gettime;
+    use libc::{clockid_t, timespec};
+
+    use super::{process_clock_id, thread_clock_id, timespec_duration};
+
+    // `clock_gettime` only exists since macOS 10.12, it's looked up at
+    // runtime so that binaries built for older deployment targets still load
+    #[cfg(target_os = "macos")]
+    unsafe fn clock_gettime(clock: clockid_t, time: *mut timespec) -> libc::c_int {
+        use std::mem;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        // 0 is not looked up yet, 1 is missing
+        static CLOCK_GETTIME: AtomicUsize = AtomicUsize::new(0);
+
+        let mut addr = CLOCK_GETTIME.load(Ordering::Relaxed);
+        if addr == 0 {
+            let name = b"clock_gettime\0";
+            addr = libc::dlsym(libc::RTLD_DEFAULT, name.as_ptr() as *const _) as usize;
+            if addr == 0 {
+                addr = 1;
+            }
+            CLOCK_GETTIME.store(addr, Ordering::Relaxed);
+        }
+        if addr == 1 {
+            return -1;
+        }
+        let clock_gettime: unsafe extern "C" fn(clockid_t, *mut timespec) -> libc::c_int =
+            mem::transmute(addr);
+        clock_gettime(clock, time)
+    }
+
+    fn libc_clock_time(clock: clockid_t) -> Option<Duration> {
+        let mut time = timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        if unsafe { clock_gettime(clock, &mut time) } == -1 {
+            return None;
+        }
+        Some(timespec_duration(time))
+    }
+
+    // 32-bit Linux libcs may still use a 32-bit `timespec`, so the 64-bit
+    // `clock_gettime64` system call (Linux 5.1+) is tried first
+    #[cfg(all(target_os = "linux", any(target_arch = "x86", target_arch = "arm")))]
+    fn clock_time(clock: clockid_t) -> Option<Duration> {
+        const SYS_CLOCK_GETTIME64: libc::c_long = 403;
+
+        // `struct __kernel_timespec`
+        #[repr(C)]
+        struct Timespec64 {
+            tv_sec: i64,
+            tv_nsec: i64,
+        }
+
+        let mut time = Timespec64 {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        let ret = unsafe { libc::syscall(SYS_CLOCK_GETTIME64, clock, &mut time) };
+        if ret == -1 {
+            return libc_clock_time(clock);
+        }
+        Some(Duration::new(time.tv_sec as u64, time.tv_nsec as u32))
+    }
+
+    #[cfg(not(all(target_os = "linux", any(target_arch = "x86", target_arch = "arm"))))]
+    fn clock_time(clock: clockid_t) -> Option<Duration> {
+        libc_clock_time(clock)
+    }
+
+    pub fn process_clock() -> Option<Duration> {
+        clock_time(process_clock_id())
+    }
+
+    pub fn thread_clock() -> Option<Duration> {
+        clock_time(thread_clock_id())
+    }
+}
+
+// A 32-bit `time_t` goes negative after 2^31 seconds, but CPU time only
+// grows from zero, so the seconds are read as unsigned there
+#[cfg(feature = "libc")]
+fn timespec_duration(time: libc::timespec) -> Duration {
+    let seconds = if size_of_val(&time.tv_sec) == 4 {
+        time.tv_sec as u32 as u64
+    } else {
+        time.tv_sec as u64
+    };
+    Duration::new(seconds, time.tv_nsec as u32)
+}
+
+#[cfg(feature = "libc")]
+fn process_clock_id() -> libc::clockid_t {
+    libc::CLOCK_PROCESS_CPUTIME_ID
+}
+
+#[cfg(feature = "libc")]
+fn thread_clock_id() -> libc::clockid_t {
+    libc::CLOCK_THREAD_CPUTIME_ID
+}
+
+// rustix doesn't expose CPU-time clocks on NetBSD, illumos and Solaris, so
+// those keep using libc even with the feature enabled
+#[cfg(all(
+    feature = "rustix",
+    not(any(target_os = "netbsd", target_os = "illumos", target_os = "solaris")),
+    not(all(
+        feature = "raw-syscalls",
+        target_os = "linux",
+        any(
+            target_arch = "x86_64",
+            target_arch = "aarch64",
+            target_arch = "riscv64"
+        )
+    ))
+))]
+mod clocks {
+    use std::time::Duration;
+
+    use rustix::time::{clock_gettime_dynamic, ClockId, DynamicClockId};
+
+    fn clock_time(clock: ClockId) -> Option<Duration> {
+        match clock_gettime_dynamic(DynamicClockId::Known(clock)) {
+            Ok(time) => Some(Duration::new(time.tv_sec as u64, time.tv_nsec as u32)),
+            Err(_) => None,
+        }
+    }
+
+    pub fn process_clock() -> Option<Duration> {
+        clock_time(ClockId::ProcessCPUTime)
+    }
+
+    pub fn thread_clock() -> Option<Duration> {
+        clock_time(ClockId::ThreadCPUTime)
+    }
+}
+
+#[cfg(all(
+    feature = "raw-syscalls",
+    target_os = "linux",
+    any(
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        target_arch = "riscv64"
+    )
+))]
+mod clocks {
+    use std::time::Duration;
+
+    use syscall::{clock_gettime, CLOCK_PROCESS_CPUTIME_ID, CLOCK_THREAD_CPUTIME_ID};
+
+    pub fn process_clock() -> Option<Duration> {
+        clock_gettime(CLOCK_PROCESS_CPUTIME_ID)
+    }
+
+    pub fn thread_clock() -> Option<Duration> {
+        clock_gettime(CLOCK_THREAD_CPUTIME_ID)
+    }
+}
+
+// Another process' CPU time, by pid
+#[cfg(all(
+    feature = "libc",
+    feature = "std",
+    any(target_os = "linux", target_os = "android")
+))]
+fn process_time_for(pid: u32) -> Result<Duration, CpuTimeError> {
+    match cpuclock::process_time(pid) {
+        Err(CpuTimeError::ProcessGone) => Err(CpuTimeError::ProcessGone),
+        Err(_) => procfs::Stat::read(pid).map(|stat| stat.cpu_time()),
+        time => time,
+    }
+}
+
+#[cfg(all(feature = "libc", any(target_os = "freebsd", target_os = "dragonfly")))]
+fn process_time_for(pid: u32) -> Result<Duration, CpuTimeError> {
+    cpuclock::process_time(pid)
+}
+
+#[cfg(all(
+    feature = "libc",
+    not(feature = "std"),
+    any(target_os = "linux", target_os = "android")
+))]
+fn process_time_for(pid: u32) -> Result<Duration, CpuTimeError> {
+    cpuclock::process_time(pid)
+}
+
+#[cfg(target_os = "macos")]
+fn process_time_for(pid: u32) -> Result<Duration, CpuTimeError> {
+    mach::process_for(pid)
+}
+
+#[cfg(not(any(
+    target_os = "macos",
+    all(
+        feature = "libc",
+        any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "freebsd",
+            target_os = "dragonfly"
+        )
+    )
+)))]
+fn process_time_for(_pid: u32) -> Result<Duration, CpuTimeError> {
+    Err(CpuTimeError::Unsupported)
+}
+
+#[cfg(target_os = "haiku")]
+fn process_fallback() -> Option<Duration> {
+    haiku::process()
+}
+
+// VxWorks only offers the POSIX clocks, there's no `getrusage`
+#[cfg(any(target_os = "vxworks", not(feature = "libc")))]
+fn process_fallback() -> Option<Duration> {
+    None
+}
+
+#[cfg(all(feature = "libc", not(any(target_os = "haiku", target_os = "vxworks"))))]
+fn process_fallback() -> Option<Duration> {
+    rusage::process()
+}
+
+#[cfg(target_os = "haiku")]
+fn process_split() -> Option<(Duration, Duration)> {
+    haiku::process_split()
+}
+
+#[cfg(any(target_os = "vxworks", not(feature = "libc")))]
+fn process_split() -> Option<(Duration, Duration)> {
+    None
+}
+
+#[cfg(all(feature = "libc", not(any(target_os = "haiku", target_os = "vxworks"))))]
+fn process_split() -> Option<(Duration, Duration)> {
+    rusage::process_split()
+}
+
+#[cfg(target_os = "haiku")]
+fn thread_split() -> Option<(Duration, Duration)> {
+    haiku::thread_split()
+}
+
+#[cfg(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "tvos",
+    target_os = "watchos"
+))]
+fn thread_split() -> Option<(Duration, Duration)> {
+    mach::thread_split()
+}
+
+#[cfg(all(
+    feature = "libc",
+    any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "openbsd",
+        target_os = "aix"
+    )
+))]
+fn thread_split() -> Option<(Duration, Duration)> {
+    rusage::thread_split()
+}
+
+#[cfg(not(any(
+    target_os = "haiku",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "tvos",
+    target_os = "watchos",
+    all(
+        feature = "libc",
+        any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "freebsd",
+            target_os = "openbsd",
+            target_os = "aix"
+        )
+    )
+)))]
+fn thread_split() -> Option<(Duration, Duration)> {
+    None
+}
+
+#[cfg(any(
+    target_os = "openbsd",
+    target_os = "aix",
+    all(target_os = "android", feature = "libc")
+))]
+fn thread_fallback() -> Option<Duration> {
+    rusage::thread()
+}
+
+// illumos and Solaris keep microstate accounting for every LWP, which is
+// what `gethrvtime()` reports
+#[cfg(any(target_os = "illumos", target_os = "solaris"))]
+fn thread_fallback() -> Option<Duration> {
+    extern "C" {
+        fn gethrvtime() -> libc::c_longlong;
+    }
+    let nanos = unsafe { gethrvtime() } as u64;
+    Some(Duration::new(
+        nanos / 1_000_000_000,
+        (nanos % 1_000_000_000) as u32,
+    ))
+}
+
+// QNX Neutrino exposes a CPU clock for every thread through its kernel calls
+#[cfg(target_os = "nto")]
+fn thread_fallback() -> Option<Duration> {
+    let thread = unsafe { libc::pthread_self() } as libc::c_int;
+    let clock = unsafe { libc::ClockId(0, thread) };
+    if clock == -1 {
+        return None;
+    }
+    let mut nanos = 0;
+    if unsafe { libc::ClockTime(clock, ptr::null(), &mut nanos) } == -1 {
+        return None;
+    }
+    Some(Duration::new(
+        nanos / 1_000_000_000,
+        (nanos % 1_000_000_000) as u32,
+    ))
+}
+
+#[cfg(target_os = "haiku")]
+fn thread_fallback() -> Option<Duration> {
+    haiku::thread()
+}
+
+// For macOS before 10.12, which has no `clock_gettime`
+#[cfg(target_os = "macos")]
+fn thread_fallback() -> Option<Duration> {
+    mach::thread()
+}
+
+#[cfg(not(any(
+    target_os = "openbsd",
+    target_os = "aix",
+    all(target_os = "android", feature = "libc"),
+    target_os = "illumos",
+    target_os = "solaris",
+    target_os = "haiku",
+    target_os = "nto",
+    target_os = "macos"
+)))]
+fn thread_fallback() -> Option<Duration> {
+    None
+}
+
+// Sandboxed apps on the mobile Apple platforms are only guaranteed access
+// to the Mach calls, so they are tried first there
+#[cfg(any(target_os = "ios", target_os = "tvos", target_os = "watchos"))]
+fn process_time() -> Option<Duration> {
+    mach::process()
+        .or_else(process_clock)
+        .or_else(process_fallback)
+}
+
+#[cfg(any(target_os = "ios", target_os = "tvos", target_os = "watchos"))]
+fn thread_time() -> Option<Duration> {
+    mach::thread()
+        .or_else(thread_clock)
+        .or_else(thread_fallback)
+}
+
+#[cfg(not(any(target_os = "ios", target_os = "tvos", target_os = "watchos")))]
+fn process_time() -> Option<Duration> {
+    process_clock().or_else(process_fallback)
+}
+
+// Some kernels and containers account `RUSAGE_THREAD` better than the thread
+// clock, the `rusage-thread` feature prefers it
+#[cfg(all(target_os = "linux", feature = "rusage-thread"))]
+fn thread_time() -> Option<Duration> {
+    rusage::thread()
+        .or_else(thread_clock)
+        .or_else(thread_fallback)
+}
+
+#[cfg(not(any(
+    target_os = "ios",
+    target_os = "tvos",
+    target_os = "watchos",
+    all(target_os = "linux", feature = "rusage-thread")
+)))]
+fn thread_time() -> Option<Duration> {
+    thread_clock().or_else(thread_fallback)
+}
+
 impl ProcessTime {
+    /// Zero CPU time, e.g. to initialize statics without reading the clock
+    pub const ZERO: ProcessTime = ProcessTime::from_nanos(0);
+    /// Resolution of `as_raw_ticks()`
+    pub const TICKS_PER_SEC: u64 = 1_000_000_000;
+
+    /// Timestamp of `secs` seconds of CPU time used
+    pub const fn from_secs(secs: u64) -> ProcessTime {
+        ProcessTime::from_duration(Duration::from_secs(secs))
+    }
+    /// Timestamp of `duration` of CPU time used
+    ///
+    /// Meant for tests of code that stores and compares timestamps.
+    /// The timestamp belongs to the current process.
+    pub const fn from_duration(duration: Duration) -> ProcessTime {
+        ProcessTime(duration, 0)
+    }
+    /// Timestamp of `nanos` nanoseconds of CPU time used
+    pub const fn from_nanos(nanos: u64) -> ProcessTime {
+        ProcessTime::from_duration(Duration::from_nanos(nanos))
+    }
     /// Get current CPU time used by a process process
     ///
     /// # Panics
     ///
-    /// This method panics if linux kernel doesn't support
-    /// CLOCK_PROCESS_CPUTIME_ID, which works since linux 2.6.12 (~ year 2005).
+    /// This method panics if neither CLOCK_PROCESS_CPUTIME_ID (works since
+    /// linux 2.6.12, ~ year 2005) nor `getrusage(RUSAGE_SELF)` work. On Haiku
+    /// `get_team_usage_info()` is used instead of `getrusage`. On iOS, tvOS
+    /// and watchOS `task_info()` is tried first.
+    ///
+    /// Use `try_now()` to handle this case without a panic.
     pub fn now() -> ProcessTime {
-        let mut time = timespec {
-            tv_sec: 0,
-            tv_nsec: 0,
-        };
-        if unsafe { clock_gettime(CLOCK_PROCESS_CPUTIME_ID, &mut time) } == -1 {
-            panic!("Process CPU time is not supported");
+        match ProcessTime::try_now() {
+            Ok(time) => time,
+            Err(_) => panic!("Process CPU time is not supported"),
+        }
+    }
+    /// Get current CPU time used by the current process
+    ///
+    /// Returns `CpuTimeError::Unsupported` instead of panicking if none of
+    /// the clocks `now()` tries work, e.g. when a sandbox denies them.
+    pub fn try_now() -> Result<ProcessTime, CpuTimeError> {
+        process_time()
+            .map(|duration| ProcessTime(duration, 0))
+            .ok_or(CpuTimeError::Unsupported)
+    }
+    /// Get current CPU time used by a given process
+    ///
+    /// Reads the clock of `clock_getcpuclockid()`, on Linux and Android
+    /// `/proc/<pid>/stat` is the fallback. On macOS `proc_pid_rusage()` is
+    /// used, which needs the same user as the target process or root.
+    ///
+    /// Returns `CpuTimeError::ProcessGone` if there is no process with this
+    /// id and `CpuTimeError::Unsupported` on other platforms or without the
+    /// `libc` feature. `elapsed()` of the result measures the same process.
+    pub fn now_for(pid: u32) -> Result<ProcessTime, CpuTimeError> {
+        process_time_for(pid).map(|duration| ProcessTime(duration, pid))
+    }
+    /// Same as `now_for()`
+    pub fn try_now_for(pid: u32) -> Result<ProcessTime, CpuTimeError> {
+        ProcessTime::now_for(pid)
+    }
+    /// Get current CPU time used by an attached process
+    ///
+    /// Like `now_for(handle.pid())`, but returns
+    /// `CpuTimeError::ProcessGone` if the process was reaped, even if its
+    /// pid now belongs to another process. `elapsed()` of the result only
+    /// knows the pid, take another reading with this method instead.
+    #[cfg(all(target_os = "linux", feature = "libc"))]
+    pub fn now_for_handle(handle: &ProcessHandle) -> Result<ProcessTime, CpuTimeError> {
+        let time = ProcessTime::now_for(handle.pid())?;
+        // Still alive after the reading, so the pid wasn't reused before it
+        if !handle.is_alive() {
+            return Err(CpuTimeError::ProcessGone);
         }
-        ProcessTime(Duration::new(time.tv_sec as u64, time.tv_nsec as u32))
+        Ok(time)
+    }
+    /// Get current CPU time used by the current process, split into user
+    /// and system time
+    ///
+    /// This reads `getrusage(RUSAGE_SELF)` (`get_team_usage_info()` on
+    /// Haiku), so its resolution may be coarser than `now()`, and it isn't
+    /// available on VxWorks or without the `libc` feature.
+    pub fn try_now_split() -> Result<SplitTime, CpuTimeError> {
+        process_split()
+            .map(|(user, system)| SplitTime { user, system })
+            .ok_or(CpuTimeError::Unsupported)
+    }
+    /// Get current CPU time used by the current process, split into user
+    /// and system time
+    ///
+    /// # Panics
+    ///
+    /// If the split can't be read. Use `try_now_split()` to handle this
+    /// case.
+    pub fn now_split() -> SplitTime {
+        match ProcessTime::try_now_split() {
+            Ok(split) => split,
+            Err(_) => panic!("Process CPU time split is not supported"),
+        }
+    }
+    /// Returns `true` if process CPU time can be measured in the current
+    /// environment, i.e. if `now()` won't panic
+    ///
+    /// This actually reads the clock, as some containers and VMs reject
+    /// CPU-time clocks at runtime.
+    pub fn is_supported() -> bool {
+        process_time().is_some()
     }
     /// Returns the amount of CPU time used from the previous timestamp to now.
+    ///
+    /// A timestamp later than now gives zero.
     pub fn elapsed(&self) -> Duration {
-        ProcessTime::now().duration_since(*self)
+        match self.try_elapsed() {
+            Ok(duration) => duration,
+            Err(error) => panic!("can't read the process CPU time: {}", error),
+        }
+    }
+    /// Returns the amount of CPU time used from the previous timestamp to now.
+    ///
+    /// Unlike `elapsed()` this returns an error if the clock can't be read.
+    /// It never panics, a timestamp later than now gives zero.
+    pub fn try_elapsed(&self) -> Result<Duration, CpuTimeError> {
+        let now = match self.1 {
+            0 => ProcessTime::try_now()?,
+            pid => ProcessTime::now_for(pid)?,
+        };
+        Ok(now.saturating_duration_since(*self))
+    }
+    /// Runs `f` and returns its result with the CPU time the process used
+    /// meanwhile
+    ///
+    /// Saves the two timestamps around a single call:
+    ///
+    /// ```rust
+    /// use cpu_time::ProcessTime;
+    ///
+    /// let (sum, time) = ProcessTime::measure(|| (0..1000u64).sum::<u64>());
+    /// println!("{} took {:?}", sum, time);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If the CPU time can't be read, like `now()`.
+    pub fn measure<R, F: FnOnce() -> R>(f: F) -> (R, Duration) {
+        let start = ProcessTime::now();
+        let result = f();
+        (result, start.elapsed())
     }
     /// Returns the amount of CPU time used from the previous timestamp.
     pub fn duration_since(&self, timestamp: ProcessTime) -> Duration {
         self.0 - timestamp.0
     }
+    /// Timestamp of `ticks` of CPU time used, in the units of the OS counter
+    /// (see `TICKS_PER_SEC`)
+    pub const fn from_raw_ticks(ticks: u64) -> ProcessTime {
+        ProcessTime::from_nanos(ticks)
+    }
+    /// Returns the amount of CPU time used in the units of the OS counter,
+    /// rounded as requested
+    pub fn as_raw_ticks_rounded(&self, rounding: Rounding) -> u64 {
+        rounding.ticks(self.0, ProcessTime::TICKS_PER_SEC)
+    }
+    /// Returns the amount of CPU time used from the previous timestamp to now
+    /// as seconds.
+    pub fn elapsed_secs_f64(&self) -> f64 {
+        self.elapsed().as_secs_f64()
+    }
+    /// Returns the amount of CPU time used from the previous timestamp to now
+    /// as seconds.
+    pub fn elapsed_secs_f32(&self) -> f32 {
+        self.elapsed().as_secs_f32()
+    }
+    /// Returns the timestamp `duration` of CPU time later than this one, or
+    /// `None` if it can't be represented.
+    pub fn checked_add(&self, duration: Duration) -> Option<ProcessTime> {
+        self.0
+            .checked_add(duration)
+            .map(|duration| ProcessTime(duration, self.1))
+    }
+    /// Returns the timestamp `duration` of CPU time earlier than this one, or
+    /// `None` if that would be before the process started.
+    pub fn checked_sub(&self, duration: Duration) -> Option<ProcessTime> {
+        self.0
+            .checked_sub(duration)
+            .map(|duration| ProcessTime(duration, self.1))
+    }
+    /// Returns the amount of CPU time used from the previous timestamp, or
+    /// zero if that timestamp is later than this one.
+    pub fn saturating_duration_since(&self, timestamp: ProcessTime) -> Duration {
+        self.checked_duration_since(timestamp)
+            .unwrap_or_else(|| Duration::new(0, 0))
+    }
+    /// Returns the amount of CPU time used from the previous timestamp, or
+    /// `None` if that timestamp is later than this one.
+    pub fn checked_duration_since(&self, timestamp: ProcessTime) -> Option<Duration> {
+        self.0.checked_sub(timestamp.0)
+    }
+    /// Returns the amount of CPU time used.
+    pub fn duration(&self) -> Duration {
+        self.0
+    }
+    /// Returns the amount of CPU time used in nanoseconds
+    ///
+    /// Saturates at `u64::MAX`, which is ~584 years.
+    pub fn as_nanos_u64(&self) -> u64 {
+        to_nanos(self.0)
+    }
+    /// Returns the amount of CPU time used in the units of the OS
+    /// counter, see `TICKS_PER_SEC`
+    pub fn as_raw_ticks(&self) -> u64 {
+        self.as_nanos_u64()
+    }
+    /// Timestamp of the CPU time in `time`, e.g. as read from `clock_id()`
+    /// with `clock_gettime()`
+    ///
+    /// A 32-bit `time_t` is read as unsigned, so the seconds don't wrap
+    /// after 2^31 (68 years). Requires the `libc` feature.
+    #[cfg(feature = "libc")]
+    pub fn from_timespec(time: libc::timespec) -> ProcessTime {
+        ProcessTime(timespec_duration(time), 0)
+    }
+    /// Returns the id of the POSIX clock read by `now()`, e.g. to pass it to
+    /// `timer_create()`
+    ///
+    /// On platforms where the clock doesn't work `now()` uses a fallback
+    /// instead, check `is_supported()` first. Requires the `libc` feature.
+    #[cfg(feature = "libc")]
+    pub fn clock_id() -> libc::clockid_t {
+        process_clock_id()
+    }
+    /// Returns `true` if no CPU time was used, e.g. for `ZERO`
+    pub fn is_zero(&self) -> bool {
+        self.0 == Duration::from_secs(0)
+    }
 }
 
 impl ThreadTime {
-    /// Get current CPU time used by a process process
+    /// Zero CPU time, e.g. to initialize statics without reading the clock
+    pub const ZERO: ThreadTime = ThreadTime::from_nanos(0);
+    /// Resolution of `as_raw_ticks()`
+    pub const TICKS_PER_SEC: u64 = 1_000_000_000;
+
+    /// Timestamp of `secs` seconds of CPU time used
+    pub const fn from_secs(secs: u64) -> ThreadTime {
+        ThreadTime::from_duration(Duration::from_secs(secs))
+    }
+    /// Timestamp of `duration` of CPU time used
+    ///
+    /// Meant for tests of code that stores and compares timestamps.
+    pub const fn from_duration(duration: Duration) -> ThreadTime {
+        ThreadTime(duration, PhantomData)
+    }
+    /// Timestamp of `nanos` nanoseconds of CPU time used
+    pub const fn from_nanos(nanos: u64) -> ThreadTime {
+        ThreadTime::from_duration(Duration::from_nanos(nanos))
+    }
+    /// Get current CPU time used by the current thread
     ///
     /// # Panics
     ///
-    /// This method panics if linux kernel doesn't support
+    /// This method panics if the kernel doesn't support
     /// CLOCK_THREAD_CPUTIME_ID, which works since linux 2.6.12 (~ year 2005).
+    /// On OpenBSD, AIX and Android `getrusage(RUSAGE_THREAD)`, on
+    /// illumos/Solaris `gethrvtime()`, on QNX `ClockTime()` and on Haiku
+    /// `get_thread_info()` are tried before giving up. On iOS, tvOS and
+    /// watchOS `thread_info()` is tried first, on macOS it's the fallback
+    /// for releases before 10.12.
+    ///
+    /// Use `try_now()` to handle this case without a panic.
     pub fn now() -> ThreadTime {
-        let mut time = timespec {
-            tv_sec: 0,
-            tv_nsec: 0,
-        };
-        if unsafe { clock_gettime(CLOCK_THREAD_CPUTIME_ID, &mut time) } == -1 {
-            panic!("Process CPU time is not supported");
+        match ThreadTime::try_now() {
+            Ok(time) => time,
+            Err(_) => panic!("Thread CPU time is not supported"),
         }
-        ThreadTime(
-            Duration::new(time.tv_sec as u64, time.tv_nsec as u32),
-            PhantomData,
+    }
+    /// Get current CPU time used by the current thread
+    ///
+    /// Returns `CpuTimeError::Unsupported` instead of panicking if none of
+    /// the clocks `now()` tries work, e.g. when a sandbox denies them.
+    pub fn try_now() -> Result<ThreadTime, CpuTimeError> {
+        thread_time()
+            .map(|d| ThreadTime(d, PhantomData))
+            .ok_or(CpuTimeError::Unsupported)
+    }
+    /// Get current CPU time used by another thread of the process
+    ///
+    /// Reads the clock of `pthread_getcpuclockid()`, so a supervisor can
+    /// sample its workers without their cooperation. Only available on
+    /// Linux, Android, FreeBSD and DragonFly with the `libc` feature.
+    ///
+    /// The result can be compared with other readings of the same thread,
+    /// but `elapsed()` always measures the *current* thread.
+    ///
+    /// Returns `CpuTimeError::ProcessGone` if the thread has exited.
+    #[cfg(all(
+        feature = "libc",
+        feature = "std",
+        any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "freebsd",
+            target_os = "dragonfly"
+        )
+    ))]
+    pub fn for_thread<T>(thread: &JoinHandle<T>) -> Result<ThreadTime, CpuTimeError> {
+        // Not joined yet, as that consumes the handle
+        unsafe { ThreadTime::for_pthread(thread.as_pthread_t()) }
+    }
+    /// Get current CPU time used by another thread of the process
+    ///
+    /// Like `for_thread()`, for threads that weren't started by `std`.
+    ///
+    /// # Safety
+    ///
+    /// `thread` must be a thread of this process that hasn't been joined,
+    /// or detached and exited, since either frees what `thread` points to.
+    #[cfg(all(
+        feature = "libc",
+        any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "freebsd",
+            target_os = "dragonfly"
         )
+    ))]
+    pub unsafe fn for_pthread(thread: libc::pthread_t) -> Result<ThreadTime, CpuTimeError> {
+        cpuclock::thread_clock_id(thread)
+            .and_then(cpuclock::clock_time)
+            .map(|d| ThreadTime(d, PhantomData))
+    }
+    /// Get current CPU time used by the current thread, split into user
+    /// and system time
+    ///
+    /// This reads `getrusage(RUSAGE_THREAD)` on Linux, Android, FreeBSD,
+    /// OpenBSD and AIX, `thread_info()` on Apple platforms and
+    /// `get_thread_info()` on Haiku. Other platforms (and Linux without the
+    /// `libc` feature) return `CpuTimeError::Unsupported`.
+    pub fn try_now_split() -> Result<SplitTime, CpuTimeError> {
+        thread_split()
+            .map(|(user, system)| SplitTime { user, system })
+            .ok_or(CpuTimeError::Unsupported)
+    }
+    /// Get current CPU time used by the current thread, split into user
+    /// and system time
+    ///
+    /// # Panics
+    ///
+    /// If the split can't be read. Use `try_now_split()` to handle this
+    /// case.
+    pub fn now_split() -> SplitTime {
+        match ThreadTime::try_now_split() {
+            Ok(split) => split,
+            Err(_) => panic!("Thread CPU time split is not supported"),
+        }
+    }
+    /// Returns `true` if thread CPU time can be measured in the current
+    /// environment, i.e. if `now()` won't panic
+    ///
+    /// This actually reads the clock, as some containers and VMs reject
+    /// CPU-time clocks at runtime.
+    pub fn is_supported() -> bool {
+        thread_time().is_some()
     }
     /// Returns the amount of CPU time used by the current thread
     /// from the previous timestamp to now.
+    ///
+    /// A timestamp later than now gives zero.
     pub fn elapsed(&self) -> Duration {
-        ThreadTime::now().duration_since(*self)
+        ThreadTime::now().saturating_duration_since(*self)
+    }
+    /// Returns the amount of CPU time used by the current thread
+    /// from the previous timestamp to now.
+    ///
+    /// Unlike `elapsed()` this returns an error if the clock can't be read.
+    /// It never panics, a timestamp later than now gives zero.
+    pub fn try_elapsed(&self) -> Result<Duration, CpuTimeError> {
+        ThreadTime::try_now().map(|now| now.saturating_duration_since(*self))
+    }
+    /// Runs `f` and returns its result with the CPU time the current thread used
+    /// meanwhile
+    ///
+    /// Saves the two timestamps around a single call:
+    ///
+    /// ```rust
+    /// use cpu_time::ThreadTime;
+    ///
+    /// let (sum, time) = ThreadTime::measure(|| (0..1000u64).sum::<u64>());
+    /// println!("{} took {:?}", sum, time);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If the CPU time can't be read, like `now()`.
+    pub fn measure<R, F: FnOnce() -> R>(f: F) -> (R, Duration) {
+        let start = ThreadTime::now();
+        let result = f();
+        (result, start.elapsed())
     }
     /// Returns the amount of CPU time used by the current thread
     /// from the previous timestamp.
     pub fn duration_since(&self, timestamp: ThreadTime) -> Duration {
         self.0 - timestamp.0
     }
+    /// Timestamp of `ticks` of CPU time used by the current thread, in the
+    /// units of the OS counter (see `TICKS_PER_SEC`)
+    pub const fn from_raw_ticks(ticks: u64) -> ThreadTime {
+        ThreadTime::from_nanos(ticks)
+    }
+    /// Returns the amount of CPU time used by the current thread in the
+    /// units of the OS counter, rounded as requested
+    pub fn as_raw_ticks_rounded(&self, rounding: Rounding) -> u64 {
+        rounding.ticks(self.0, ThreadTime::TICKS_PER_SEC)
+    }
+    /// Returns the amount of CPU time used by the current thread
+    /// from the previous timestamp to now as seconds.
+    pub fn elapsed_secs_f64(&self) -> f64 {
+        self.elapsed().as_secs_f64()
+    }
+    /// Returns the amount of CPU time used by the current thread
+    /// from the previous timestamp to now as seconds.
+    pub fn elapsed_secs_f32(&self) -> f32 {
+        self.elapsed().as_secs_f32()
+    }
+    /// Returns the timestamp `duration` of CPU time later than this one, or
+    /// `None` if it can't be represented.
+    pub fn checked_add(&self, duration: Duration) -> Option<ThreadTime> {
+        self.0
+            .checked_add(duration)
+            .map(|d| ThreadTime(d, PhantomData))
+    }
+    /// Returns the timestamp `duration` of CPU time earlier than this one, or
+    /// `None` if that would be before the thread started.
+    pub fn checked_sub(&self, duration: Duration) -> Option<ThreadTime> {
+        self.0
+            .checked_sub(duration)
+            .map(|d| ThreadTime(d, PhantomData))
+    }
+    /// Returns the amount of CPU time used by the current thread
+    /// from the previous timestamp, or zero if that timestamp is later than
+    /// this one.
+    pub fn saturating_duration_since(&self, timestamp: ThreadTime) -> Duration {
+        self.checked_duration_since(timestamp)
+            .unwrap_or_else(|| Duration::new(0, 0))
+    }
+    /// Returns the amount of CPU time used by the current thread
+    /// from the previous timestamp, or `None` if that timestamp is later
+    /// than this one.
+    pub fn checked_duration_since(&self, timestamp: ThreadTime) -> Option<Duration> {
+        self.0.checked_sub(timestamp.0)
+    }
+    /// Returns the amount of CPU time used by the current thread.
+    pub fn duration(&self) -> Duration {
+        self.0
+    }
+    /// Returns the amount of CPU time used by the current thread in nanoseconds
+    ///
+    /// Saturates at `u64::MAX`, which is ~584 years.
+    pub fn as_nanos_u64(&self) -> u64 {
+        to_nanos(self.0)
+    }
+    /// Returns the amount of CPU time used by the current thread in the units of the OS
+    /// counter, see `TICKS_PER_SEC`
+    pub fn as_raw_ticks(&self) -> u64 {
+        self.as_nanos_u64()
+    }
+    /// Timestamp of the CPU time in `time`, e.g. as read from `clock_id()`
+    /// with `clock_gettime()`
+    ///
+    /// A 32-bit `time_t` is read as unsigned, so the seconds don't wrap
+    /// after 2^31 (68 years). Requires the `libc` feature.
+    #[cfg(feature = "libc")]
+    pub fn from_timespec(time: libc::timespec) -> ThreadTime {
+        ThreadTime(timespec_duration(time), PhantomData)
+    }
+    /// Returns the id of the POSIX clock read by `now()`, e.g. to pass it to
+    /// `timer_create()`
+    ///
+    /// The id always refers to the thread using it, not to the one that
+    /// called this function, on every platform. Where the clock doesn't
+    /// work `now()` uses a fallback instead. Requires the `libc` feature.
+    #[cfg(feature = "libc")]
+    pub fn clock_id() -> libc::clockid_t {
+        thread_clock_id()
+    }
+    /// Returns `true` if no CPU time was used, e.g. for `ZERO`
+    pub fn is_zero(&self) -> bool {
+        self.0 == Duration::from_secs(0)
+    }
 }
@@ -0,0 +1,171 @@
+#[cfg(windows)]
+use std::convert::TryFrom;
+use std::io;
+#[cfg(unix)]
+use std::os::unix::process::CommandExt as _;
+#[cfg(windows)]
+use std::os::windows::io::AsRawHandle;
+#[cfg(windows)]
+use std::os::windows::process::CommandExt as _;
+use std::process::{Child, Command};
+use std::time::Duration;
+#[cfg(windows)]
+use std::{mem, ptr};
+
+#[cfg(unix)]
+use libc::{rlim_t, rlimit, RLIMIT_CPU};
+#[cfg(windows)]
+use winapi::shared::minwindef::DWORD;
+#[cfg(windows)]
+use winapi::um::handleapi::CloseHandle;
+#[cfg(windows)]
+use winapi::um::jobapi2::{AssignProcessToJobObject, CreateJobObjectW, SetInformationJobObject};
+#[cfg(windows)]
+use winapi::um::winbase::CREATE_SUSPENDED;
+#[cfg(windows)]
+use winapi::um::winnt::{
+    JobObjectBasicLimitInformation, HANDLE, JOBOBJECT_BASIC_LIMIT_INFORMATION,
+    JOB_OBJECT_LIMIT_PROCESS_TIME,
+};
+
+#[cfg(windows)]
+use nt;
+
+/// CPU limits for a `std::process::Command`
+///
+/// For test harnesses that kill runaway children by how much CPU they
+/// burned rather than by a wall-clock timeout, which a loaded machine makes
+/// flaky:
+///
+/// ```rust,no_run
+/// use std::process::Command;
+/// use std::time::Duration;
+/// use cpu_time::CommandExt;
+///
+/// let status = Command::new("./fuzz-case")
+///     .spawn_with_cpu_limit(Duration::from_secs(10))
+///     .unwrap()
+///     .wait()
+///     .unwrap();
+/// ```
+///
+/// The limit applies to the child and, separately, to each process it
+/// starts.
+pub trait CommandExt {
+    /// Limits the CPU time of the spawned process to `limit`, rounded up to
+    /// whole seconds
+    ///
+    /// Sets `RLIMIT_CPU` between `fork()` and `exec()`. The kernel sends
+    /// `SIGXCPU` when the limit is reached and `SIGKILL` a second later. The
+    /// limit can't exceed the hard limit of the current process, it's
+    /// lowered to that. Only available on unix, see `spawn_with_cpu_limit()`
+    /// for Windows.
+    #[cfg(unix)]
+    fn cpu_limit(&mut self, limit: Duration) -> &mut Command;
+    /// Spawns the process with its CPU time limited to `limit`
+    ///
+    /// On unix this is `cpu_limit()` followed by `spawn()`. On Windows the
+    /// process is started suspended, assigned to a new Job Object with a
+    /// per-process user time limit (`JOB_OBJECT_LIMIT_PROCESS_TIME`, which
+    /// doesn't count kernel time) and then resumed, so it can't run before
+    /// the limit applies. It's terminated when the limit is reached.
+    ///
+    /// On Windows this replaces the creation flags set with
+    /// `creation_flags()`, which std can't read back, and clears them after
+    /// spawning: set `CREATE_NO_WINDOW` and the like again before spawning
+    /// another process with the `Command`. A limit that doesn't fit the
+    /// Job Object's 100 ns ticks is an `InvalidInput` error.
+    fn spawn_with_cpu_limit(&mut self, limit: Duration) -> io::Result<Child>;
+}
+
+#[cfg(unix)]
+impl CommandExt for Command {
+    fn cpu_limit(&mut self, limit: Duration) -> &mut Command {
+        let secs = limit
+            .as_secs()
+            .saturating_add(if limit.subsec_nanos() > 0 { 1 } else { 0 });
+        // A limit of zero behaves like one second on Linux anyway
+        let secs = secs.max(1) as rlim_t;
+        // Between `fork()` and `exec()` only async-signal-safe functions may
+        // be called, which `getrlimit()` and `setrlimit()` are
+        let set_limit = move || {
+            let mut current = rlimit {
+                rlim_cur: 0,
+                rlim_max: 0,
+            };
+            if unsafe { libc::getrlimit(RLIMIT_CPU, &mut current) } == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            let soft = secs.min(current.rlim_max);
+            let limit = rlimit {
+                rlim_cur: soft,
+                rlim_max: soft.saturating_add(1).min(current.rlim_max),
+            };
+            if unsafe { libc::setrlimit(RLIMIT_CPU, &limit) } == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        };
+        unsafe { self.pre_exec(set_limit) }
+    }
+    fn spawn_with_cpu_limit(&mut self, limit: Duration) -> io::Result<Child> {
+        self.cpu_limit(limit).spawn()
+    }
+}
+
+#[cfg(windows)]
+impl CommandExt for Command {
+    fn spawn_with_cpu_limit(&mut self, limit: Duration) -> io::Result<Child> {
+        let ticks = ticks(limit)?;
+        let child = self.creation_flags(CREATE_SUSPENDED).spawn();
+        // Or a later `spawn()` would start a process nobody resumes
+        self.creation_flags(0);
+        let mut child = child?;
+        if let Err(error) = limit_process(child.as_raw_handle() as HANDLE, ticks) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(error);
+        }
+        Ok(child)
+    }
+}
+
+// In 100 ns units
+#[cfg(windows)]
+fn ticks(limit: Duration) -> io::Result<i64> {
+    i64::try_from(limit.as_secs())
+        .ok()
+        .and_then(|secs| secs.checked_mul(10_000_000))
+        .and_then(|ticks| ticks.checked_add(i64::from(limit.subsec_nanos() / 100)))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "CPU time limit too large"))
+}
+
+#[cfg(windows)]
+fn limit_process(process: HANDLE, ticks: i64) -> io::Result<()> {
+    let job = unsafe { CreateJobObjectW(ptr::null_mut(), ptr::null()) };
+    if job.is_null() {
+        return Err(io::Error::last_os_error());
+    }
+    let mut info: JOBOBJECT_BASIC_LIMIT_INFORMATION = unsafe { mem::zeroed() };
+    unsafe { *info.PerProcessUserTimeLimit.QuadPart_mut() = ticks };
+    info.LimitFlags = JOB_OBJECT_LIMIT_PROCESS_TIME;
+    let ok = unsafe {
+        SetInformationJobObject(
+            job,
+            JobObjectBasicLimitInformation,
+            &mut info as *mut _ as *mut _,
+            mem::size_of_val(&info) as DWORD,
+        ) != 0
+            && AssignProcessToJobObject(job, process) != 0
+    };
+    let result = if !ok {
+        Err(io::Error::last_os_error())
+    } else if !nt::resume_process(process) {
+        Err(io::Error::other("can't resume the suspended process"))
+    } else {
+        Ok(())
+    };
+    // The job and its limit live on while processes are assigned to it
+    unsafe { CloseHandle(job) };
+    result
+}
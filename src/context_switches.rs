@@ -0,0 +1,157 @@
+#[cfg(windows)]
+use winapi::um::processthreadsapi::{GetCurrentProcessId, GetCurrentThreadId};
+
+#[cfg(windows)]
+use nt::Snapshot;
+use CpuTimeError;
+#[cfg(unix)]
+use ResourceUsage;
+
+/// Number of Times The Scheduler Switched Away From a Process or Thread
+///
+/// A high count of involuntary switches means the CPU was contended, a
+/// high count of voluntary ones that the code blocked a lot, both are
+/// common explanations for CPU-time anomalies.
+///
+/// Unix reads `getrusage()`, which keeps the two kinds apart. Windows only
+/// counts all switches of every thread (read with
+/// `NtQuerySystemInformation`), so there `voluntary` and `involuntary` are
+/// `None`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(::rkyv::Archive, ::rkyv::Serialize, ::rkyv::Deserialize)
+)]
+#[cfg_attr(feature = "bincode", derive(::bincode::Encode, ::bincode::Decode))]
+#[cfg_attr(feature = "arbitrary", derive(::arbitrary::Arbitrary))]
+pub struct ContextSwitches {
+    /// Switches because the process waited, e.g. for I/O or a lock
+    pub voluntary: Option<u64>,
+    /// Switches because the time slice ran out or a higher priority task
+    /// became runnable
+    pub involuntary: Option<u64>,
+    /// All switches
+    pub total: u64,
+}
+
+impl ContextSwitches {
+    #[cfg(unix)]
+    fn from_usage(usage: ResourceUsage) -> ContextSwitches {
+        ContextSwitches {
+            voluntary: Some(usage.voluntary_context_switches),
+            involuntary: Some(usage.involuntary_context_switches),
+            total: usage
+                .voluntary_context_switches
+                .saturating_add(usage.involuntary_context_switches),
+        }
+    }
+    /// Get the context switches of the whole process
+    ///
+    /// On Windows this sums up the threads that are still running.
+    #[cfg(unix)]
+    pub fn process() -> Result<ContextSwitches, CpuTimeError> {
+        ResourceUsage::process().map(ContextSwitches::from_usage)
+    }
+    /// Get the context switches of the whole process
+    ///
+    /// On Windows this sums up the threads that are still running.
+    #[cfg(windows)]
+    pub fn process() -> Result<ContextSwitches, CpuTimeError> {
+        let snapshot = Snapshot::capture()?;
+        let process = snapshot
+            .process(unsafe { GetCurrentProcessId() })
+            .ok_or(CpuTimeError::ProcessGone)?;
+        let total = process
+            .threads()
+            .iter()
+            .map(|thread| thread.context_switches as u64)
+            .sum();
+        Ok(ContextSwitches {
+            voluntary: None,
+            involuntary: None,
+            total,
+        })
+    }
+    /// Get the context switches of the current thread
+    ///
+    /// On unix only available where `RUSAGE_THREAD` exists (Linux, Android,
+    /// FreeBSD, OpenBSD and AIX), otherwise this returns
+    /// `CpuTimeError::Unsupported`.
+    #[cfg(unix)]
+    pub fn thread() -> Result<ContextSwitches, CpuTimeError> {
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "freebsd",
+            target_os = "openbsd",
+            target_os = "aix"
+        ))]
+        return ResourceUsage::thread().map(ContextSwitches::from_usage);
+        #[cfg(not(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "freebsd",
+            target_os = "openbsd",
+            target_os = "aix"
+        )))]
+        return Err(CpuTimeError::Unsupported);
+    }
+    /// Get the context switches of the current thread
+    ///
+    /// On unix only available where `RUSAGE_THREAD` exists (Linux, Android,
+    /// FreeBSD, OpenBSD and AIX), otherwise this returns
+    /// `CpuTimeError::Unsupported`.
+    #[cfg(windows)]
+    pub fn thread() -> Result<ContextSwitches, CpuTimeError> {
+        let snapshot = Snapshot::capture()?;
+        let process = snapshot
+            .process(unsafe { GetCurrentProcessId() })
+            .ok_or(CpuTimeError::ProcessGone)?;
+        let id = unsafe { GetCurrentThreadId() } as usize;
+        let thread = process
+            .threads()
+            .iter()
+            .find(|thread| thread.unique_thread as usize == id)
+            .ok_or(CpuTimeError::ProcessGone)?;
+        Ok(ContextSwitches {
+            voluntary: None,
+            involuntary: None,
+            total: thread.context_switches as u64,
+        })
+    }
+    /// Returns the switches from the previous reading to this one,
+    /// saturating at zero
+    pub fn saturating_sub(&self, earlier: ContextSwitches) -> ContextSwitches {
+        fn sub(later: Option<u64>, earlier: Option<u64>) -> Option<u64> {
+            match (later, earlier) {
+                (Some(later), Some(earlier)) => Some(later.saturating_sub(earlier)),
+                _ => None,
+            }
+        }
+        ContextSwitches {
+            voluntary: sub(self.voluntary, earlier.voluntary),
+            involuntary: sub(self.involuntary, earlier.involuntary),
+            total: self.total.saturating_sub(earlier.total),
+        }
+    }
+}
+
+#[cfg(feature = "proptest")]
+impl ::proptest::arbitrary::Arbitrary for ContextSwitches {
+    type Parameters = ();
+    type Strategy = ::proptest::strategy::BoxedStrategy<ContextSwitches>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        use proptest::arbitrary::any;
+        use proptest::strategy::Strategy;
+
+        any::<(Option<u64>, Option<u64>, u64)>()
+            .prop_map(|(voluntary, involuntary, total)| ContextSwitches {
+                voluntary,
+                involuntary,
+                total,
+            })
+            .boxed()
+    }
+}
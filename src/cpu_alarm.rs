@@ -0,0 +1,259 @@
+use std::convert::TryFrom;
+use std::mem;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use libc::{c_int, clockid_t, itimerspec, sigevent, timer_t, timespec};
+use libc::{CLOCK_PROCESS_CPUTIME_ID, EAGAIN, EINVAL, SA_RESTART, SIGEV_SIGNAL, TIMER_ABSTIME};
+
+use cpuclock::{clock_time, thread_clock_id};
+use CpuTimeError;
+
+// How many alarms can be armed at once. The signal handler can't tell which
+// timer fired (`libc` can't read `si_value` of glibc's `siginfo_t`), so it
+// checks the deadline of each of them.
+const SLOTS: usize = 32;
+
+const FREE: usize = 0;
+const CLAIMED: usize = 1;
+const ARMED: usize = 2;
+
+struct Slot {
+    state: AtomicUsize,
+    clock: AtomicUsize,
+    deadline_secs: AtomicUsize,
+    deadline_nanos: AtomicUsize,
+    fired: AtomicBool,
+    // A `fn()` set with `on_fire()`, 0 if none
+    callback: AtomicUsize,
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY: Slot = Slot {
+    state: AtomicUsize::new(FREE),
+    clock: AtomicUsize::new(0),
+    deadline_secs: AtomicUsize::new(0),
+    deadline_nanos: AtomicUsize::new(0),
+    fired: AtomicBool::new(false),
+    callback: AtomicUsize::new(0),
+};
+
+static ALARMS: [Slot; SLOTS] = [EMPTY; SLOTS];
+
+impl Slot {
+    fn clock(&self) -> clockid_t {
+        self.clock.load(Ordering::SeqCst) as u32 as clockid_t
+    }
+    fn deadline(&self) -> Duration {
+        Duration::new(
+            self.deadline_secs.load(Ordering::SeqCst) as u64,
+            self.deadline_nanos.load(Ordering::SeqCst) as u32,
+        )
+    }
+    // Async-signal-safe, `clock_gettime()` is
+    fn check(&self) -> bool {
+        if self.fired.load(Ordering::SeqCst) {
+            return true;
+        }
+        match clock_time(self.clock()) {
+            Ok(now) if now >= self.deadline() => {}
+            _ => return false,
+        }
+        if !self.fired.swap(true, Ordering::SeqCst) {
+            let callback = self.callback.load(Ordering::SeqCst);
+            if callback != 0 {
+                let callback: fn() = unsafe { mem::transmute(callback) };
+                callback();
+            }
+        }
+        true
+    }
+}
+
+extern "C" fn handle_signal(_: c_int) {
+    for slot in ALARMS.iter() {
+        if slot.state.load(Ordering::SeqCst) == ARMED {
+            slot.check();
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn errno() -> c_int {
+    unsafe { *libc::__errno_location() }
+}
+
+#[cfg(target_os = "android")]
+fn errno() -> c_int {
+    unsafe { *libc::__errno() }
+}
+
+/// A one-shot alarm on a CPU-time clock
+///
+/// Fires once the process (or a thread) has used the given amount of CPU
+/// time from now on, for soft deadlines that don't count time spent
+/// sleeping or waiting:
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+/// use cpu_time::CpuAlarm;
+///
+/// # fn refine() {}
+/// let alarm = CpuAlarm::process(Duration::from_millis(500)).unwrap();
+/// while !alarm.fired() {
+///     refine();
+/// }
+/// ```
+///
+/// It's a POSIX timer (`timer_create()`) on the CPU-time clock, which
+/// notifies the process with the `CpuAlarm::signal()` real-time signal. Its
+/// handler replaces any other one for that signal. At most 32 alarms can
+/// exist at the same time. Only available on Linux and Android.
+#[derive(Debug)]
+pub struct CpuAlarm {
+    slot: usize,
+    timer: timer_t,
+}
+
+// Timers belong to the process, not to the thread that created them
+unsafe impl Send for CpuAlarm {}
+unsafe impl Sync for CpuAlarm {}
+
+impl CpuAlarm {
+    /// Arms an alarm that fires after the whole process has used `after`
+    /// of CPU time
+    ///
+    /// Returns `CpuTimeError::Os(EINVAL)` if the deadline is too far off to
+    /// be represented, and `EAGAIN` if 32 alarms exist already.
+    pub fn process(after: Duration) -> Result<CpuAlarm, CpuTimeError> {
+        CpuAlarm::new(CLOCK_PROCESS_CPUTIME_ID, after)
+    }
+    /// Arms an alarm that fires after the current thread has used `after`
+    /// of CPU time
+    ///
+    /// The thread must outlive the alarm, it never fires after the thread
+    /// exited.
+    pub fn thread(after: Duration) -> Result<CpuAlarm, CpuTimeError> {
+        let clock = unsafe { thread_clock_id(libc::pthread_self()) }?;
+        CpuAlarm::new(clock, after)
+    }
+    /// Returns the real-time signal the alarms are delivered with,
+    /// `SIGRTMAX()`
+    pub fn signal() -> c_int {
+        libc::SIGRTMAX()
+    }
+    fn new(clock: clockid_t, after: Duration) -> Result<CpuAlarm, CpuTimeError> {
+        let slot = ALARMS
+            .iter()
+            .position(|slot| {
+                slot.state
+                    .compare_exchange(FREE, CLAIMED, Ordering::SeqCst, Ordering::SeqCst)
+                    .is_ok()
+            })
+            .ok_or(CpuTimeError::Os(EAGAIN))?;
+        match CpuAlarm::arm(slot, clock, after) {
+            Ok(timer) => Ok(CpuAlarm { slot, timer }),
+            Err(error) => {
+                ALARMS[slot].state.store(FREE, Ordering::SeqCst);
+                Err(error)
+            }
+        }
+    }
+    fn arm(slot: usize, clock: clockid_t, after: Duration) -> Result<timer_t, CpuTimeError> {
+        // The seconds must fit an `AtomicUsize` and a `time_t`, which are
+        // 32 bits on some targets
+        let deadline = clock_time(clock)?
+            .checked_add(after)
+            .ok_or(CpuTimeError::Os(EINVAL))?;
+        let secs = usize::try_from(deadline.as_secs()).map_err(|_| CpuTimeError::Os(EINVAL))?;
+        let time_secs =
+            libc::time_t::try_from(deadline.as_secs()).map_err(|_| CpuTimeError::Os(EINVAL))?;
+        let alarm = &ALARMS[slot];
+        alarm.clock.store(clock as u32 as usize, Ordering::SeqCst);
+        alarm.deadline_secs.store(secs, Ordering::SeqCst);
+        alarm
+            .deadline_nanos
+            .store(deadline.subsec_nanos() as usize, Ordering::SeqCst);
+        alarm.fired.store(false, Ordering::SeqCst);
+        alarm.callback.store(0, Ordering::SeqCst);
+
+        let mut action: libc::sigaction = unsafe { mem::zeroed() };
+        action.sa_sigaction = handle_signal as extern "C" fn(c_int) as libc::sighandler_t;
+        action.sa_flags = SA_RESTART;
+        unsafe { libc::sigemptyset(&mut action.sa_mask) };
+        if unsafe { libc::sigaction(CpuAlarm::signal(), &action, ptr::null_mut()) } == -1 {
+            return Err(CpuTimeError::Os(errno()));
+        }
+
+        let mut event: sigevent = unsafe { mem::zeroed() };
+        event.sigev_notify = SIGEV_SIGNAL;
+        event.sigev_signo = CpuAlarm::signal();
+        let mut timer: timer_t = ptr::null_mut();
+        if unsafe { libc::timer_create(clock, &mut event, &mut timer) } == -1 {
+            return Err(CpuTimeError::Os(errno()));
+        }
+        alarm.state.store(ARMED, Ordering::SeqCst);
+        let spec = itimerspec {
+            it_interval: timespec {
+                tv_sec: 0,
+                tv_nsec: 0,
+            },
+            // Zero would disarm it
+            it_value: timespec {
+                tv_sec: time_secs,
+                tv_nsec: deadline.subsec_nanos().max(1) as libc::c_long,
+            },
+        };
+        if unsafe { libc::timer_settime(timer, TIMER_ABSTIME, &spec, ptr::null_mut()) } == -1 {
+            let code = errno();
+            alarm.state.store(CLAIMED, Ordering::SeqCst);
+            unsafe { libc::timer_delete(timer) };
+            return Err(CpuTimeError::Os(code));
+        }
+        Ok(timer)
+    }
+    /// Returns `true` once the CPU time has been used
+    ///
+    /// Also checks the clock itself, so this is exact even if the signal
+    /// hasn't been handled yet, e.g. because it's blocked.
+    pub fn fired(&self) -> bool {
+        ALARMS[self.slot].check()
+    }
+    /// Returns the CPU time left until the alarm fires, zero if it did (or
+    /// its thread exited)
+    pub fn remaining(&self) -> Duration {
+        let alarm = &ALARMS[self.slot];
+        match clock_time(alarm.clock()) {
+            Ok(now) => alarm
+                .deadline()
+                .checked_sub(now)
+                .unwrap_or_else(|| Duration::new(0, 0)),
+            Err(_) => Duration::new(0, 0),
+        }
+    }
+    /// Sets a function for the signal handler to call when the alarm fires
+    ///
+    /// Isn't called if the alarm already fired.
+    ///
+    /// # Safety
+    ///
+    /// `callback` runs in a signal handler, interrupting whatever the
+    /// thread was doing. It may only call async-signal-safe functions, so no
+    /// allocation, locking or printing with `println!()`.
+    pub unsafe fn on_fire(&self, callback: fn()) {
+        ALARMS[self.slot]
+            .callback
+            .store(callback as usize, Ordering::SeqCst);
+    }
+}
+
+impl Drop for CpuAlarm {
+    fn drop(&mut self) {
+        let alarm = &ALARMS[self.slot];
+        // Keep the handler off the slot before it's reused
+        alarm.state.store(CLAIMED, Ordering::SeqCst);
+        unsafe { libc::timer_delete(self.timer) };
+        alarm.state.store(FREE, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,96 @@
+use std::fmt;
+use std::time::Duration;
+
+/// CPU time formatted for humans
+///
+/// Displays the duration in the largest unit that keeps it above one: `s`,
+/// `ms`, `µs` or `ns`. Seconds get three decimals by default and the smaller
+/// units none, the formatter precision overrides both. The unit is picked
+/// after rounding, so 999.6 ms shows as `1.000 s`:
+///
+/// ```rust
+/// use std::time::Duration;
+/// use cpu_time::CpuDuration;
+///
+/// assert_eq!(CpuDuration(Duration::from_millis(1234)).to_string(), "1.234 s");
+/// assert_eq!(CpuDuration(Duration::from_millis(87)).to_string(), "87 ms");
+/// assert_eq!(format!("{:.1}", CpuDuration(Duration::from_micros(412))), "412.0 µs");
+/// ```
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, Default)]
+pub struct CpuDuration(pub Duration);
+
+impl CpuDuration {
+    /// Returns the wrapped duration
+    pub fn duration(&self) -> Duration {
+        self.0
+    }
+}
+
+impl From<Duration> for CpuDuration {
+    fn from(duration: Duration) -> CpuDuration {
+        CpuDuration(duration)
+    }
+}
+
+impl From<CpuDuration> for Duration {
+    fn from(duration: CpuDuration) -> Duration {
+        duration.0
+    }
+}
+
+// In nanoseconds, largest first
+const UNITS: [u128; 4] = [1_000_000_000, 1_000_000, 1_000, 1];
+
+// Rounds `duration` to `precision` decimals, or the default of the unit, in
+// the largest unit that keeps the rounded value at least one. Returns the
+// unit's index, the integer part, the first (at most nine) decimals and the
+// precision
+fn round(duration: Duration, precision: Option<usize>) -> (usize, u128, u128, usize) {
+    let nanos = duration.as_nanos();
+    let mut unit = UNITS
+        .iter()
+        .position(|&size| nanos >= size)
+        .unwrap_or(UNITS.len() - 1);
+    loop {
+        let size = UNITS[unit];
+        let precision = precision.unwrap_or(if unit == 0 { 3 } else { 0 });
+        // The decimals past nanoseconds are always zero
+        let scale = 10u128.pow(precision.min(9) as u32);
+        let scaled = (nanos * scale + size / 2) / size;
+        // E.g. 999.6 ms, which rounds to 1000 ms
+        if unit > 0 && scaled >= 1000 * scale {
+            unit -= 1;
+            continue;
+        }
+        return (unit, scaled / scale, scaled % scale, precision);
+    }
+}
+
+impl fmt::Display for CpuDuration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (unit, whole, decimals, precision) = round(self.0, f.precision());
+        write!(f, "{}", whole)?;
+        if precision > 0 {
+            write!(f, ".{:0width$}", decimals, width = precision.min(9))?;
+            for _ in 9..precision {
+                f.write_str("0")?;
+            }
+        }
+        write!(f, " {}", ["s", "ms", "µs", "ns"][unit])
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for CpuDuration {
+    // Rounded like `Display` with the default precision. Interned strings
+    // are kept ASCII, so microseconds are `us`
+    fn format(&self, f: defmt::Formatter) {
+        let (unit, whole, decimals, _) = round(self.0, None);
+        match unit {
+            0 => defmt::write!(f, "{=u128}.{=u128:03} s", whole, decimals),
+            1 => defmt::write!(f, "{=u128} ms", whole),
+            2 => defmt::write!(f, "{=u128} us", whole),
+            _ => defmt::write!(f, "{=u128} ns", whole),
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use std::mem;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use libc::{c_int, rlim_t, rlimit, EINVAL, RLIMIT_CPU, RLIM_INFINITY, SA_RESTART, SIGXCPU};
+
+use CpuTimeError;
+use ProcessTime;
+
+/// The `RLIMIT_CPU` limits of the current process
+///
+/// When the process has used `soft` of CPU time the kernel sends it
+/// `SIGXCPU` (on Linux again every second after that), at `hard` it's
+/// killed. `None` means unlimited. The limits are inherited by child
+/// processes, e.g. from a shell's `ulimit -t`.
+///
+/// A plugin host can cap how much CPU untrusted code may use, and wind
+/// down cleanly on `SIGXCPU` instead of being killed by it:
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+/// use cpu_time::CpuLimit;
+///
+/// # fn run_plugin_step() {}
+/// cpu_time::catch_sigxcpu().unwrap();
+/// CpuLimit {
+///     soft: Some(Duration::from_secs(60)),
+///     hard: Some(Duration::from_secs(65)),
+/// }
+/// .set()
+/// .unwrap();
+/// while !cpu_time::sigxcpu_received() {
+///     run_plugin_step();
+/// }
+/// ```
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash, Default)]
+pub struct CpuLimit {
+    /// Limit at which `SIGXCPU` is sent
+    pub soft: Option<Duration>,
+    /// Limit the soft limit can be raised to without privileges, the
+    /// process is killed when reaching it
+    pub hard: Option<Duration>,
+}
+
+// `rlim_t` is signed on some platforms
+#[allow(clippy::unnecessary_cast)]
+fn to_duration(limit: rlim_t) -> Option<Duration> {
+    if limit == RLIM_INFINITY {
+        None
+    } else {
+        Some(Duration::from_secs(limit as u64))
+    }
+}
+
+// Rounded up to whole seconds
+#[allow(clippy::unnecessary_cast)]
+fn to_limit(duration: Option<Duration>) -> rlim_t {
+    match duration {
+        Some(duration) => {
+            let secs = duration.as_secs() + if duration.subsec_nanos() > 0 { 1 } else { 0 };
+            if secs >= RLIM_INFINITY as u64 {
+                RLIM_INFINITY
+            } else {
+                secs as rlim_t
+            }
+        }
+        None => RLIM_INFINITY,
+    }
+}
+
+impl CpuLimit {
+    /// Reads the limits with `getrlimit(RLIMIT_CPU)`
+    pub fn current() -> Result<CpuLimit, CpuTimeError> {
+        let mut limit = rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        if unsafe { libc::getrlimit(RLIMIT_CPU, &mut limit) } == -1 {
+            return Err(CpuTimeError::Unsupported);
+        }
+        Ok(CpuLimit {
+            soft: to_duration(limit.rlim_cur),
+            hard: to_duration(limit.rlim_max),
+        })
+    }
+    /// Sets the limits with `setrlimit(RLIMIT_CPU)`, rounded up to whole
+    /// seconds
+    ///
+    /// Lowering the hard limit can't be undone without privileges, raising
+    /// it returns `CpuTimeError::PermissionDenied` then. The soft limit
+    /// can't exceed the hard one. The default action of `SIGXCPU` is to
+    /// terminate the process, see `catch_sigxcpu()`.
+    pub fn set(&self) -> Result<(), CpuTimeError> {
+        let limit = rlimit {
+            rlim_cur: to_limit(self.soft),
+            rlim_max: to_limit(self.hard),
+        };
+        if unsafe { libc::setrlimit(RLIMIT_CPU, &limit) } == -1 {
+            // `errno` can't be read portably without `std`, but there are
+            // only two reasons
+            let raised = match (CpuLimit::current()?.hard, self.hard) {
+                (Some(current), Some(hard)) => hard > current,
+                (Some(_), None) => true,
+                (None, _) => false,
+            };
+            return Err(if raised {
+                CpuTimeError::PermissionDenied
+            } else {
+                CpuTimeError::Os(EINVAL)
+            });
+        }
+        Ok(())
+    }
+}
+
+static SIGXCPU_RECEIVED: AtomicBool = AtomicBool::new(false);
+// A `fn()` set with `on_sigxcpu()`, 0 if none
+static SIGXCPU_HOOK: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn handle_sigxcpu(_: c_int) {
+    SIGXCPU_RECEIVED.store(true, Ordering::SeqCst);
+    let hook = SIGXCPU_HOOK.load(Ordering::SeqCst);
+    if hook != 0 {
+        let hook: fn() = unsafe { mem::transmute(hook) };
+        hook();
+    }
+}
+
+/// Installs a `SIGXCPU` handler, so that reaching the soft CPU limit
+/// doesn't terminate the process
+///
+/// The handler only records the signal for `sigxcpu_received()` and calls
+/// the hook of `on_sigxcpu()`, if any. It replaces any previous handler.
+/// Reaching the hard limit still kills the process.
+pub fn catch_sigxcpu() -> Result<(), CpuTimeError> {
+    let mut action: libc::sigaction = unsafe { mem::zeroed() };
+    action.sa_sigaction = handle_sigxcpu as extern "C" fn(c_int) as libc::sighandler_t;
+    action.sa_flags = SA_RESTART;
+    unsafe { libc::sigemptyset(&mut action.sa_mask) };
+    if unsafe { libc::sigaction(SIGXCPU, &action, std::ptr::null_mut()) } == -1 {
+        // Only fails for invalid signals
+        return Err(CpuTimeError::Os(EINVAL));
+    }
+    Ok(())
+}
+
+/// Returns `true` if `SIGXCPU` was received since the last call
+///
+/// Requires `catch_sigxcpu()`.
+pub fn sigxcpu_received() -> bool {
+    SIGXCPU_RECEIVED.swap(false, Ordering::SeqCst)
+}
+
+/// Sets a function for the `catch_sigxcpu()` handler to call
+///
+/// # Safety
+///
+/// `hook` runs in a signal handler, interrupting whatever the thread was
+/// doing. It may only call async-signal-safe functions, so no allocation,
+/// locking or printing with `println!()`.
+pub unsafe fn on_sigxcpu(hook: fn()) {
+    SIGXCPU_HOOK.store(hook as usize, Ordering::SeqCst);
+}
+
+/// Returns how much CPU time the process may still use before it gets
+/// `SIGXCPU`, or `None` if there is no limit
+///
+/// That is the soft `RLIMIT_CPU` minus `ProcessTime::now()`, for long
+/// computations that want to write a checkpoint in time:
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+///
+/// # fn step() {}
+/// # fn checkpoint() {}
+/// loop {
+///     step();
+///     if let Some(left) = cpu_time::remaining_cpu_budget().unwrap() {
+///         if left < Duration::from_secs(5) {
+///             checkpoint();
+///             break;
+///         }
+///     }
+/// }
+/// ```
+///
+/// Zero once the limit is reached. The kernel checks the limit at its tick
+/// rate, so the signal can come a few milliseconds late.
+pub fn remaining_cpu_budget() -> Result<Option<Duration>, CpuTimeError> {
+    let soft = match CpuLimit::current()?.soft {
+        Some(soft) => soft,
+        None => return Ok(None),
+    };
+    let used = ProcessTime::try_now()?.duration();
+    Ok(Some(
+        soft.checked_sub(used)
+            .unwrap_or_else(|| Duration::new(0, 0)),
+    ))
+}
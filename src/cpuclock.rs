@@ -0,0 +1,71 @@
+use std::mem::size_of_val;
+use std::time::Duration;
+
+use libc::{clockid_t, pid_t, pthread_t, timespec, EPERM, ESRCH};
+
+use CpuTimeError;
+
+// Linux encodes the pid in 29 bits of the clock id, larger ones would wrap
+// around to other processes (`PID_MAX_LIMIT` is 2^22 anyway)
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const MAX_PID: u32 = (1 << 29) - 1;
+
+// Larger ones are negative `pid_t`s
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+const MAX_PID: u32 = i32::MAX as u32;
+
+/// Returns the id of the CPU-time clock of process `pid`
+pub fn process_clock_id(pid: u32) -> Result<clockid_t, CpuTimeError> {
+    if pid > MAX_PID {
+        return Err(CpuTimeError::ProcessGone);
+    }
+    let mut clock: clockid_t = 0;
+    // Returns the error code instead of setting `errno`
+    match unsafe { libc::clock_getcpuclockid(pid as pid_t, &mut clock) } {
+        0 => Ok(clock),
+        ESRCH => Err(CpuTimeError::ProcessGone),
+        EPERM => Err(CpuTimeError::PermissionDenied),
+        code => Err(CpuTimeError::Os(code)),
+    }
+}
+
+/// Returns the id of the CPU-time clock of `thread`
+///
+/// # Safety
+///
+/// `thread` must not have been joined or detached and exited, as that frees
+/// the memory behind it.
+pub unsafe fn thread_clock_id(thread: pthread_t) -> Result<clockid_t, CpuTimeError> {
+    let mut clock: clockid_t = 0;
+    match libc::pthread_getcpuclockid(thread, &mut clock) {
+        0 => Ok(clock),
+        ESRCH => Err(CpuTimeError::ProcessGone),
+        code => Err(CpuTimeError::Os(code)),
+    }
+}
+
+/// Reads a clock returned by `process_clock_id()` or `thread_clock_id()`
+///
+/// The id was valid when it was made, so a failure means that the process
+/// or thread has exited in between.
+pub fn clock_time(clock: clockid_t) -> Result<Duration, CpuTimeError> {
+    let mut time = timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    if unsafe { libc::clock_gettime(clock, &mut time) } == -1 {
+        return Err(CpuTimeError::ProcessGone);
+    }
+    // Read as unsigned like the own clocks, see `clocks::libc_clock_time()`
+    let seconds = if size_of_val(&time.tv_sec) == 4 {
+        time.tv_sec as u32 as u64
+    } else {
+        time.tv_sec as u64
+    };
+    Ok(Duration::new(seconds, time.tv_nsec as u32))
+}
+
+/// Returns the CPU time used by process `pid`
+pub fn process_time(pid: u32) -> Result<Duration, CpuTimeError> {
+    process_clock_id(pid).and_then(clock_time)
+}
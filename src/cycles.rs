@@ -0,0 +1,177 @@
+use std::marker::PhantomData;
+use std::time::Duration;
+#[cfg(feature = "std")]
+use std::time::Instant;
+
+use winapi::um::errhandlingapi::GetLastError;
+use winapi::um::processthreadsapi::{GetCurrentProcess, GetCurrentThread};
+use winapi::um::realtimeapiset::{QueryProcessCycleTime, QueryThreadCycleTime};
+
+use windows::error;
+use CpuTimeError;
+
+/// CPU Cycles Used by The Whole Process
+///
+/// Read with `QueryProcessCycleTime`, which counts the cycles of every
+/// thread of the process, including ones that exited. Unlike
+/// `GetProcessTimes`, which only advances when the scheduler ticks (every
+/// 15.6 ms by default), this is cycle-accurate. Cycles aren't a time unit
+/// though: the rate differs between processors and changes with frequency
+/// scaling.
+///
+/// Only available on Windows.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+pub struct ProcessCycles(u64);
+
+impl ProcessCycles {
+    /// Get current number of CPU cycles used by the current process
+    ///
+    /// # Panics
+    ///
+    /// If `QueryProcessCycleTime` fails. Use `try_now()` to handle this
+    /// case.
+    pub fn now() -> ProcessCycles {
+        match ProcessCycles::try_now() {
+            Ok(cycles) => cycles,
+            Err(_) => panic!("Can't get process cycle time"),
+        }
+    }
+    /// Get current number of CPU cycles used by the current process
+    pub fn try_now() -> Result<ProcessCycles, CpuTimeError> {
+        let mut cycles = 0;
+        if unsafe { QueryProcessCycleTime(GetCurrentProcess(), &mut cycles) } == 0 {
+            return Err(error(unsafe { GetLastError() }));
+        }
+        Ok(ProcessCycles(cycles))
+    }
+    /// Timestamp of `cycles` CPU cycles used
+    ///
+    /// Meant for tests of code that stores and compares timestamps.
+    pub const fn from_cycles(cycles: u64) -> ProcessCycles {
+        ProcessCycles(cycles)
+    }
+    /// Returns the number of cycles used from the previous timestamp to
+    /// now.
+    pub fn elapsed(&self) -> u64 {
+        ProcessCycles::now().cycles_since(*self)
+    }
+    /// Returns the number of cycles used from the previous timestamp, or
+    /// zero if that timestamp is later than this one.
+    pub fn cycles_since(&self, timestamp: ProcessCycles) -> u64 {
+        self.0.saturating_sub(timestamp.0)
+    }
+    /// Returns the number of cycles used.
+    pub fn cycles(&self) -> u64 {
+        self.0
+    }
+}
+
+/// CPU Cycles Used by The Current Thread
+///
+/// Read with `QueryThreadCycleTime`, for micro-benchmarks where the
+/// resolution of `GetThreadTimes` is useless. Use a `CycleRate` to turn
+/// cycles into approximate time.
+///
+/// This type is non-thread-shareable (!Sync, !Send) like `ThreadTime`.
+///
+/// Only available on Windows.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+pub struct ThreadCycles(
+    u64,
+    // makes type non-sync and non-send
+    PhantomData<*const ()>,
+);
+
+impl ThreadCycles {
+    /// Get current number of CPU cycles used by the current thread
+    ///
+    /// # Panics
+    ///
+    /// If `QueryThreadCycleTime` fails. Use `try_now()` to handle this
+    /// case.
+    pub fn now() -> ThreadCycles {
+        match ThreadCycles::try_now() {
+            Ok(cycles) => cycles,
+            Err(_) => panic!("Can't get thread cycle time"),
+        }
+    }
+    /// Get current number of CPU cycles used by the current thread
+    pub fn try_now() -> Result<ThreadCycles, CpuTimeError> {
+        let mut cycles = 0;
+        if unsafe { QueryThreadCycleTime(GetCurrentThread(), &mut cycles) } == 0 {
+            return Err(error(unsafe { GetLastError() }));
+        }
+        Ok(ThreadCycles(cycles, PhantomData))
+    }
+    /// Timestamp of `cycles` CPU cycles used by the current thread
+    ///
+    /// Meant for tests of code that stores and compares timestamps.
+    pub const fn from_cycles(cycles: u64) -> ThreadCycles {
+        ThreadCycles(cycles, PhantomData)
+    }
+    /// Returns the number of cycles used by the current thread from the
+    /// previous timestamp to now.
+    pub fn elapsed(&self) -> u64 {
+        ThreadCycles::now().cycles_since(*self)
+    }
+    /// Returns the number of cycles used by the current thread from the
+    /// previous timestamp, or zero if that timestamp is later than this one.
+    pub fn cycles_since(&self, timestamp: ThreadCycles) -> u64 {
+        self.0.saturating_sub(timestamp.0)
+    }
+    /// Returns the number of cycles used by the current thread.
+    pub fn cycles(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Approximate Rate of CPU Cycles, to Convert Cycles to Time
+///
+/// The rate depends on the processor the thread ran on and on frequency
+/// scaling, so converted values are estimates. Calibrate again if the
+/// power plan changes.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub struct CycleRate {
+    cycles_per_sec: u64,
+}
+
+impl CycleRate {
+    /// Rate of `cycles_per_sec` cycles per second, e.g. the nominal
+    /// frequency of the processor
+    pub fn from_cycles_per_sec(cycles_per_sec: u64) -> CycleRate {
+        CycleRate { cycles_per_sec }
+    }
+    /// Measures the rate by busy-waiting on the current thread for `sample`
+    /// of wall-clock time
+    ///
+    /// Tens of milliseconds give a usable estimate. Time the thread spends
+    /// preempted makes the rate look lower.
+    #[cfg(feature = "std")]
+    pub fn calibrate(sample: Duration) -> Result<CycleRate, CpuTimeError> {
+        let start = ThreadCycles::try_now()?;
+        let wall = Instant::now();
+        while wall.elapsed() < sample {}
+        let cycles = ThreadCycles::try_now()?.cycles_since(start);
+        let nanos = wall.elapsed().as_nanos().max(1);
+        let cycles_per_sec = cycles as u128 * 1_000_000_000 / nanos;
+        Ok(CycleRate {
+            cycles_per_sec: cycles_per_sec.min(u64::MAX as u128) as u64,
+        })
+    }
+    /// Returns the number of cycles per second
+    pub fn cycles_per_sec(&self) -> u64 {
+        self.cycles_per_sec
+    }
+    /// Converts `cycles` to approximate time, zero if the rate is zero
+    pub fn to_duration(&self, cycles: u64) -> Duration {
+        if self.cycles_per_sec == 0 {
+            return Duration::new(0, 0);
+        }
+        let nanos = cycles as u128 * 1_000_000_000 / self.cycles_per_sec as u128;
+        let secs = nanos / 1_000_000_000;
+        if secs > u64::MAX as u128 {
+            return Duration::new(u64::MAX, 999_999_999);
+        }
+        Duration::new(secs as u64, (nanos % 1_000_000_000) as u32)
+    }
+}
@@ -0,0 +1,15 @@
+use defmt::{Format, Formatter};
+
+use {ProcessTime, ThreadTime};
+
+impl Format for ProcessTime {
+    fn format(&self, f: Formatter) {
+        defmt::write!(f, "ProcessTime({=u64} ns)", self.as_nanos_u64())
+    }
+}
+
+impl Format for ThreadTime {
+    fn format(&self, f: Formatter) {
+        defmt::write!(f, "ThreadTime({=u64} ns)", self.as_nanos_u64())
+    }
+}
@@ -0,0 +1,35 @@
+#[cfg(feature = "std")]
+use std::error::Error;
+use std::fmt;
+
+/// Error returned when CPU time can't be measured
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CpuTimeError {
+    /// The platform or runtime doesn't provide the requested CPU clock
+    Unsupported,
+    /// The clock exists, but reading it was denied, e.g. by a sandbox or
+    /// because another user's process was requested
+    PermissionDenied,
+    /// The requested process (or thread) doesn't exist (anymore)
+    ProcessGone,
+    /// Any other error, with the raw OS error code (`errno` on unix,
+    /// `GetLastError()` on Windows)
+    Os(i32),
+}
+
+impl fmt::Display for CpuTimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CpuTimeError::Unsupported => f.write_str("CPU time clock is not supported"),
+            CpuTimeError::PermissionDenied => {
+                f.write_str("permission denied to read CPU time clock")
+            }
+            CpuTimeError::ProcessGone => f.write_str("process doesn't exist"),
+            CpuTimeError::Os(code) => write!(f, "can't read CPU time clock (os error {})", code),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for CpuTimeError {}
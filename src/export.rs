@@ -0,0 +1,255 @@
+use std::cmp;
+use std::io::{self, Write};
+use std::time::Duration;
+
+use nanos::to_nanos;
+#[cfg(feature = "serde")]
+use Region;
+use {Event, Report};
+
+// Writes `value` as a JSON string
+fn write_string<W: Write>(out: &mut W, value: &str) -> io::Result<()> {
+    out.write_all(b"\"")?;
+    for c in value.chars() {
+        match c {
+            '"' => out.write_all(b"\\\"")?,
+            '\\' => out.write_all(b"\\\\")?,
+            '\n' => out.write_all(b"\\n")?,
+            '\r' => out.write_all(b"\\r")?,
+            '\t' => out.write_all(b"\\t")?,
+            c if c < ' ' => write!(out, "\\u{:04x}", c as u32)?,
+            c => write!(out, "{}", c)?,
+        }
+    }
+    out.write_all(b"\"")
+}
+
+// WASI and browsers have no process ids, `process::id()` would panic
+#[cfg(any(unix, windows))]
+fn pid() -> u32 {
+    ::std::process::id()
+}
+
+#[cfg(not(any(unix, windows)))]
+fn pid() -> u32 {
+    0
+}
+
+// Writes `field` quoted if it contains a separator, quote or line break
+#[cfg(feature = "serde")]
+fn write_csv_field(out: &mut String, field: &str) {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        out.push('"');
+        out.push_str(&field.replace('"', "\"\""));
+        out.push('"');
+    } else {
+        out.push_str(field);
+    }
+}
+
+// Appends a row per region, depth first, with the names of its parents
+#[cfg(feature = "serde")]
+fn write_csv_rows(out: &mut String, parent: &str, regions: &[Region]) {
+    for region in regions {
+        let path = match parent {
+            "" => region.name.clone(),
+            parent => format!("{}/{}", parent, region.name),
+        };
+        write_csv_field(out, &path);
+        out.push_str(&format!(
+            ",{},{},{},",
+            region.calls,
+            to_nanos(region.inclusive),
+            to_nanos(region.exclusive)
+        ));
+        if let Some(split) = region.split {
+            out.push_str(&format!(
+                "{},{}",
+                to_nanos(split.user),
+                to_nanos(split.system)
+            ));
+        } else {
+            out.push(',');
+        }
+        out.push('\n');
+        write_csv_rows(out, &path, &region.children);
+    }
+}
+
+// Microseconds, the unit of trace timestamps, keeping the nanoseconds
+fn micros(time: Duration) -> String {
+    format!("{}.{:03}", time.as_micros(), time.subsec_nanos() % 1000)
+}
+
+impl Report {
+    /// Writes the events of a profiling session as Chrome Trace Event JSON
+    ///
+    /// The file opens in Perfetto (ui.perfetto.dev) and `chrome://tracing`,
+    /// each region as a slice on one track:
+    ///
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use cpu_time::profile::{region, Session};
+    ///
+    /// let session = Session::start();
+    /// {
+    ///     let _work = region("work");
+    /// }
+    /// let report = session.finish();
+    /// report.write_chrome_trace(File::create("trace.json").unwrap()).unwrap();
+    /// ```
+    ///
+    /// The timestamps are the thread's CPU time since the session started,
+    /// not wall-clock time: waiting doesn't show up, the slices are packed
+    /// together. The process id is that of the current process (0 on WASI
+    /// and in browsers).
+    pub fn write_chrome_trace<W: Write>(&self, mut out: W) -> io::Result<()> {
+        let pid = pid();
+        write!(
+            out,
+            "{{\"displayTimeUnit\":\"ns\",\"traceEvents\":[\
+             {{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":{},\"tid\":0,\
+             \"args\":{{\"name\":\"CPU time\"}}}}",
+            pid
+        )?;
+        for event in &self.events {
+            out.write_all(b",{\"name\":")?;
+            write_string(&mut out, &event.name)?;
+            write!(
+                out,
+                ",\"cat\":\"cpu\",\"ph\":\"X\",\"ts\":{},\"dur\":{},\"pid\":{},\"tid\":0}}",
+                micros(event.start),
+                micros(event.duration),
+                pid
+            )?;
+        }
+        out.write_all(b"]}\n")
+    }
+    /// Returns the events as Chrome Trace Event JSON, see
+    /// `write_chrome_trace()`
+    pub fn to_chrome_trace(&self) -> String {
+        let mut trace = Vec::new();
+        self.write_chrome_trace(&mut trace)
+            .expect("writing to a Vec failed");
+        String::from_utf8(trace).expect("trace is not UTF-8")
+    }
+    /// Writes the events of a profiling session in the file format of
+    /// speedscope (speedscope.app)
+    ///
+    /// The session becomes one "evented" profile, in nanoseconds of the
+    /// thread's CPU time since the session started, with a frame per region
+    /// name:
+    ///
+    /// ```rust,no_run
+    /// use std::fs::File;
+    /// use cpu_time::profile::{region, Session};
+    ///
+    /// let session = Session::start();
+    /// {
+    ///     let _work = region("work");
+    /// }
+    /// let report = session.finish();
+    /// report.write_speedscope(File::create("profile.speedscope.json").unwrap()).unwrap();
+    /// ```
+    pub fn write_speedscope<W: Write>(&self, mut out: W) -> io::Result<()> {
+        let mut frames: Vec<&str> = Vec::new();
+        // Opens and closes as (is_open, frame, at), properly nested
+        let mut changes = Vec::with_capacity(2 * self.events.len());
+        let mut open: Vec<(&Event, usize)> = Vec::new();
+        let mut end = to_nanos(self.total);
+        for event in &self.events {
+            while let Some(&(last, index)) = open.last() {
+                if last.depth < event.depth {
+                    break;
+                }
+                open.pop();
+                changes.push((
+                    false,
+                    index,
+                    to_nanos(last.start.saturating_add(last.duration)),
+                ));
+            }
+            let index = match frames.iter().position(|&frame| frame == event.name) {
+                Some(index) => index,
+                None => {
+                    frames.push(&event.name);
+                    frames.len() - 1
+                }
+            };
+            changes.push((true, index, to_nanos(event.start)));
+            open.push((event, index));
+        }
+        while let Some((last, index)) = open.pop() {
+            changes.push((
+                false,
+                index,
+                to_nanos(last.start.saturating_add(last.duration)),
+            ));
+        }
+        if let Some(&(_, _, at)) = changes.last() {
+            end = cmp::max(end, at);
+        }
+
+        out.write_all(
+            b"{\"$schema\":\"https://www.speedscope.app/file-format-schema.json\",\
+              \"exporter\":\"cpu-time\",\"shared\":{\"frames\":[",
+        )?;
+        for (index, name) in frames.iter().enumerate() {
+            if index > 0 {
+                out.write_all(b",")?;
+            }
+            out.write_all(b"{\"name\":")?;
+            write_string(&mut out, name)?;
+            out.write_all(b"}")?;
+        }
+        write!(
+            out,
+            "]}},\"profiles\":[{{\"type\":\"evented\",\"name\":\"CPU time\",\
+             \"unit\":\"nanoseconds\",\"startValue\":0,\"endValue\":{},\"events\":[",
+            end
+        )?;
+        for (index, &(is_open, frame, at)) in changes.iter().enumerate() {
+            if index > 0 {
+                out.write_all(b",")?;
+            }
+            write!(
+                out,
+                "{{\"type\":\"{}\",\"frame\":{},\"at\":{}}}",
+                if is_open { "O" } else { "C" },
+                frame,
+                at
+            )?;
+        }
+        out.write_all(b"]}]}\n")
+    }
+    /// Returns the events in the file format of speedscope, see
+    /// `write_speedscope()`
+    pub fn to_speedscope(&self) -> String {
+        let mut profile = Vec::new();
+        self.write_speedscope(&mut profile)
+            .expect("writing to a Vec failed");
+        String::from_utf8(profile).expect("profile is not UTF-8")
+    }
+    /// Returns the report as JSON, with all times in nanoseconds
+    ///
+    /// The same as its `Serialize` implementation gives: an object with
+    /// `total`, the region tree as `regions` and `events`. For archiving
+    /// the CPU time breakdown of CI runs and diffing them over time.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        ::serde_json::to_string(self).expect("serializing a report failed")
+    }
+    /// Returns the regions as CSV, one row per region
+    ///
+    /// The columns are `path,calls,inclusive,exclusive,user,system`, the
+    /// times in nanoseconds. The path is the names of the region and its
+    /// parents, outermost first, joined by `/`. `user` and `system` are
+    /// empty unless the session recorded them. The events and `total`
+    /// aren't included.
+    #[cfg(feature = "serde")]
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("path,calls,inclusive,exclusive,user,system\n");
+        write_csv_rows(&mut csv, "", &self.regions);
+        csv
+    }
+}
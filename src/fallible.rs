@@ -0,0 +1,66 @@
+//! Panic-free API
+//!
+//! Nothing reachable from this module panics: clock errors are returned as
+//! `CpuTimeError` and arithmetic saturates instead of overflowing, so it can
+//! be used from `#[no_panic]` functions and in builds that forbid panics.
+//!
+//! Besides these functions the following methods of `ProcessTime` and
+//! `ThreadTime` never panic either: `try_now()`, `try_elapsed()`,
+//! `is_supported()`, `checked_duration_since()`,
+//! `saturating_duration_since()`, `checked_add()`, `checked_sub()`,
+//! `duration()`, `as_nanos_u64()` and the constructors. Avoid `now()`,
+//! `elapsed()`, `duration_since()` and the `+`/`-` operators, which do.
+use std::time::Duration;
+
+use {CpuTimeError, ProcessTime, ThreadTime};
+
+/// Reads the CPU time used by the current process
+///
+/// Same as `ProcessTime::try_now()`.
+pub fn process_time() -> Result<ProcessTime, CpuTimeError> {
+    ProcessTime::try_now()
+}
+
+/// Reads the CPU time used by the current thread
+///
+/// Same as `ThreadTime::try_now()`.
+pub fn thread_time() -> Result<ThreadTime, CpuTimeError> {
+    ThreadTime::try_now()
+}
+
+/// Returns the CPU time used by the current process so far
+pub fn process_cpu_time() -> Result<Duration, CpuTimeError> {
+    ProcessTime::try_now().map(|time| time.duration())
+}
+
+/// Returns the CPU time used by the current thread so far
+pub fn thread_cpu_time() -> Result<Duration, CpuTimeError> {
+    ThreadTime::try_now().map(|time| time.duration())
+}
+
+/// Returns the CPU time used by the current process since `start`
+///
+/// Gives zero if `start` is later than now, e.g. if it was deserialized
+/// from another process.
+pub fn process_elapsed(start: ProcessTime) -> Result<Duration, CpuTimeError> {
+    start.try_elapsed()
+}
+
+/// Returns the CPU time used by the current thread since `start`
+///
+/// Gives zero if `start` is later than now.
+pub fn thread_elapsed(start: ThreadTime) -> Result<Duration, CpuTimeError> {
+    start.try_elapsed()
+}
+
+/// Returns the CPU time used between two timestamps, or zero if `earlier`
+/// is actually later
+pub fn process_duration_between(earlier: ProcessTime, later: ProcessTime) -> Duration {
+    later.saturating_duration_since(earlier)
+}
+
+/// Returns the CPU time used between two timestamps, or zero if `earlier`
+/// is actually later
+pub fn thread_duration_between(earlier: ThreadTime, later: ThreadTime) -> Duration {
+    later.saturating_duration_since(earlier)
+}
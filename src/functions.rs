@@ -0,0 +1,25 @@
+use std::time::Duration;
+
+use {ProcessTime, ThreadTime};
+
+/// Returns the CPU time used by the current process so far
+///
+/// Same as `ProcessTime::now().duration()`.
+///
+/// # Panics
+///
+/// If `ProcessTime::now()` panics, i.e. if the clock can't be read.
+pub fn process_cpu_time() -> Duration {
+    ProcessTime::now().duration()
+}
+
+/// Returns the CPU time used by the current thread so far
+///
+/// Same as `ThreadTime::now().duration()`.
+///
+/// # Panics
+///
+/// If `ThreadTime::now()` panics, i.e. if the clock can't be read.
+pub fn thread_cpu_time() -> Duration {
+    ThreadTime::now().duration()
+}
@@ -0,0 +1,178 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+#[cfg(feature = "futures-core")]
+use futures_core::Stream;
+
+use ThreadTime;
+
+/// Measures the CPU time futures use while they are polled
+///
+/// An executor thread runs many futures in turn, so its CPU time says
+/// little about any of them. `cpu_timed()` adds up the thread CPU time
+/// spent inside each `poll()` of one future, wherever it is polled:
+///
+/// ```rust,edition2018
+/// use cpu_time::FutureExt;
+///
+/// # async fn handle() -> u32 { 7 }
+/// # async fn caller() {
+/// let (response, used) = handle().cpu_timed().await;
+/// println!("handling used {:?} of CPU time", used);
+/// # let _ = response;
+/// # }
+/// ```
+///
+/// Time spent waiting, e.g. for I/O or a timer, isn't counted, and
+/// neither is work the future hands off to other tasks or threads.
+pub trait FutureExt: Future + Sized {
+    /// Returns the future's output together with the CPU time used by
+    /// all of its polls
+    fn cpu_timed(self) -> CpuTimed<Self> {
+        CpuTimed {
+            future: self,
+            used: Duration::new(0, 0),
+        }
+    }
+}
+
+impl<F: Future> FutureExt for F {}
+
+/// A future measuring the CPU time of its polls, returned by
+/// `FutureExt::cpu_timed()`
+///
+/// A poll during which the thread CPU time can't be read isn't counted.
+#[derive(Clone, Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct CpuTimed<F> {
+    future: F,
+    used: Duration,
+}
+
+impl<F> CpuTimed<F> {
+    /// Returns the CPU time used by the polls so far
+    pub fn elapsed(&self) -> Duration {
+        self.used
+    }
+    /// Returns the wrapped future
+    pub fn into_inner(self) -> F {
+        self.future
+    }
+}
+
+impl<F: Future> Future for CpuTimed<F> {
+    type Output = (F::Output, Duration);
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        // `future` is never moved out of a pinned `CpuTimed`
+        let this = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+        let start = ThreadTime::try_now();
+        let poll = future.poll(cx);
+        if let (Ok(start), Ok(now)) = (start, ThreadTime::try_now()) {
+            this.used = this
+                .used
+                .saturating_add(now.saturating_duration_since(start));
+        }
+        poll.map(|output| (output, this.used))
+    }
+}
+
+/// Measures the CPU time streams use to produce each item
+///
+/// With the `futures-core` feature. For streaming pipelines: which
+/// messages take long to decode, parse or compute?
+///
+/// ```rust
+/// use cpu_time::StreamExt;
+///
+/// # fn next<S>(_: &mut S) -> Option<(u32, std::time::Duration)> { None }
+/// # struct Messages;
+/// # impl futures_core::Stream for Messages {
+/// #     type Item = u32;
+/// #     fn poll_next(
+/// #         self: std::pin::Pin<&mut Self>,
+/// #         _: &mut std::task::Context,
+/// #     ) -> std::task::Poll<Option<u32>> {
+/// #         std::task::Poll::Ready(None)
+/// #     }
+/// # }
+/// let mut messages = Messages.cpu_per_item();
+/// // With `futures::StreamExt::next()`: `messages.next().await`
+/// while let Some((message, used)) = next(&mut messages) {
+///     if used.as_millis() > 10 {
+///         println!("message {} took {:?}", message, used);
+///     }
+/// }
+/// ```
+///
+/// The time of an item is that of all polls since the previous item, up
+/// to and including the poll returning it.
+#[cfg(feature = "futures-core")]
+pub trait StreamExt: Stream + Sized {
+    /// Returns a stream of the items together with the CPU time used to
+    /// produce each of them
+    fn cpu_per_item(self) -> CpuPerItem<Self> {
+        CpuPerItem {
+            stream: self,
+            used: Duration::new(0, 0),
+        }
+    }
+}
+
+#[cfg(feature = "futures-core")]
+impl<S: Stream> StreamExt for S {}
+
+/// A stream measuring the CPU time of producing each item, returned by
+/// `StreamExt::cpu_per_item()`
+///
+/// A poll during which the thread CPU time can't be read isn't counted.
+#[cfg(feature = "futures-core")]
+#[derive(Clone, Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct CpuPerItem<S> {
+    stream: S,
+    // Since the last item
+    used: Duration,
+}
+
+#[cfg(feature = "futures-core")]
+impl<S> CpuPerItem<S> {
+    /// Returns the wrapped stream
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+#[cfg(feature = "futures-core")]
+impl<S: Stream> Stream for CpuPerItem<S> {
+    type Item = (S::Item, Duration);
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        // `stream` is never moved out of a pinned `CpuPerItem`
+        let this = unsafe { self.get_unchecked_mut() };
+        let stream = unsafe { Pin::new_unchecked(&mut this.stream) };
+        let start = ThreadTime::try_now();
+        let poll = stream.poll_next(cx);
+        if let (Ok(start), Ok(now)) = (start, ThreadTime::try_now()) {
+            this.used = this
+                .used
+                .saturating_add(now.saturating_duration_since(start));
+        }
+        match poll {
+            Poll::Ready(Some(item)) => {
+                let used = this.used;
+                this.used = Duration::new(0, 0);
+                Poll::Ready(Some((item, used)))
+            }
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.stream.size_hint()
+    }
+}
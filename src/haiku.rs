@@ -0,0 +1,53 @@
+use std::mem;
+use std::ptr;
+use std::time::Duration;
+
+use libc::{bigtime_t, find_thread, get_team_usage_info, get_thread_info};
+use libc::{team_usage_info, thread_info, B_CURRENT_TEAM, B_OK, B_TEAM_USAGE_SELF};
+
+fn to_duration(user_time: bigtime_t, kernel_time: bigtime_t) -> Duration {
+    // resolution: 1us
+    let micros = user_time.saturating_add(kernel_time) as u64;
+    Duration::new(micros / 1_000_000, (micros % 1_000_000) as u32 * 1000)
+}
+
+/// CPU time of the current team as reported by `get_team_usage_info`
+pub fn process() -> Option<Duration> {
+    let mut info: team_usage_info = unsafe { mem::zeroed() };
+    let status = unsafe { get_team_usage_info(B_CURRENT_TEAM, B_TEAM_USAGE_SELF, &mut info) };
+    if status != B_OK {
+        return None;
+    }
+    Some(to_duration(info.user_time, info.kernel_time))
+}
+
+/// User and kernel CPU time of the current team
+pub fn process_split() -> Option<(Duration, Duration)> {
+    let mut info: team_usage_info = unsafe { mem::zeroed() };
+    let status = unsafe { get_team_usage_info(B_CURRENT_TEAM, B_TEAM_USAGE_SELF, &mut info) };
+    if status != B_OK {
+        return None;
+    }
+    Some((
+        to_duration(info.user_time, 0),
+        to_duration(0, info.kernel_time),
+    ))
+}
+
+/// CPU time of the current thread as reported by `get_thread_info`
+pub fn thread() -> Option<Duration> {
+    thread_split().map(|(user, kernel)| user.saturating_add(kernel))
+}
+
+/// User and kernel CPU time of the current thread
+pub fn thread_split() -> Option<(Duration, Duration)> {
+    let mut info: thread_info = unsafe { mem::zeroed() };
+    let status = unsafe { get_thread_info(find_thread(ptr::null()), &mut info) };
+    if status != B_OK {
+        return None;
+    }
+    Some((
+        to_duration(info.user_time, 0),
+        to_duration(0, info.kernel_time),
+    ))
+}
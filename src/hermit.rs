@@ -0,0 +1,390 @@
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use libc::{clock_gettime, clockid_t, timespec};
+
+use nanos::to_nanos;
+use CpuTimeError;
+use Rounding;
+use SplitTime;
+
+/// CPU Time Used by The Whole Process
+///
+/// This is an opaque type similar to `std::time::Instant`.
+/// Use `elapsed()` or `duration_since()` to get meaningful time deltas.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+pub struct ProcessTime(Duration);
+
+/// CPU Time Used by The Current Thread
+///
+/// This is an opaque type similar to `std::time::Instant`.
+/// Use `elapsed()` or `duration_since()` to get meaningful time deltas.
+///
+/// This type is non-thread-shareable (!Sync, !Send) because otherwise it's
+/// to easy to mess up times from different threads. However, you can freely
+/// send Duration's returned by `elapsed()` and `duration_since()`.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+pub struct ThreadTime(
+    Duration,
+    // makes type non-sync and non-send
+    PhantomData<*const ()>,
+);
+
+// libc only defines the wall clocks for Hermit, these ids are the ones
+// the kernel reserves for the CPU-time clocks
+const CLOCK_PROCESS_CPUTIME_ID: clockid_t = 2;
+const CLOCK_THREAD_CPUTIME_ID: clockid_t = 3;
+
+// Not every kernel release implements the CPU-time clocks, so any error is
+// reported as unsupported
+fn clock_time(clock: clockid_t) -> Result<Duration, CpuTimeError> {
+    let mut time = timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    if unsafe { clock_gettime(clock, &mut time) } != 0 {
+        return Err(CpuTimeError::Unsupported);
+    }
+    Ok(Duration::new(time.tv_sec as u64, time.tv_nsec as u32))
+}
+
+impl ProcessTime {
+    /// Zero CPU time, e.g. to initialize statics without reading the clock
+    pub const ZERO: ProcessTime = ProcessTime::from_nanos(0);
+    /// Resolution of `as_raw_ticks()`
+    pub const TICKS_PER_SEC: u64 = 1_000_000_000;
+
+    /// Timestamp of `secs` seconds of CPU time used
+    pub const fn from_secs(secs: u64) -> ProcessTime {
+        ProcessTime::from_duration(Duration::from_secs(secs))
+    }
+    /// Timestamp of `duration` of CPU time used
+    ///
+    /// Meant for tests of code that stores and compares timestamps.
+    pub const fn from_duration(duration: Duration) -> ProcessTime {
+        ProcessTime(duration)
+    }
+    /// Timestamp of `nanos` nanoseconds of CPU time used
+    pub const fn from_nanos(nanos: u64) -> ProcessTime {
+        ProcessTime::from_duration(Duration::from_nanos(nanos))
+    }
+    /// Get current CPU time used by the unikernel application
+    ///
+    /// # Panics
+    ///
+    /// If the kernel doesn't provide the process CPU-time clock. Use
+    /// `try_now()` to handle this case.
+    pub fn now() -> ProcessTime {
+        match ProcessTime::try_now() {
+            Ok(time) => time,
+            Err(_) => panic!("Process CPU time is not supported"),
+        }
+    }
+    /// Get current CPU time used by the unikernel application
+    ///
+    /// Returns `CpuTimeError::Unsupported` if the kernel doesn't
+    /// provide the process CPU-time clock.
+    pub fn try_now() -> Result<ProcessTime, CpuTimeError> {
+        clock_time(CLOCK_PROCESS_CPUTIME_ID).map(ProcessTime)
+    }
+    /// Get current CPU time used by the current process, split into user
+    /// and system time
+    ///
+    /// Hermit has no such split, this always returns
+    /// `CpuTimeError::Unsupported`.
+    pub fn try_now_split() -> Result<SplitTime, CpuTimeError> {
+        Err(CpuTimeError::Unsupported)
+    }
+    /// Get current CPU time used by the current process, split into user
+    /// and system time
+    ///
+    /// # Panics
+    ///
+    /// If the split can't be read. Use `try_now_split()` to handle this
+    /// case.
+    pub fn now_split() -> SplitTime {
+        match ProcessTime::try_now_split() {
+            Ok(split) => split,
+            Err(_) => panic!("Process CPU time split is not supported"),
+        }
+    }
+    /// Returns `true` if process CPU time can be measured in the current
+    /// environment, i.e. if `now()` won't panic
+    ///
+    /// This actually reads the clock, as the kernel may not implement it.
+    pub fn is_supported() -> bool {
+        ProcessTime::try_now().is_ok()
+    }
+    /// Returns the amount of CPU time used from the previous timestamp to now.
+    ///
+    /// A timestamp later than now gives zero.
+    pub fn elapsed(&self) -> Duration {
+        ProcessTime::now().saturating_duration_since(*self)
+    }
+    /// Returns the amount of CPU time used from the previous timestamp to now.
+    ///
+    /// Unlike `elapsed()` this returns an error if the clock can't be read.
+    /// It never panics, a timestamp later than now gives zero.
+    pub fn try_elapsed(&self) -> Result<Duration, CpuTimeError> {
+        ProcessTime::try_now().map(|now| now.saturating_duration_since(*self))
+    }
+    /// Runs `f` and returns its result with the CPU time the process used
+    /// meanwhile
+    ///
+    /// Saves the two timestamps around a single call:
+    ///
+    /// ```rust
+    /// use cpu_time::ProcessTime;
+    ///
+    /// let (sum, time) = ProcessTime::measure(|| (0..1000u64).sum::<u64>());
+    /// println!("{} took {:?}", sum, time);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If the CPU time can't be read, like `now()`.
+    pub fn measure<R, F: FnOnce() -> R>(f: F) -> (R, Duration) {
+        let start = ProcessTime::now();
+        let result = f();
+        (result, start.elapsed())
+    }
+    /// Returns the amount of CPU time used from the previous timestamp.
+    pub fn duration_since(&self, timestamp: ProcessTime) -> Duration {
+        self.0 - timestamp.0
+    }
+    /// Timestamp of `ticks` of CPU time used, in the units of the OS counter
+    /// (see `TICKS_PER_SEC`)
+    pub const fn from_raw_ticks(ticks: u64) -> ProcessTime {
+        ProcessTime::from_nanos(ticks)
+    }
+    /// Returns the amount of CPU time used in the units of the OS counter,
+    /// rounded as requested
+    pub fn as_raw_ticks_rounded(&self, rounding: Rounding) -> u64 {
+        rounding.ticks(self.0, ProcessTime::TICKS_PER_SEC)
+    }
+    /// Returns the amount of CPU time used from the previous timestamp to now
+    /// as seconds.
+    pub fn elapsed_secs_f64(&self) -> f64 {
+        self.elapsed().as_secs_f64()
+    }
+    /// Returns the amount of CPU time used from the previous timestamp to now
+    /// as seconds.
+    pub fn elapsed_secs_f32(&self) -> f32 {
+        self.elapsed().as_secs_f32()
+    }
+    /// Returns the timestamp `duration` of CPU time later than this one, or
+    /// `None` if it can't be represented.
+    pub fn checked_add(&self, duration: Duration) -> Option<ProcessTime> {
+        self.0.checked_add(duration).map(ProcessTime)
+    }
+    /// Returns the timestamp `duration` of CPU time earlier than this one, or
+    /// `None` if that would be before the process started.
+    pub fn checked_sub(&self, duration: Duration) -> Option<ProcessTime> {
+        self.0.checked_sub(duration).map(ProcessTime)
+    }
+    /// Returns the amount of CPU time used from the previous timestamp, or
+    /// zero if that timestamp is later than this one.
+    pub fn saturating_duration_since(&self, timestamp: ProcessTime) -> Duration {
+        self.checked_duration_since(timestamp)
+            .unwrap_or_else(|| Duration::new(0, 0))
+    }
+    /// Returns the amount of CPU time used from the previous timestamp, or
+    /// `None` if that timestamp is later than this one.
+    pub fn checked_duration_since(&self, timestamp: ProcessTime) -> Option<Duration> {
+        self.0.checked_sub(timestamp.0)
+    }
+    /// Returns the amount of CPU time used.
+    pub fn duration(&self) -> Duration {
+        self.0
+    }
+    /// Returns the amount of CPU time used in nanoseconds
+    ///
+    /// Saturates at `u64::MAX`, which is ~584 years.
+    pub fn as_nanos_u64(&self) -> u64 {
+        to_nanos(self.0)
+    }
+    /// Returns the amount of CPU time used in the units of the OS
+    /// counter, see `TICKS_PER_SEC`
+    pub fn as_raw_ticks(&self) -> u64 {
+        self.as_nanos_u64()
+    }
+    /// Returns `true` if no CPU time was used, e.g. for `ZERO`
+    pub fn is_zero(&self) -> bool {
+        self.0 == Duration::from_secs(0)
+    }
+}
+
+impl ThreadTime {
+    /// Zero CPU time, e.g. to initialize statics without reading the clock
+    pub const ZERO: ThreadTime = ThreadTime::from_nanos(0);
+    /// Resolution of `as_raw_ticks()`
+    pub const TICKS_PER_SEC: u64 = 1_000_000_000;
+
+    /// Timestamp of `secs` seconds of CPU time used
+    pub const fn from_secs(secs: u64) -> ThreadTime {
+        ThreadTime::from_duration(Duration::from_secs(secs))
+    }
+    /// Timestamp of `duration` of CPU time used
+    ///
+    /// Meant for tests of code that stores and compares timestamps.
+    pub const fn from_duration(duration: Duration) -> ThreadTime {
+        ThreadTime(duration, PhantomData)
+    }
+    /// Timestamp of `nanos` nanoseconds of CPU time used
+    pub const fn from_nanos(nanos: u64) -> ThreadTime {
+        ThreadTime::from_duration(Duration::from_nanos(nanos))
+    }
+    /// Get current CPU time used by the current thread
+    ///
+    /// # Panics
+    ///
+    /// If the kernel doesn't provide the thread CPU-time clock. Use
+    /// `try_now()` to handle this case.
+    pub fn now() -> ThreadTime {
+        match ThreadTime::try_now() {
+            Ok(time) => time,
+            Err(_) => panic!("Thread CPU time is not supported"),
+        }
+    }
+    /// Get current CPU time used by the current thread
+    ///
+    /// Returns `CpuTimeError::Unsupported` if the kernel doesn't
+    /// provide the thread CPU-time clock.
+    pub fn try_now() -> Result<ThreadTime, CpuTimeError> {
+        clock_time(CLOCK_THREAD_CPUTIME_ID).map(|d| ThreadTime(d, PhantomData))
+    }
+    /// Get current CPU time used by the current thread, split into user
+    /// and system time
+    ///
+    /// Hermit has no such split, this always returns
+    /// `CpuTimeError::Unsupported`.
+    pub fn try_now_split() -> Result<SplitTime, CpuTimeError> {
+        Err(CpuTimeError::Unsupported)
+    }
+    /// Get current CPU time used by the current thread, split into user
+    /// and system time
+    ///
+    /// # Panics
+    ///
+    /// If the split can't be read. Use `try_now_split()` to handle this
+    /// case.
+    pub fn now_split() -> SplitTime {
+        match ThreadTime::try_now_split() {
+            Ok(split) => split,
+            Err(_) => panic!("Thread CPU time split is not supported"),
+        }
+    }
+    /// Returns `true` if thread CPU time can be measured in the current
+    /// environment, i.e. if `now()` won't panic
+    ///
+    /// This actually reads the clock, as the kernel may not implement it.
+    pub fn is_supported() -> bool {
+        ThreadTime::try_now().is_ok()
+    }
+    /// Returns the amount of CPU time used by the current thread
+    /// from the previous timestamp to now.
+    ///
+    /// A timestamp later than now gives zero.
+    pub fn elapsed(&self) -> Duration {
+        ThreadTime::now().saturating_duration_since(*self)
+    }
+    /// Returns the amount of CPU time used by the current thread
+    /// from the previous timestamp to now.
+    ///
+    /// Unlike `elapsed()` this returns an error if the clock can't be read.
+    /// It never panics, a timestamp later than now gives zero.
+    pub fn try_elapsed(&self) -> Result<Duration, CpuTimeError> {
+        ThreadTime::try_now().map(|now| now.saturating_duration_since(*self))
+    }
+    /// Runs `f` and returns its result with the CPU time the current thread used
+    /// meanwhile
+    ///
+    /// Saves the two timestamps around a single call:
+    ///
+    /// ```rust
+    /// use cpu_time::ThreadTime;
+    ///
+    /// let (sum, time) = ThreadTime::measure(|| (0..1000u64).sum::<u64>());
+    /// println!("{} took {:?}", sum, time);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If the CPU time can't be read, like `now()`.
+    pub fn measure<R, F: FnOnce() -> R>(f: F) -> (R, Duration) {
+        let start = ThreadTime::now();
+        let result = f();
+        (result, start.elapsed())
+    }
+    /// Returns the amount of CPU time used by the current thread
+    /// from the previous timestamp.
+    pub fn duration_since(&self, timestamp: ThreadTime) -> Duration {
+        self.0 - timestamp.0
+    }
+    /// Timestamp of `ticks` of CPU time used by the current thread, in the
+    /// units of the OS counter (see `TICKS_PER_SEC`)
+    pub const fn from_raw_ticks(ticks: u64) -> ThreadTime {
+        ThreadTime::from_nanos(ticks)
+    }
+    /// Returns the amount of CPU time used by the current thread in the
+    /// units of the OS counter, rounded as requested
+    pub fn as_raw_ticks_rounded(&self, rounding: Rounding) -> u64 {
+        rounding.ticks(self.0, ThreadTime::TICKS_PER_SEC)
+    }
+    /// Returns the amount of CPU time used by the current thread
+    /// from the previous timestamp to now as seconds.
+    pub fn elapsed_secs_f64(&self) -> f64 {
+        self.elapsed().as_secs_f64()
+    }
+    /// Returns the amount of CPU time used by the current thread
+    /// from the previous timestamp to now as seconds.
+    pub fn elapsed_secs_f32(&self) -> f32 {
+        self.elapsed().as_secs_f32()
+    }
+    /// Returns the timestamp `duration` of CPU time later than this one, or
+    /// `None` if it can't be represented.
+    pub fn checked_add(&self, duration: Duration) -> Option<ThreadTime> {
+        self.0
+            .checked_add(duration)
+            .map(|d| ThreadTime(d, PhantomData))
+    }
+    /// Returns the timestamp `duration` of CPU time earlier than this one, or
+    /// `None` if that would be before the thread started.
+    pub fn checked_sub(&self, duration: Duration) -> Option<ThreadTime> {
+        self.0
+            .checked_sub(duration)
+            .map(|d| ThreadTime(d, PhantomData))
+    }
+    /// Returns the amount of CPU time used by the current thread
+    /// from the previous timestamp, or zero if that timestamp is later than
+    /// this one.
+    pub fn saturating_duration_since(&self, timestamp: ThreadTime) -> Duration {
+        self.checked_duration_since(timestamp)
+            .unwrap_or_else(|| Duration::new(0, 0))
+    }
+    /// Returns the amount of CPU time used by the current thread
+    /// from the previous timestamp, or `None` if that timestamp is later
+    /// than this one.
+    pub fn checked_duration_since(&self, timestamp: ThreadTime) -> Option<Duration> {
+        self.0.checked_sub(timestamp.0)
+    }
+    /// Returns the amount of CPU time used by the current thread.
+    pub fn duration(&self) -> Duration {
+        self.0
+    }
+    /// Returns the amount of CPU time used by the current thread in nanoseconds
+    ///
+    /// Saturates at `u64::MAX`, which is ~584 years.
+    pub fn as_nanos_u64(&self) -> u64 {
+        to_nanos(self.0)
+    }
+    /// Returns the amount of CPU time used by the current thread in the units of the OS
+    /// counter, see `TICKS_PER_SEC`
+    pub fn as_raw_ticks(&self) -> u64 {
+        self.as_nanos_u64()
+    }
+    /// Returns `true` if no CPU time was used, e.g. for `ZERO`
+    pub fn is_zero(&self) -> bool {
+        self.0 == Duration::from_secs(0)
+    }
+}
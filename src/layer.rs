@@ -0,0 +1,178 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use tower_layer::Layer;
+use tower_service::Service;
+
+use {report, CpuTimeError, ThreadTime};
+
+/// The CPU time used to handle a request, in the extensions of responses
+/// of services wrapped with `CpuTimeLayer`
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash, Default)]
+pub struct RequestCpuTime(pub Duration);
+
+/// Responses that can carry the CPU time of their request
+///
+/// Implemented for `http::Response`, which gets a `RequestCpuTime`
+/// extension.
+pub trait WithCpuTime {
+    /// Attaches `time`, the CPU time used to produce the response
+    fn set_cpu_time(&mut self, time: Duration);
+}
+
+impl<B> WithCpuTime for http::Response<B> {
+    fn set_cpu_time(&mut self, time: Duration) {
+        self.extensions_mut().insert(RequestCpuTime(time));
+    }
+}
+
+/// A Tower layer measuring the CPU time of each request
+///
+/// For services that bill or limit CPU time per request. The time is the
+/// thread CPU time of `call()` and of each poll of the response future,
+/// wherever the executor runs it:
+///
+/// ```rust
+/// # extern crate cpu_time;
+/// # extern crate http;
+/// # extern crate tower_layer;
+/// # extern crate tower_service;
+/// use cpu_time::{CpuTimeLayer, RequestCpuTime};
+/// use tower_layer::Layer;
+///
+/// # fn main() {}
+/// # fn wrap<S>(service: S) -> cpu_time::CpuTimeService<S> {
+/// // Reports "http" and the time to `cpu_time::report()`
+/// let service = CpuTimeLayer::new("http").layer(service);
+/// # service
+/// # }
+/// # fn handle(response: http::Response<()>) {
+/// // And the response has it too
+/// if let Some(&RequestCpuTime(used)) = response.extensions().get() {
+///     println!("request used {:?}", used);
+/// }
+/// # }
+/// ```
+///
+/// When the response is ready, it gets the time (see `WithCpuTime`) and
+/// the label and time are passed to the sink, the global reporter of
+/// `set_reporter()` by default. Failed requests are reported too. Work
+/// done in other tasks or threads isn't counted, and neither is streaming
+/// the response body after the response is returned.
+#[derive(Copy, Clone, Debug)]
+pub struct CpuTimeLayer {
+    label: &'static str,
+    sink: fn(&str, Duration),
+}
+
+impl CpuTimeLayer {
+    /// Reports the requests as `label` to the global reporter
+    pub fn new(label: &'static str) -> CpuTimeLayer {
+        CpuTimeLayer::with_sink(label, report)
+    }
+    /// Reports the requests as `label` to `sink`, e.g. `accumulate()` or
+    /// a function feeding a metrics library
+    pub fn with_sink(label: &'static str, sink: fn(&str, Duration)) -> CpuTimeLayer {
+        CpuTimeLayer { label, sink }
+    }
+}
+
+impl<S> Layer<S> for CpuTimeLayer {
+    type Service = CpuTimeService<S>;
+
+    fn layer(&self, inner: S) -> CpuTimeService<S> {
+        CpuTimeService {
+            inner,
+            label: self.label,
+            sink: self.sink,
+        }
+    }
+}
+
+/// A service measuring the CPU time of each request, created by
+/// `CpuTimeLayer`
+#[derive(Clone, Debug)]
+pub struct CpuTimeService<S> {
+    inner: S,
+    label: &'static str,
+    sink: fn(&str, Duration),
+}
+
+impl<S> CpuTimeService<S> {
+    /// Returns the wrapped service
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+fn used_since(start: Result<ThreadTime, CpuTimeError>) -> Duration {
+    match (start, ThreadTime::try_now()) {
+        (Ok(start), Ok(now)) => now.saturating_duration_since(start),
+        // Not counted if the clock can't be read
+        _ => Duration::new(0, 0),
+    }
+}
+
+impl<S, R> Service<R> for CpuTimeService<S>
+where
+    S: Service<R>,
+    S::Response: WithCpuTime,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = CpuTimeFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> CpuTimeFuture<S::Future> {
+        let start = ThreadTime::try_now();
+        let future = self.inner.call(request);
+        CpuTimeFuture {
+            future,
+            used: used_since(start),
+            label: self.label,
+            sink: self.sink,
+        }
+    }
+}
+
+/// The response future of `CpuTimeService`
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct CpuTimeFuture<F> {
+    future: F,
+    used: Duration,
+    label: &'static str,
+    sink: fn(&str, Duration),
+}
+
+impl<F, T, E> Future for CpuTimeFuture<F>
+where
+    F: Future<Output = Result<T, E>>,
+    T: WithCpuTime,
+{
+    type Output = Result<T, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<T, E>> {
+        // `future` is never moved out of a pinned `CpuTimeFuture`
+        let this = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+        let start = ThreadTime::try_now();
+        let poll = future.poll(cx);
+        this.used = this.used.saturating_add(used_since(start));
+        match poll {
+            Poll::Ready(mut result) => {
+                if let Ok(ref mut response) = result {
+                    response.set_cpu_time(this.used);
+                }
+                (this.sink)(this.label, this.used);
+                Poll::Ready(result)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
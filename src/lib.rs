@@ -18,23 +18,866 @@
 //! println!(" {:?}", cpu_time);
 //!
 //! ```
+//!
+//! # Platform Support
+//!
+//! On unix systems CPU time is read with `clock_gettime` using
+//! `CLOCK_PROCESS_CPUTIME_ID` and `CLOCK_THREAD_CPUTIME_ID`. This covers
+//! Linux, Android, macOS, FreeBSD, NetBSD, OpenBSD, DragonFly BSD, illumos,
+//! Solaris, Haiku, AIX, QNX Neutrino, GNU/Hurd and VxWorks (RTP
+//! applications).
+//!
+//! On 32-bit Linux (x86 and ARM) the `clock_gettime64` system call is tried
+//! first, so readings stay correct even where the libc `timespec` has 32-bit
+//! seconds.
+//!
+//! On iOS, tvOS and watchOS the Mach `task_info()` and `thread_info()` calls
+//! are preferred because they are always permitted in sandboxed apps, and
+//! `clock_gettime` is only used if they fail.
+//!
+//! On macOS `clock_gettime` is looked up at runtime, as it only exists since
+//! 10.12. Binaries built for older deployment targets fall back to
+//! `getrusage()` for the process and `thread_info()` for the thread.
+//!
+//! With the `rustix` feature the clocks are read through `rustix` instead of
+//! raw `libc` calls. With `default-features = false` (and `std` if needed)
+//! the crate then has no libc dependency on Linux and Android, which, like
+//! with `raw-syscalls` below, disables the `getrusage` fallback. NetBSD,
+//! illumos and Solaris keep using `libc`, as `rustix` doesn't expose
+//! CPU-time clocks there.
+//!
+//! The `raw-syscalls` feature issues the `clock_gettime` system call directly
+//! on Linux (x86_64, aarch64 and riscv64). Together with
+//! `default-features = false`, which drops the `libc` feature, the crate
+//! has no libc dependency at all, e.g. for static no-libc builds. This skips
+//! the vDSO, so every reading is a real system call, and disables the
+//! `getrusage` fallback.
+//!
+//! Without the default `std` feature the crate is `#![no_std]` and only
+//! depends on `core` (`CpuTimeError` then doesn't implement
+//! `std::error::Error`). Keep the `libc` feature enabled unless you use
+//! `raw-syscalls` or `rustix`:
+//! `default-features = false, features = ["libc"]`.
+//!
+//! On Linux the `rusage-thread` feature reads thread time with
+//! `getrusage(RUSAGE_THREAD)` instead of `CLOCK_THREAD_CPUTIME_ID` (with
+//! the clock as fallback), for kernels and containers where it behaves
+//! better. Its resolution is microseconds.
+//!
+//! If a clock is not available at runtime, process time falls back to
+//! `getrusage(RUSAGE_SELF)` and thread time to a platform-specific call:
+//!
+//! * OpenBSD, AIX and Android: `getrusage(RUSAGE_THREAD)`, on Android this
+//!   covers old devices where the thread clock returns `EINVAL`
+//! * illumos and Solaris: `gethrvtime()`
+//! * QNX Neutrino: the per-thread `ClockTime()` kernel call
+//! * Haiku: `get_thread_info()`, and `get_team_usage_info()` for the process
+//! * VxWorks has neither, only the POSIX clocks are used
+//!
+//! Emscripten goes through the same `clock_gettime` path, but its runtime
+//! emulates both CPU-time clocks with `emscripten_get_now()`, so on that
+//! target the values are wall-clock time, including time spent sleeping.
+//!
+//! `ProcessTime::now_for(pid)` measures another process, through the clock
+//! of `clock_getcpuclockid()` on Linux, Android, FreeBSD and DragonFly
+//! (with the `libc` feature), and `proc_pid_rusage()` on macOS. On Linux
+//! and Android `/proc/<pid>/stat` is the fallback, in clock ticks.
+//! `elapsed()` of such a timestamp reads the same process again.
+//!
+//! `ThreadTime::for_thread()` and `for_pthread()` read another thread's
+//! clock from `pthread_getcpuclockid()`, on the same platforms as
+//! `clock_getcpuclockid()`.
+//!
+//! On Linux 5.3+ a `ProcessHandle` attaches to a process with
+//! `pidfd_open()`, `ProcessTime::now_for_handle()` then fails with
+//! `CpuTimeError::ProcessGone` once it exited, instead of measuring another
+//! process that got the same pid. On Windows `ProcessHandle` owns a handle
+//! from `OpenProcess`.
+//!
+//! The `procfs` module parses `/proc/<pid>/stat` into a `Stat` with the
+//! process' own and children's times, its start time and last CPU, for
+//! monitoring tools on Linux and Android.
+//!
+//! `ProcessTime::now_split()` returns a `SplitTime` with separate user and
+//! system time, from `getrusage()` on unix and `GetProcessTimes` on
+//! Windows. `ThreadTime::now_split()` does the same for the current thread
+//! with `getrusage(RUSAGE_THREAD)`, `thread_info()` or `GetThreadTimes`.
+//!
+//! `ChildrenTime` reads `getrusage(RUSAGE_CHILDREN)`, the CPU time of
+//! terminated and waited-for child processes, for build tools and test
+//! runners. It's available on unix with the `libc` feature, except on Haiku
+//! and VxWorks.
+//!
+//! `ChildExt::wait_with_cpu_time()` waits for one `std::process::Child`
+//! and returns its exit status and `SplitTime` (with the `std` feature). On
+//! unix it uses `wait4()`, except on Haiku, illumos, Solaris and VxWorks;
+//! `wait4()` and `try_wait4()` expose it for custom process supervisors,
+//! with the reaped child's whole `ResourceUsage`. On Windows it reads
+//! `GetProcessTimes` of the exited child's handle, which
+//! `ChildExt::cpu_time()` can do even after `Child::wait()`. `ChildSet`
+//! reaps several children as they exit and adds up their CPU time.
+//!
+//! `CommandExt::spawn_with_cpu_limit()` starts a process whose CPU time is
+//! limited, with `RLIMIT_CPU` on unix (also as `CommandExt::cpu_limit()`)
+//! and a Job Object on Windows.
+//!
+//! `bench_command()` runs a command repeatedly and returns the mean,
+//! median and standard deviation of its CPU time, like a CPU-time
+//! `hyperfine`.
+//!
+//! `CpuLimit::current()` reads the `RLIMIT_CPU` limits of the process and
+//! `remaining_cpu_budget()` how much CPU time is left until `SIGXCPU` (on
+//! unix with the `libc` feature, except on Haiku and VxWorks).
+//! `CpuLimit::set()` changes them, and `catch_sigxcpu()` turns the signal
+//! into a flag for `sigxcpu_received()`, with an optional `on_sigxcpu()`
+//! hook.
+//!
+//! `CpuAlarm` fires once the process or a thread has used a given amount of
+//! CPU time, from a POSIX timer on its CPU-time clock (Linux and Android).
+//!
+//! `ProcessTime::measure()` and `ThreadTime::measure()` run a closure and
+//! return its result with the CPU time used meanwhile.
+//!
+//! `run_with_cpu_budget()` runs a closure and returns `BudgetExceeded` if
+//! it used more CPU time than allowed. The closure gets a `CpuBudget` to
+//! check along the way and give up early.
+//!
+//! `CpuStopwatch` adds up the CPU time of several intervals, with
+//! `pause()` and `resume()`, on the process or the thread clock (either
+//! `CpuClock`). With the `std` feature it also records laps.
+//!
+//! `CpuScope::new("label")` measures the thread (or with `process()` the
+//! process) CPU time until it's dropped and reports it to a sink: a
+//! closure, or the global reporter of `set_reporter()`, which prints to
+//! stderr by default. The `log` feature adds `log_reporter()`, emitting
+//! `log` records.
+//!
+//! `time_block!("label", { ... })` adds the thread CPU time of a block to a
+//! global accumulator of that label, which `accumulated()` and
+//! `accumulators()` read (with the `std` feature). `counter("label")`
+//! adds to one from anywhere, and `report_at_exit()` prints a table of all
+//! of them when the process exits (on unix with the `libc` feature and on
+//! Windows).
+//!
+//! The `macros` feature adds the `#[cpu_time]` attribute, which measures
+//! every call of a function and reports it like `CpuScope::new()`, with
+//! the function's path as the label.
+//!
+//! The `profile` module records nested regions of code into a tree of
+//! inclusive and exclusive CPU times, like a minimal instrumenting
+//! profiler (with the `std` feature).
+//! Sessions and the accumulators produce a `Report`, which prints as an
+//! aligned table of calls, total and mean CPU time, percentages and, if
+//! recorded, user and system time.
+//! `Report::write_chrome_trace()` exports the regions a session entered
+//! as Chrome Trace Event JSON, for Perfetto and `chrome://tracing`, and
+//! `write_speedscope()` in the file format of speedscope.
+//! With the `serde` feature `Report` implements `Serialize` and
+//! `Deserialize`, `to_json()` writes it with `serde_json` and `to_csv()`
+//! gives CPU time breakdowns to archive in CI. `Report::merge()` combines
+//! the reports of several worker processes into one. On unix
+//! `SharedCounters` adds up CPU time by label in memory shared with forked
+//! workers, for the parent to read.
+//!
+//! `FutureExt::cpu_timed()` adds up the thread CPU time used inside each
+//! poll of a future, so async code can attribute CPU time to an operation
+//! rather than to the executor threads it ran on. With the `futures-core`
+//! feature `StreamExt::cpu_per_item()` pairs each item of a stream with the
+//! CPU time used to produce it. The `task` module keeps the CPU time of
+//! each running task, on any executor (with the `std` feature), and with
+//! the `tokio` feature of Tokio tasks by their id.
+//!
+//! With the `tower` feature `CpuTimeLayer` measures the CPU time of each
+//! request of a Tower service, for the global reporter and as a
+//! `RequestCpuTime` extension of HTTP responses.
+//! `RequestCpuLayer` is the same for axum and hyper, with a `RequestCpu`
+//! request extension that handlers can read while they run (an extractor
+//! with the `axum` feature) and an `x-cpu-time` response header.
+//!
+//! `ResourceUsage` returns everything `getrusage()` reports (CPU time, peak
+//! RSS, page faults, context switches and block I/O) for the process, its
+//! children or the current thread.
+//!
+//! `ContextSwitches` counts how often the process or the current thread was
+//! switched out, from `getrusage()` on unix and `NtQuerySystemInformation`
+//! on Windows (which doesn't tell voluntary and involuntary ones apart).
+//!
+//! `processes()` and `ProcessesSnapshot` list every visible process of the
+//! system with its name and CPU time, for `top`-like tools, on Linux,
+//! Android, macOS and Windows (with the `std` feature).
+//! `process_tree_time()` sums up a process and all its descendants, with
+//! their reaped children if requested, `process_group_time()` the
+//! processes of a process group and `process_time_by_uid()` those of each
+//! user (both not on Windows). Subtracting two snapshots gives a
+//! `ProcessesDelta` with the CPU time each process used in between.
+//!
+//! `ThreadsSnapshot` lists every thread of the process with its id, name
+//! and CPU time, from `/proc/self/task` on Linux and Android,
+//! `task_threads()` on Apple platforms and `NtQuerySystemInformation` on
+//! Windows (with the `std` feature). Subtracting two snapshots gives a
+//! `ThreadsDelta`, likewise.
+//!
+//! `CpuTimes` mirrors POSIX `times()`: user and system time of the process
+//! and of its reaped children from a single call, for `time(1)`-like
+//! tools.
+//!
+//! On Windows `GetProcessTimes` and `GetThreadTimes` are used.
+//! `ThreadTime::now_for_id()` opens another thread by id with `OpenThread`
+//! for the latter.
+//! `ProcessCycles` reads `QueryProcessCycleTime` instead, which counts CPU
+//! cycles and so isn't limited to the scheduler's 15.6 ms granularity.
+//! `ThreadCycles` does the same with `QueryThreadCycleTime`, and
+//! `CycleRate::calibrate()` estimates how many cycles make a second.
+//! `SystemTimes` reads the machine-wide idle, kernel and user time from
+//! `GetSystemTimes`, for CPU utilization. They count
+//! in 100 ns ticks, which `as_raw_ticks()` and `from_raw_ticks()` expose;
+//! `as_raw_ticks_rounded()` takes a `Rounding` mode, so that totals of many
+//! converted samples aren't biased by truncation.
+//!
+//! On WASI the CPU-time clocks of `clock_time_get` are used. Runtimes may
+//! not implement them, in which case `try_now()` returns
+//! `CpuTimeError::Unsupported`.
+//!
+//! On Hermit `clock_gettime` is used with the kernel's CPU-time clock ids.
+//!
+//! On every platform `now()` and `elapsed()` panic if the clock can't be
+//! read. Use `try_now()` and `try_elapsed()` to get a `CpuTimeError`
+//! instead, e.g. to degrade gracefully when a sandbox denies the clock.
+//! Both `elapsed()` and `try_elapsed()` give zero for a timestamp later
+//! than now.
+//! Readings of other processes and threads (`now_for()` and friends)
+//! always return a `Result`.
+//!
+//! The `fallible` module goes further: no code path reachable from it can
+//! panic, errors always surface as `Result` and arithmetic saturates, for
+//! `#[no_panic]` and safety-critical builds.
+//!
+//! In browsers (`wasm32-unknown-unknown` with the `wasm-bindgen` feature)
+//! there is no CPU-time clock at all, so both timers approximate it with
+//! `performance.now()`. That's wall-clock time, which
+//! `Capabilities::wall_clock()` reports, so benchmark crates can build for
+//! the web and label their numbers accordingly.
+//!
+//! With the `serde` feature `ProcessTime` and `ThreadTime` are serialized as
+//! the number of nanoseconds of CPU time. `SplitTime`, the snapshots
+//! (`ThreadsSnapshot`, `ProcessesSnapshot`, `ResourceUsage`, `CpuTimes`,
+//! `ContextSwitches` and `SystemTimes`) and `Report` are serialized as
+//! structs of their fields, times in nanoseconds too.
+//!
+//! The `rkyv` and `bincode` (2.x) features store the timestamps the same
+//! way, as a plain `u64`, for high-frequency measurement streams.
+//! `SplitTime` and the snapshots are archived and encoded field by field,
+//! with the crates' own `Duration` formats.
+//!
+//! The `defmt` feature implements `defmt::Format` for the timestamps,
+//! `CpuDuration` and `CpuTimeError`, for logging on embedded targets.
+//!
+//! The `chrono` and `time` features add `TryFrom` conversions between
+//! `CpuDuration` and `chrono::Duration` or `time::Duration` (in both
+//! directions, negative durations fail). With `chrono`,
+//! `CpuDuration::iso8601()` formats for reports that want ISO 8601.
+//!
+//! The `arbitrary` and `proptest` features implement their `Arbitrary`
+//! traits for the timestamps, `SplitTime` and the snapshots, for fuzzing
+//! and property tests.
+//!
+//! Under Miri, which can't run the FFI calls, a deterministic shim is used
+//! instead: every reading advances a virtual CPU clock by 1 µs, both for the
+//! current thread and for the process.
+//!
+//! Other targets (e.g. `wasm32-unknown-unknown`) have no CPU-time clock and
+//! the crate exports no timer types there. Enable the `portable` feature to
+//! get `ProcessTime` and `ThreadTime` anyway: their `try_now()` always
+//! returns `CpuTimeError::Unsupported`, so libraries can depend on this
+//! crate unconditionally.
 
 #![warn(missing_debug_implementations)]
 #![warn(missing_docs)]
+#![cfg_attr(not(feature = "std"), no_std)]
 
-#[cfg(unix)]
+// Everything the crate needs is in `core`, so `std::` paths are kept working
+// in `no_std` builds by aliasing
+#[cfg(not(feature = "std"))]
+extern crate core as std;
+
+#[cfg(feature = "arbitrary")]
+extern crate arbitrary;
+#[cfg(feature = "axum")]
+extern crate axum_core;
+#[cfg(feature = "chrono")]
+extern crate chrono;
+#[cfg(all(any(unix, target_os = "hermit"), feature = "libc"))]
 extern crate libc;
+#[cfg(all(
+    unix,
+    feature = "rustix",
+    not(any(target_os = "netbsd", target_os = "illumos", target_os = "solaris"))
+))]
+extern crate rustix;
+#[cfg(feature = "bincode")]
+#[macro_use]
+extern crate bincode;
+#[cfg(feature = "macros")]
+extern crate cpu_time_macros;
+#[cfg(feature = "defmt")]
+extern crate defmt;
+#[cfg(feature = "futures-core")]
+extern crate futures_core;
+#[cfg(feature = "tower")]
+extern crate http;
+#[cfg(feature = "log")]
+extern crate log;
+#[cfg(feature = "proptest")]
+extern crate proptest;
+#[cfg(feature = "rkyv")]
+extern crate rkyv;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "serde")]
+extern crate serde_json;
+#[cfg(feature = "time")]
+extern crate time;
+#[cfg(feature = "tokio")]
+extern crate tokio;
+#[cfg(feature = "tower")]
+extern crate tower_layer;
+#[cfg(feature = "tower")]
+extern crate tower_service;
+#[cfg(target_os = "wasi")]
+extern crate wasi;
+#[cfg(all(
+    target_arch = "wasm32",
+    target_os = "unknown",
+    feature = "wasm-bindgen"
+))]
+extern crate wasm_bindgen;
 #[cfg(windows)]
 extern crate winapi;
 
+// Items that need `ProcessTime` and `ThreadTime`, which exist on the targets
+// with a native backend and everywhere with the `portable` feature. `stub:`
+// items are only for the portable stand-ins, `or(...):` ones also compile
+// without the timer types if the condition holds.
+macro_rules! cfg_timers {
+    (@native $native:tt stub: $($item:item)*) => {
+        $(
+            #[cfg(all(feature = "portable", not(any $native)))]
+            $item
+        )*
+    };
+    (@native $native:tt or $or:tt: $($item:item)*) => {
+        $(
+            #[cfg(any(feature = "portable", any $native, any $or))]
+            $item
+        )*
+    };
+    (@native $native:tt $($item:item)*) => {
+        $(
+            #[cfg(any(feature = "portable", any $native))]
+            $item
+        )*
+    };
+    ($($body:tt)*) => {
+        cfg_timers! {
+            @native (
+                unix,
+                windows,
+                target_os = "wasi",
+                target_os = "hermit",
+                all(
+                    target_arch = "wasm32",
+                    target_os = "unknown",
+                    feature = "wasm-bindgen"
+                ),
+                miri
+            )
+            $($body)*
+        }
+    };
+}
+
+cfg_timers! {
+    #[cfg(feature = "arbitrary")]
+    mod arbitrary_impls;
+}
+#[cfg(any(
+    all(
+        unix,
+        feature = "libc",
+        feature = "std",
+        not(any(
+            target_os = "haiku",
+            target_os = "illumos",
+            target_os = "solaris",
+            target_os = "vxworks"
+        )),
+        not(miri)
+    ),
+    all(windows, feature = "std", not(miri))
+))]
+mod bench;
+cfg_timers! {
+    #[cfg(feature = "bincode")]
+    mod bincode_impls;
+    mod budget;
+    mod capabilities;
+}
+#[cfg(any(
+    all(
+        unix,
+        feature = "libc",
+        feature = "std",
+        not(any(
+            target_os = "haiku",
+            target_os = "illumos",
+            target_os = "solaris",
+            target_os = "vxworks"
+        )),
+        not(miri)
+    ),
+    all(windows, feature = "std", not(miri))
+))]
+mod child;
+#[cfg(all(
+    unix,
+    feature = "libc",
+    not(any(target_os = "haiku", target_os = "vxworks")),
+    not(miri)
+))]
+mod children;
+#[cfg(feature = "chrono")]
+mod chrono_impls;
+cfg_timers! {
+    mod clock;
+}
+#[cfg(any(
+    all(
+        unix,
+        feature = "libc",
+        feature = "std",
+        not(any(
+            target_os = "haiku",
+            target_os = "illumos",
+            target_os = "solaris",
+            target_os = "vxworks"
+        )),
+        not(miri)
+    ),
+    all(windows, feature = "std", not(miri))
+))]
+mod command;
+#[cfg(any(
+    all(
+        unix,
+        feature = "libc",
+        not(any(target_os = "haiku", target_os = "vxworks")),
+        not(miri)
+    ),
+    all(windows, feature = "std", not(miri))
+))]
+mod context_switches;
+#[cfg(all(
+    any(target_os = "linux", target_os = "android"),
+    feature = "libc",
+    not(miri)
+))]
+mod cpu_alarm;
+mod cpu_duration;
+#[cfg(all(
+    unix,
+    feature = "libc",
+    not(any(target_os = "haiku", target_os = "vxworks")),
+    not(miri)
+))]
+mod cpu_limit;
+#[cfg(all(windows, not(miri)))]
+mod cycles;
+cfg_timers! {
+    #[cfg(feature = "defmt")]
+    mod defmt_impls;
+}
+mod error;
+cfg_timers! {
+    #[cfg(feature = "std")]
+    mod export;
+    pub mod fallible;
+    mod functions;
+    mod future;
+    #[cfg(all(feature = "tower", feature = "std"))]
+    mod layer;
+}
+cfg_timers! {
+    or(feature = "serde"):
+    mod nanos;
+}
+cfg_timers! {
+    mod ops;
+}
+#[cfg(all(target_os = "linux", feature = "libc", not(miri)))]
+mod process_handle;
+#[cfg(all(
+    feature = "std",
+    any(
+        all(any(target_os = "linux", target_os = "android"), feature = "libc"),
+        target_os = "macos",
+        windows
+    ),
+    not(miri)
+))]
+mod processes;
+#[cfg(all(
+    feature = "libc",
+    feature = "std",
+    any(target_os = "linux", target_os = "android"),
+    not(miri)
+))]
+pub mod procfs;
+cfg_timers! {
+    #[cfg(feature = "std")]
+    pub mod profile;
+    #[cfg(feature = "proptest")]
+    mod proptest_impls;
+    #[cfg(feature = "std")]
+    mod registry;
+    #[cfg(feature = "std")]
+    mod report;
+    #[cfg(all(feature = "tower", feature = "std"))]
+    mod request;
+}
+#[cfg(all(
+    unix,
+    feature = "libc",
+    not(any(target_os = "haiku", target_os = "vxworks")),
+    not(miri)
+))]
+mod resource_usage;
+cfg_timers! {
+    #[cfg(feature = "rkyv")]
+    mod rkyv_impls;
+}
+mod rounding;
+cfg_timers! {
+    mod scope;
+    #[cfg(feature = "serde")]
+    mod serde_impls;
+}
+#[cfg(all(feature = "std", unix, feature = "libc", not(miri)))]
+mod shared;
+mod split_time;
+cfg_timers! {
+    mod stopwatch;
+}
+#[cfg(all(windows, not(miri)))]
+mod system_times;
+cfg_timers! {
+    #[cfg(feature = "std")]
+    pub mod task;
+}
+#[cfg(all(
+    feature = "std",
+    any(
+        all(any(target_os = "linux", target_os = "android"), feature = "libc"),
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "tvos",
+        target_os = "watchos",
+        windows
+    ),
+    not(miri)
+))]
+mod threads_snapshot;
+#[cfg(feature = "time")]
+mod time_impls;
+#[cfg(all(unix, feature = "libc", not(miri)))]
+mod times;
+
 // It looks like all modern unixes support clock_gettime(..CPUTIME..)
-#[cfg(unix)]
+#[cfg(all(unix, not(miri)))]
 mod clock_gettime;
-#[cfg(windows)]
+#[cfg(all(target_os = "wasi", not(miri)))]
+mod clock_time_get;
+#[cfg(all(
+    feature = "libc",
+    any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "dragonfly"
+    ),
+    not(miri)
+))]
+mod cpuclock;
+#[cfg(all(target_os = "haiku", not(miri)))]
+mod haiku;
+#[cfg(all(target_os = "hermit", not(miri)))]
+mod hermit;
+#[cfg(all(
+    any(
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "tvos",
+        target_os = "watchos"
+    ),
+    not(miri)
+))]
+mod mach;
+#[cfg(miri)]
+mod miri;
+#[cfg(all(
+    target_arch = "wasm32",
+    target_os = "unknown",
+    feature = "wasm-bindgen",
+    not(miri)
+))]
+mod performance;
+#[cfg(all(
+    unix,
+    feature = "libc",
+    not(any(target_os = "haiku", target_os = "vxworks")),
+    not(miri)
+))]
+mod rusage;
+cfg_timers! {
+    stub:
+    mod stub;
+}
+#[cfg(all(
+    feature = "raw-syscalls",
+    target_os = "linux",
+    any(
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        target_arch = "riscv64"
+    ),
+    not(miri)
+))]
+mod syscall;
+
+#[cfg(all(windows, feature = "std", not(miri)))]
+mod nt;
+#[cfg(all(windows, not(miri)))]
 mod windows;
 
-#[cfg(unix)]
+#[cfg(all(
+    any(unix, target_os = "hermit"),
+    not(feature = "libc"),
+    not(all(
+        feature = "raw-syscalls",
+        target_os = "linux",
+        any(
+            target_arch = "x86_64",
+            target_arch = "aarch64",
+            target_arch = "riscv64"
+        )
+    )),
+    not(all(feature = "rustix", any(target_os = "linux", target_os = "android")))
+))]
+compile_error!(
+    "the `libc` feature is required on this target, `raw-syscalls` only \
+     covers Linux on x86_64, aarch64 and riscv64 and `rustix` Linux and \
+     Android"
+);
+
+#[cfg(any(
+    all(
+        unix,
+        feature = "libc",
+        feature = "std",
+        not(any(
+            target_os = "haiku",
+            target_os = "illumos",
+            target_os = "solaris",
+            target_os = "vxworks"
+        )),
+        not(miri)
+    ),
+    all(windows, feature = "std", not(miri))
+))]
+pub use bench::{bench_command, CommandBench};
+cfg_timers! {
+    pub use budget::{run_with_cpu_budget, BudgetExceeded, CpuBudget};
+    pub use capabilities::{capabilities, Capabilities};
+}
+#[cfg(all(
+    unix,
+    feature = "libc",
+    feature = "std",
+    not(any(
+        target_os = "haiku",
+        target_os = "illumos",
+        target_os = "solaris",
+        target_os = "vxworks"
+    )),
+    not(miri)
+))]
+pub use child::{try_wait4, wait4, ReapedChild};
+#[cfg(any(
+    all(
+        unix,
+        feature = "libc",
+        feature = "std",
+        not(any(
+            target_os = "haiku",
+            target_os = "illumos",
+            target_os = "solaris",
+            target_os = "vxworks"
+        )),
+        not(miri)
+    ),
+    all(windows, feature = "std", not(miri))
+))]
+pub use child::{ChildExt, ChildSet, FinishedChild};
+#[cfg(all(
+    unix,
+    feature = "libc",
+    not(any(target_os = "haiku", target_os = "vxworks")),
+    not(miri)
+))]
+pub use children::ChildrenTime;
+cfg_timers! {
+    pub use clock::CpuClock;
+}
+#[cfg(any(
+    all(
+        unix,
+        feature = "libc",
+        feature = "std",
+        not(any(
+            target_os = "haiku",
+            target_os = "illumos",
+            target_os = "solaris",
+            target_os = "vxworks"
+        )),
+        not(miri)
+    ),
+    all(windows, feature = "std", not(miri))
+))]
+pub use command::CommandExt;
+#[cfg(any(
+    all(
+        unix,
+        feature = "libc",
+        not(any(target_os = "haiku", target_os = "vxworks")),
+        not(miri)
+    ),
+    all(windows, feature = "std", not(miri))
+))]
+pub use context_switches::ContextSwitches;
+#[cfg(all(
+    any(target_os = "linux", target_os = "android"),
+    feature = "libc",
+    not(miri)
+))]
+pub use cpu_alarm::CpuAlarm;
+pub use cpu_duration::CpuDuration;
+#[cfg(all(
+    unix,
+    feature = "libc",
+    not(any(target_os = "haiku", target_os = "vxworks")),
+    not(miri)
+))]
+pub use cpu_limit::{catch_sigxcpu, on_sigxcpu, remaining_cpu_budget, sigxcpu_received, CpuLimit};
+#[cfg(feature = "macros")]
+pub use cpu_time_macros::cpu_time;
+#[cfg(all(windows, not(miri)))]
+pub use cycles::{CycleRate, ProcessCycles, ThreadCycles};
+pub use error::CpuTimeError;
+cfg_timers! {
+    pub use functions::{process_cpu_time, thread_cpu_time};
+    #[cfg(feature = "futures-core")]
+    pub use future::{CpuPerItem, StreamExt};
+    pub use future::{CpuTimed, FutureExt};
+    #[cfg(all(feature = "tower", feature = "std"))]
+    pub use layer::{CpuTimeFuture, CpuTimeLayer, CpuTimeService, RequestCpuTime, WithCpuTime};
+}
+#[cfg(all(target_os = "linux", feature = "libc", not(miri)))]
+pub use process_handle::ProcessHandle;
+#[cfg(all(
+    feature = "std",
+    any(
+        all(any(target_os = "linux", target_os = "android"), feature = "libc"),
+        target_os = "macos",
+        windows
+    ),
+    not(miri)
+))]
+pub use processes::{
+    process_group_time, process_time_by_uid, process_tree_time, processes, ProcessEntry,
+    ProcessesDelta, ProcessesSnapshot,
+};
+#[cfg(all(feature = "std", any(all(unix, feature = "libc"), windows), not(miri)))]
+pub use registry::report_at_exit;
+cfg_timers! {
+    #[cfg(feature = "std")]
+    pub use registry::{
+        accumulate, accumulated, accumulators, counter, reset_accumulators, Accumulated, Counter,
+    };
+    #[cfg(feature = "std")]
+    pub use report::{Event, Region, Report};
+    #[cfg(all(feature = "tower", feature = "std"))]
+    pub use request::{RequestCpu, RequestCpuFuture, RequestCpuLayer, RequestCpuService};
+}
+#[cfg(all(
+    unix,
+    feature = "libc",
+    not(any(target_os = "haiku", target_os = "vxworks")),
+    not(miri)
+))]
+pub use resource_usage::ResourceUsage;
+pub use rounding::Rounding;
+cfg_timers! {
+    #[cfg(feature = "log")]
+    pub use scope::log_reporter;
+    pub use scope::{report, set_reporter, CpuScope};
+}
+#[cfg(all(feature = "std", unix, feature = "libc", not(miri)))]
+pub use shared::SharedCounters;
+pub use split_time::SplitTime;
+cfg_timers! {
+    pub use stopwatch::CpuStopwatch;
+}
+#[cfg(all(windows, not(miri)))]
+pub use system_times::SystemTimes;
+#[cfg(all(
+    feature = "std",
+    any(
+        all(any(target_os = "linux", target_os = "android"), feature = "libc"),
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "tvos",
+        target_os = "watchos",
+        windows
+    ),
+    not(miri)
+))]
+pub use threads_snapshot::{ThreadEntry, ThreadsDelta, ThreadsSnapshot};
+#[cfg(all(unix, feature = "libc", not(miri)))]
+pub use times::CpuTimes;
+
+#[cfg(all(unix, not(miri)))]
 pub use clock_gettime::{ProcessTime, ThreadTime};
 
-#[cfg(windows)]
-pub use windows::{ProcessTime, ThreadTime};
+#[cfg(all(windows, not(miri)))]
+pub use windows::{ProcessHandle, ProcessTime, ThreadTime};
+
+#[cfg(all(target_os = "wasi", not(miri)))]
+pub use clock_time_get::{ProcessTime, ThreadTime};
+
+#[cfg(all(target_os = "hermit", not(miri)))]
+pub use hermit::{ProcessTime, ThreadTime};
+
+#[cfg(all(
+    target_arch = "wasm32",
+    target_os = "unknown",
+    feature = "wasm-bindgen",
+    not(miri)
+))]
+pub use performance::{ProcessTime, ThreadTime};
+
+cfg_timers! {
+    stub:
+    pub use stub::{ProcessTime, ThreadTime};
+}
+
+#[cfg(miri)]
+pub use miri::{ProcessTime, ThreadTime};
@@ -0,0 +1,326 @@
+use std::mem;
+use std::time::Duration;
+#[cfg(feature = "std")]
+use std::{ptr, slice};
+
+#[cfg(all(target_os = "macos", feature = "std"))]
+use libc::{c_int, proc_bsdinfo, proc_listallpids, proc_pidinfo, PROC_PIDTBSDINFO};
+use libc::{kern_return_t, mach_port_t, time_value_t, KERN_SUCCESS};
+#[cfg(not(target_os = "macos"))]
+use libc::{mach_task_basic_info, MACH_TASK_BASIC_INFO, MACH_TASK_BASIC_INFO_COUNT};
+#[cfg(target_os = "macos")]
+use libc::{proc_pid_rusage, rusage_info_v1, RUSAGE_INFO_V1};
+#[cfg(not(target_os = "macos"))]
+use libc::{
+    task_info, task_thread_times_info, TASK_THREAD_TIMES_INFO, TASK_THREAD_TIMES_INFO_COUNT,
+};
+#[cfg(feature = "std")]
+use libc::{
+    task_threads, thread_act_array_t, thread_act_t, thread_extended_info, thread_identifier_info,
+    vm_address_t, vm_deallocate, vm_size_t, THREAD_EXTENDED_INFO, THREAD_EXTENDED_INFO_COUNT,
+    THREAD_IDENTIFIER_INFO, THREAD_IDENTIFIER_INFO_COUNT,
+};
+use libc::{thread_basic_info, thread_info, THREAD_BASIC_INFO, THREAD_BASIC_INFO_COUNT};
+
+#[cfg(target_os = "macos")]
+use CpuTimeError;
+#[cfg(feature = "std")]
+use ThreadEntry;
+#[cfg(all(target_os = "macos", feature = "std"))]
+use {ProcessEntry, SplitTime};
+
+// libc only declares these for macOS, but they are part of libSystem on
+// every Apple platform
+extern "C" {
+    static mach_task_self_: mach_port_t;
+    fn mach_thread_self() -> mach_port_t;
+    fn mach_port_deallocate(task: mach_port_t, name: mach_port_t) -> kern_return_t;
+}
+
+// `struct mach_timebase_info`, declared here as libc deprecated its own
+#[cfg(target_os = "macos")]
+#[repr(C)]
+struct Timebase {
+    numer: u32,
+    denom: u32,
+}
+
+#[cfg(target_os = "macos")]
+extern "C" {
+    fn mach_timebase_info(info: *mut Timebase) -> kern_return_t;
+}
+
+fn mach_task_self() -> mach_port_t {
+    unsafe { mach_task_self_ }
+}
+
+fn to_duration(time: time_value_t) -> Duration {
+    Duration::new(time.seconds as u64, time.microseconds as u32 * 1000)
+}
+
+/// CPU time of the current task as reported by `task_info()`
+///
+/// `MACH_TASK_BASIC_INFO` only accounts for terminated threads, so times of
+/// live threads are added from `TASK_THREAD_TIMES_INFO`, the same way the
+/// kernel computes `getrusage`.
+#[cfg(not(target_os = "macos"))]
+pub fn process() -> Option<Duration> {
+    let task = mach_task_self();
+    let mut basic: mach_task_basic_info = unsafe { mem::zeroed() };
+    let mut count = MACH_TASK_BASIC_INFO_COUNT;
+    let result = unsafe {
+        task_info(
+            task,
+            MACH_TASK_BASIC_INFO,
+            &mut basic as *mut _ as *mut _,
+            &mut count,
+        )
+    };
+    if result != KERN_SUCCESS {
+        return None;
+    }
+    let mut threads: task_thread_times_info = unsafe { mem::zeroed() };
+    let mut count = TASK_THREAD_TIMES_INFO_COUNT;
+    let result = unsafe {
+        task_info(
+            task,
+            TASK_THREAD_TIMES_INFO,
+            &mut threads as *mut _ as *mut _,
+            &mut count,
+        )
+    };
+    if result != KERN_SUCCESS {
+        return None;
+    }
+    let (user_time, system_time) = (basic.user_time, basic.system_time);
+    Some(
+        to_duration(user_time)
+            .saturating_add(to_duration(system_time))
+            .saturating_add(to_duration(threads.user_time))
+            .saturating_add(to_duration(threads.system_time)),
+    )
+}
+
+/// CPU time of the current thread as reported by `thread_info()`
+pub fn thread() -> Option<Duration> {
+    thread_split().map(|(user, system)| user.saturating_add(system))
+}
+
+/// User and system CPU time of the current thread
+pub fn thread_split() -> Option<(Duration, Duration)> {
+    let thread = unsafe { mach_thread_self() };
+    let mut info: thread_basic_info = unsafe { mem::zeroed() };
+    let mut count = THREAD_BASIC_INFO_COUNT;
+    let result = unsafe {
+        thread_info(
+            thread,
+            THREAD_BASIC_INFO as _,
+            &mut info as *mut _ as *mut _,
+            &mut count,
+        )
+    };
+    // `mach_thread_self()` returns a new send right every time
+    unsafe { mach_port_deallocate(mach_task_self(), thread) };
+    if result != KERN_SUCCESS {
+        return None;
+    }
+    Some((to_duration(info.user_time), to_duration(info.system_time)))
+}
+
+// `proc_pid_rusage()` of process `pid`
+#[cfg(target_os = "macos")]
+fn rusage_for(pid: u32) -> Result<rusage_info_v1, CpuTimeError> {
+    if pid > i32::MAX as u32 {
+        return Err(CpuTimeError::ProcessGone);
+    }
+    let mut info: rusage_info_v1 = unsafe { mem::zeroed() };
+    if unsafe { proc_pid_rusage(pid as i32, RUSAGE_INFO_V1, &mut info as *mut _ as *mut _) } != 0 {
+        return Err(match unsafe { *libc::__error() } {
+            libc::ESRCH => CpuTimeError::ProcessGone,
+            libc::EPERM => CpuTimeError::PermissionDenied,
+            code => CpuTimeError::Os(code),
+        });
+    }
+    Ok(info)
+}
+
+// The times of `proc_pid_rusage()` are in Mach absolute time units, which
+// are nanoseconds on Intel but not on Apple silicon
+#[cfg(target_os = "macos")]
+fn absolute_to_duration(ticks: u64) -> Result<Duration, CpuTimeError> {
+    let mut timebase = Timebase { numer: 0, denom: 0 };
+    if unsafe { mach_timebase_info(&mut timebase) } != KERN_SUCCESS || timebase.denom == 0 {
+        return Err(CpuTimeError::Unsupported);
+    }
+    let nanos = ticks as u128 * timebase.numer as u128 / timebase.denom as u128;
+    Ok(Duration::new(
+        (nanos / 1_000_000_000) as u64,
+        (nanos % 1_000_000_000) as u32,
+    ))
+}
+
+/// User and system time of process `pid` as reported by
+/// `proc_pid_rusage()`
+#[cfg(target_os = "macos")]
+pub fn process_split_for(pid: u32) -> Result<(Duration, Duration), CpuTimeError> {
+    let info = rusage_for(pid)?;
+    Ok((
+        absolute_to_duration(info.ri_user_time)?,
+        absolute_to_duration(info.ri_system_time)?,
+    ))
+}
+
+/// CPU time of process `pid`, see `process_split_for()`
+#[cfg(target_os = "macos")]
+pub fn process_for(pid: u32) -> Result<Duration, CpuTimeError> {
+    process_split_for(pid).map(|(user, system)| user.saturating_add(system))
+}
+
+/// Every process of the system whose times can be read
+///
+/// Lists them with `proc_listallpids()` and names them from
+/// `PROC_PIDTBSDINFO`. Without root `proc_pid_rusage()` refuses the
+/// processes of other users, those are left out.
+#[cfg(all(target_os = "macos", feature = "std"))]
+pub fn processes() -> Option<Vec<ProcessEntry>> {
+    let count = unsafe { proc_listallpids(ptr::null_mut(), 0) };
+    if count <= 0 {
+        return None;
+    }
+    // Processes may have started in between, leave some slack
+    let mut pids = vec![0 as c_int; count as usize + 64];
+    let bytes = (pids.len() * mem::size_of::<c_int>()) as c_int;
+    let count = unsafe { proc_listallpids(pids.as_mut_ptr() as *mut _, bytes) };
+    if count <= 0 {
+        return None;
+    }
+    pids.truncate(count as usize);
+    Some(
+        pids.iter()
+            .filter_map(|&pid| process_entry(pid as u32))
+            .collect(),
+    )
+}
+
+// Processes that exit in between or can't be read are skipped
+// The names are fixed-size arrays, which are only NUL-terminated if the
+// name is shorter
+#[cfg(feature = "std")]
+fn array_name(name: &[libc::c_char]) -> String {
+    let bytes = name
+        .iter()
+        .take_while(|&&c| c != 0)
+        .map(|&c| c as u8)
+        .collect::<Vec<_>>();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[cfg(all(target_os = "macos", feature = "std"))]
+fn process_entry(pid: u32) -> Option<ProcessEntry> {
+    let mut info: proc_bsdinfo = unsafe { mem::zeroed() };
+    let size = mem::size_of::<proc_bsdinfo>() as c_int;
+    let written = unsafe {
+        proc_pidinfo(
+            pid as c_int,
+            PROC_PIDTBSDINFO,
+            0,
+            &mut info as *mut _ as *mut _,
+            size,
+        )
+    };
+    if written != size {
+        return None;
+    }
+    let usage = rusage_for(pid).ok()?;
+    let time = |ticks| absolute_to_duration(ticks).ok();
+    // `pbi_comm` is cut at 16 bytes
+    let name = if info.pbi_name[0] != 0 {
+        array_name(&info.pbi_name)
+    } else {
+        array_name(&info.pbi_comm)
+    };
+    Some(ProcessEntry {
+        pid,
+        ppid: info.pbi_ppid,
+        pgid: Some(info.pbi_pgid),
+        uid: Some(info.pbi_uid),
+        name,
+        user: time(usage.ri_user_time)?,
+        system: time(usage.ri_system_time)?,
+        children: Some(SplitTime {
+            user: time(usage.ri_child_user_time)?,
+            system: time(usage.ri_child_system_time)?,
+        }),
+    })
+}
+
+/// Every thread of the current task
+///
+/// `THREAD_EXTENDED_INFO` has the times in nanoseconds and the name set
+/// with `pthread_setname_np()`, the id comes from `THREAD_IDENTIFIER_INFO`
+/// (the same as `pthread_threadid_np()`).
+#[cfg(feature = "std")]
+pub fn threads() -> Option<Vec<ThreadEntry>> {
+    let task = mach_task_self();
+    let mut list: thread_act_array_t = ptr::null_mut();
+    let mut count = 0;
+    if unsafe { task_threads(task, &mut list, &mut count) } != KERN_SUCCESS {
+        return None;
+    }
+    let ports = unsafe { slice::from_raw_parts(list, count as usize) };
+    let threads = ports
+        .iter()
+        .filter_map(|&thread| {
+            let info = thread_entry(thread);
+            // `task_threads()` hands out a send right per thread
+            unsafe { mach_port_deallocate(task, thread) };
+            info
+        })
+        .collect();
+    unsafe {
+        vm_deallocate(
+            task,
+            list as vm_address_t,
+            count as vm_size_t * mem::size_of::<thread_act_t>(),
+        )
+    };
+    Some(threads)
+}
+
+// Threads that exit in between are skipped
+#[cfg(feature = "std")]
+fn thread_entry(thread: thread_act_t) -> Option<ThreadEntry> {
+    let mut identifier: thread_identifier_info = unsafe { mem::zeroed() };
+    let mut count = THREAD_IDENTIFIER_INFO_COUNT;
+    let result = unsafe {
+        thread_info(
+            thread,
+            THREAD_IDENTIFIER_INFO as _,
+            &mut identifier as *mut _ as *mut _,
+            &mut count,
+        )
+    };
+    if result != KERN_SUCCESS {
+        return None;
+    }
+    let mut extended: thread_extended_info = unsafe { mem::zeroed() };
+    let mut count = THREAD_EXTENDED_INFO_COUNT;
+    let result = unsafe {
+        thread_info(
+            thread,
+            THREAD_EXTENDED_INFO as _,
+            &mut extended as *mut _ as *mut _,
+            &mut count,
+        )
+    };
+    if result != KERN_SUCCESS {
+        return None;
+    }
+    let name = array_name(&extended.pth_name);
+    Some(ThreadEntry {
+        tid: identifier.thread_id,
+        name: if name.is_empty() { None } else { Some(name) },
+        user: Duration::from_nanos(extended.pth_user_time),
+        system: Duration::from_nanos(extended.pth_system_time),
+    })
+}
@@ -0,0 +1,33 @@
+use std::time::Duration;
+
+/// Converts CPU time to nanoseconds for `as_nanos_u64()` and the
+/// serialization formats
+///
+/// Saturates at ~584 years of CPU time.
+pub fn to_nanos(duration: Duration) -> u64 {
+    duration
+        .as_secs()
+        .checked_mul(1_000_000_000)
+        .and_then(|nanos| nanos.checked_add(duration.subsec_nanos() as u64))
+        .unwrap_or(u64::MAX)
+}
+
+/// The times in the fields of the derived `Serialize` and `Deserialize`
+/// implementations, as nanoseconds
+#[cfg(feature = "serde")]
+pub mod serde {
+    use std::time::Duration;
+
+    use serde::de::{Deserialize, Deserializer};
+    use serde::ser::Serializer;
+
+    use super::to_nanos;
+
+    pub fn serialize<S: Serializer>(time: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(to_nanos(*time))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_nanos)
+    }
+}
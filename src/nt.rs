@@ -0,0 +1,186 @@
+use std::mem;
+use std::slice;
+use std::time::Duration;
+
+use winapi::ctypes::c_void;
+use winapi::shared::minwindef::ULONG;
+use winapi::um::winnt::HANDLE;
+
+use CpuTimeError;
+
+// `SystemProcessInformation`, not in winapi 0.3
+const SYSTEM_PROCESS_INFORMATION: ULONG = 5;
+const STATUS_INFO_LENGTH_MISMATCH: i32 = 0xC000_0004_u32 as i32;
+
+#[link(name = "ntdll")]
+extern "system" {
+    fn NtQuerySystemInformation(
+        class: ULONG,
+        information: *mut c_void,
+        length: ULONG,
+        return_length: *mut ULONG,
+    ) -> i32; // NTSTATUS
+    fn NtResumeProcess(process: HANDLE) -> i32;
+}
+
+#[repr(C)]
+pub struct UnicodeString {
+    pub length: u16,
+    pub maximum_length: u16,
+    pub buffer: *const u16,
+}
+
+/// `SYSTEM_THREAD_INFORMATION`
+#[repr(C)]
+pub struct ThreadInformation {
+    pub kernel_time: i64,
+    pub user_time: i64,
+    pub create_time: i64,
+    pub wait_time: u32,
+    pub start_address: *const c_void,
+    pub unique_process: HANDLE,
+    pub unique_thread: HANDLE,
+    pub priority: i32,
+    pub base_priority: i32,
+    pub context_switches: u32,
+    pub thread_state: u32,
+    pub wait_reason: u32,
+}
+
+/// `SYSTEM_PROCESS_INFORMATION`, followed by its threads
+#[repr(C)]
+pub struct ProcessInformation {
+    next_entry_offset: u32,
+    number_of_threads: u32,
+    pub working_set_private_size: i64,
+    pub hard_fault_count: u32,
+    pub number_of_threads_high_watermark: u32,
+    pub cycle_time: u64,
+    pub create_time: i64,
+    pub user_time: i64,
+    pub kernel_time: i64,
+    pub image_name: UnicodeString,
+    pub base_priority: i32,
+    pub unique_process_id: HANDLE,
+    pub inherited_from_unique_process_id: HANDLE,
+    pub handle_count: u32,
+    pub session_id: u32,
+    pub unique_process_key: usize,
+    pub peak_virtual_size: usize,
+    pub virtual_size: usize,
+    pub page_fault_count: u32,
+    pub peak_working_set_size: usize,
+    pub working_set_size: usize,
+    pub quota_peak_paged_pool_usage: usize,
+    pub quota_paged_pool_usage: usize,
+    pub quota_peak_non_paged_pool_usage: usize,
+    pub quota_non_paged_pool_usage: usize,
+    pub pagefile_usage: usize,
+    pub peak_pagefile_usage: usize,
+    pub private_page_count: usize,
+    pub read_operation_count: i64,
+    pub write_operation_count: i64,
+    pub other_operation_count: i64,
+    pub read_transfer_count: i64,
+    pub write_transfer_count: i64,
+    pub other_transfer_count: i64,
+    threads: [ThreadInformation; 0],
+}
+
+/// Resumes all threads of a process started with `CREATE_SUSPENDED`
+pub fn resume_process(process: HANDLE) -> bool {
+    unsafe { NtResumeProcess(process) >= 0 }
+}
+
+/// Converts a time in 100 ns units
+pub fn to_duration(ticks: i64) -> Duration {
+    let ticks = if ticks < 0 { 0 } else { ticks as u64 };
+    Duration::new(ticks / 10_000_000, (ticks % 10_000_000) as u32 * 100)
+}
+
+impl ProcessInformation {
+    pub fn pid(&self) -> u32 {
+        self.unique_process_id as usize as u32
+    }
+    /// The executable's file name, empty for the idle process
+    pub fn name(&self) -> String {
+        let name = &self.image_name;
+        if name.buffer.is_null() {
+            return String::new();
+        }
+        // The buffer points into the snapshot, `length` is in bytes
+        let units = unsafe { slice::from_raw_parts(name.buffer, name.length as usize / 2) };
+        String::from_utf16_lossy(units)
+    }
+    pub fn threads(&self) -> &[ThreadInformation] {
+        // The kernel places `number_of_threads` entries right after the
+        // process entry
+        unsafe { slice::from_raw_parts(self.threads.as_ptr(), self.number_of_threads as usize) }
+    }
+}
+
+/// Information about every process and thread of the system, at one
+/// moment
+pub struct Snapshot {
+    // `u64`s to keep the entries aligned
+    buffer: Vec<u64>,
+}
+
+impl Snapshot {
+    pub fn capture() -> Result<Snapshot, CpuTimeError> {
+        let mut size = 256 * 1024;
+        loop {
+            let mut buffer = vec![0u64; size / mem::size_of::<u64>()];
+            let mut needed = 0;
+            let status = unsafe {
+                NtQuerySystemInformation(
+                    SYSTEM_PROCESS_INFORMATION,
+                    buffer.as_mut_ptr() as *mut c_void,
+                    size as ULONG,
+                    &mut needed,
+                )
+            };
+            match status {
+                // Processes may have started in between, leave some slack
+                STATUS_INFO_LENGTH_MISMATCH => size = needed as usize + 64 * 1024,
+                status if status >= 0 => return Ok(Snapshot { buffer }),
+                status => return Err(CpuTimeError::Os(status)),
+            }
+        }
+    }
+    pub fn processes(&self) -> Processes<'_> {
+        Processes {
+            snapshot: self,
+            offset: Some(0),
+        }
+    }
+    pub fn process(&self, pid: u32) -> Option<&ProcessInformation> {
+        self.processes().find(|process| process.pid() == pid)
+    }
+}
+
+pub struct Processes<'a> {
+    snapshot: &'a Snapshot,
+    offset: Option<usize>,
+}
+
+impl<'a> Iterator for Processes<'a> {
+    type Item = &'a ProcessInformation;
+    fn next(&mut self) -> Option<&'a ProcessInformation> {
+        let offset = self.offset?;
+        let bytes = self.snapshot.buffer.len() * mem::size_of::<u64>();
+        if offset + mem::size_of::<ProcessInformation>() > bytes {
+            self.offset = None;
+            return None;
+        }
+        let process = unsafe {
+            &*((self.snapshot.buffer.as_ptr() as *const u8).add(offset)
+                as *const ProcessInformation)
+        };
+        self.offset = match process.next_entry_offset {
+            0 => None,
+            next => Some(offset + next as usize),
+        };
+        Some(process)
+    }
+}
@@ -0,0 +1,112 @@
+use std::ops::{Add, AddAssign, Sub, SubAssign};
+use std::time::Duration;
+
+use {ProcessTime, ThreadTime};
+
+impl Add<Duration> for ProcessTime {
+    type Output = ProcessTime;
+
+    /// # Panics
+    ///
+    /// If the resulting timestamp can't be represented. Use `checked_add()`
+    /// to handle this case.
+    fn add(self, duration: Duration) -> ProcessTime {
+        self.checked_add(duration)
+            .expect("overflow when adding duration to CPU time")
+    }
+}
+
+impl AddAssign<Duration> for ProcessTime {
+    fn add_assign(&mut self, duration: Duration) {
+        *self = *self + duration;
+    }
+}
+
+impl Sub<Duration> for ProcessTime {
+    type Output = ProcessTime;
+
+    /// # Panics
+    ///
+    /// If the resulting timestamp would be before the process started. Use
+    /// `checked_sub()` to handle this case.
+    fn sub(self, duration: Duration) -> ProcessTime {
+        self.checked_sub(duration)
+            .expect("overflow when subtracting duration from CPU time")
+    }
+}
+
+impl SubAssign<Duration> for ProcessTime {
+    fn sub_assign(&mut self, duration: Duration) {
+        *self = *self - duration;
+    }
+}
+
+impl Sub<ProcessTime> for ProcessTime {
+    type Output = Duration;
+
+    /// Same as `duration_since()`
+    fn sub(self, earlier: ProcessTime) -> Duration {
+        self.duration_since(earlier)
+    }
+}
+
+impl From<Duration> for ProcessTime {
+    /// Same as `ProcessTime::from_duration()`
+    fn from(duration: Duration) -> ProcessTime {
+        ProcessTime::from_duration(duration)
+    }
+}
+
+impl Add<Duration> for ThreadTime {
+    type Output = ThreadTime;
+
+    /// # Panics
+    ///
+    /// If the resulting timestamp can't be represented. Use `checked_add()`
+    /// to handle this case.
+    fn add(self, duration: Duration) -> ThreadTime {
+        self.checked_add(duration)
+            .expect("overflow when adding duration to CPU time")
+    }
+}
+
+impl AddAssign<Duration> for ThreadTime {
+    fn add_assign(&mut self, duration: Duration) {
+        *self = *self + duration;
+    }
+}
+
+impl Sub<Duration> for ThreadTime {
+    type Output = ThreadTime;
+
+    /// # Panics
+    ///
+    /// If the resulting timestamp would be before the thread started. Use
+    /// `checked_sub()` to handle this case.
+    fn sub(self, duration: Duration) -> ThreadTime {
+        self.checked_sub(duration)
+            .expect("overflow when subtracting duration from CPU time")
+    }
+}
+
+impl SubAssign<Duration> for ThreadTime {
+    fn sub_assign(&mut self, duration: Duration) {
+        *self = *self - duration;
+    }
+}
+
+impl Sub<ThreadTime> for ThreadTime {
+    type Output = Duration;
+
+    /// Same as `duration_since()`
+    fn sub(self, earlier: ThreadTime) -> Duration {
+        self.duration_since(earlier)
+    }
+}
+
+impl From<Duration> for ThreadTime {
+    /// Same as `ThreadTime::from_duration()`
+    fn from(duration: Duration) -> ThreadTime {
+        ThreadTime::from_duration(duration)
+    }
+}
@@ -0,0 +1,93 @@
+#[cfg(feature = "std")]
+use std::os::unix::io::{AsRawFd, RawFd};
+
+use libc::{c_int, c_long, pid_t, syscall, SYS_pidfd_open, SYS_pidfd_send_signal};
+use libc::{ENOSYS, EPERM, ESRCH};
+
+use CpuTimeError;
+
+/// A process attached through a pidfd (Linux 5.3+)
+///
+/// A pid is only a number, once the process exits and is reaped it can be
+/// reused by an unrelated one. The pidfd keeps referring to the original
+/// process, so `ProcessTime::now_for_handle()` can tell that it's gone
+/// instead of silently measuring the new one:
+///
+/// ```rust,no_run
+/// use cpu_time::{ProcessHandle, ProcessTime};
+///
+/// let handle = ProcessHandle::open(1234).unwrap();
+/// let start = ProcessTime::now_for_handle(&handle).unwrap();
+/// // ...
+/// let used = ProcessTime::now_for_handle(&handle).unwrap().duration_since(start);
+/// ```
+///
+/// The descriptor is closed on drop.
+#[derive(Debug)]
+pub struct ProcessHandle {
+    fd: c_int,
+    pid: u32,
+}
+
+fn errno() -> c_int {
+    unsafe { *libc::__errno_location() }
+}
+
+impl ProcessHandle {
+    /// Attaches to process `pid` with `pidfd_open()`
+    ///
+    /// Returns `CpuTimeError::ProcessGone` if there is no such process and
+    /// `CpuTimeError::Unsupported` on kernels before 5.3.
+    pub fn open(pid: u32) -> Result<ProcessHandle, CpuTimeError> {
+        if pid == 0 || pid > i32::MAX as u32 {
+            return Err(CpuTimeError::ProcessGone);
+        }
+        let fd = unsafe { syscall(SYS_pidfd_open, pid as pid_t, 0 as c_int) };
+        if fd == -1 {
+            return Err(match errno() {
+                ESRCH => CpuTimeError::ProcessGone,
+                ENOSYS => CpuTimeError::Unsupported,
+                EPERM => CpuTimeError::PermissionDenied,
+                code => CpuTimeError::Os(code),
+            });
+        }
+        Ok(ProcessHandle {
+            fd: fd as c_int,
+            pid,
+        })
+    }
+    /// Returns the pid of the process, as passed to `open()`
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+    /// Returns `true` until the process has been reaped
+    ///
+    /// A zombie process still counts as alive, its pid can't be reused yet.
+    pub fn is_alive(&self) -> bool {
+        // Signal 0 only checks the target, `EPERM` means it exists but
+        // belongs to another user
+        let ret = unsafe {
+            syscall(
+                SYS_pidfd_send_signal,
+                self.fd,
+                0 as c_int,
+                0 as c_long,
+                0 as c_int,
+            )
+        };
+        ret == 0 || errno() != ESRCH
+    }
+}
+
+#[cfg(feature = "std")]
+impl AsRawFd for ProcessHandle {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl Drop for ProcessHandle {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.fd) };
+    }
+}
@@ -0,0 +1,466 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Sub;
+use std::time::Duration;
+use std::vec;
+
+#[cfg(target_os = "macos")]
+use mach;
+#[cfg(windows)]
+use nt::{to_duration, Snapshot};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use procfs::Stat;
+use CpuTimeError;
+use SplitTime;
+
+/// CPU time of one process in a `ProcessesSnapshot`
+#[derive(Clone, PartialEq, Eq, Debug, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(::rkyv::Archive, ::rkyv::Serialize, ::rkyv::Deserialize)
+)]
+#[cfg_attr(feature = "bincode", derive(::bincode::Encode, ::bincode::Decode))]
+#[cfg_attr(feature = "arbitrary", derive(::arbitrary::Arbitrary))]
+pub struct ProcessEntry {
+    /// Process id
+    pub pid: u32,
+    /// Id of the parent process, 0 if there is none
+    ///
+    /// On Windows this is the process that started it, which may have
+    /// exited since and its id may be reused.
+    pub ppid: u32,
+    /// Process group id, `None` on Windows, which has no process groups
+    pub pgid: Option<u32>,
+    /// Effective user id, `None` on Windows, which identifies users by SID
+    pub uid: Option<u32>,
+    /// Name of the process: the command name on Linux (at most 15 bytes),
+    /// the executable's file name on macOS and Windows
+    pub name: String,
+    /// CPU time spent running user code
+    #[cfg_attr(feature = "serde", serde(with = "::nanos::serde"))]
+    pub user: Duration,
+    /// CPU time the kernel spent on behalf of the process
+    #[cfg_attr(feature = "serde", serde(with = "::nanos::serde"))]
+    pub system: Duration,
+    /// CPU time of the terminated and waited-for children, or `None` where
+    /// the OS doesn't report it (Windows)
+    pub children: Option<SplitTime>,
+}
+
+impl ProcessEntry {
+    /// Returns the user and system time added up
+    pub fn cpu_time(&self) -> Duration {
+        self.user.saturating_add(self.system)
+    }
+    /// Returns the user and system time
+    pub fn split(&self) -> SplitTime {
+        SplitTime {
+            user: self.user,
+            system: self.system,
+        }
+    }
+}
+
+/// CPU time of every visible process of the system, at one moment
+///
+/// The base for small `top`-like tools:
+///
+/// ```rust,no_run
+/// let mut processes = cpu_time::processes().unwrap().collect::<Vec<_>>();
+/// processes.sort_by_key(|process| std::cmp::Reverse(process.cpu_time()));
+/// for process in processes.iter().take(5) {
+///     println!("{:>7} {:<16} {:?}", process.pid, process.name, process.cpu_time());
+/// }
+/// ```
+///
+/// Linux and Android read `/proc`, macOS `proc_listallpids()` and
+/// `proc_pid_rusage()` and Windows `NtQuerySystemInformation`. Processes
+/// that start or exit meanwhile may be missing, and ones whose times can't
+/// be read are left out (on macOS those of other users, unless running as
+/// root). On Linux the times are in clock ticks, usually 10 ms.
+#[derive(Clone, PartialEq, Eq, Debug, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(::rkyv::Archive, ::rkyv::Serialize, ::rkyv::Deserialize)
+)]
+#[cfg_attr(feature = "bincode", derive(::bincode::Encode, ::bincode::Decode))]
+#[cfg_attr(feature = "arbitrary", derive(::arbitrary::Arbitrary))]
+pub struct ProcessesSnapshot {
+    processes: Vec<ProcessEntry>,
+}
+
+impl ProcessesSnapshot {
+    /// Reads all visible processes
+    pub fn capture() -> Result<ProcessesSnapshot, CpuTimeError> {
+        capture().map(|processes| ProcessesSnapshot { processes })
+    }
+    /// Returns the processes, in the order the OS listed them
+    pub fn processes(&self) -> &[ProcessEntry] {
+        &self.processes
+    }
+    /// Returns the process with id `pid`
+    pub fn get(&self, pid: u32) -> Option<&ProcessEntry> {
+        self.processes.iter().find(|process| process.pid == pid)
+    }
+    /// Returns `root` and all its running descendants, `root` first
+    ///
+    /// Returns an empty list if `root` isn't in the snapshot.
+    pub fn tree(&self, root: u32) -> Vec<&ProcessEntry> {
+        let mut children = HashMap::new();
+        for process in &self.processes {
+            // The idle and init processes may be their own parents
+            if process.pid != process.ppid {
+                children
+                    .entry(process.ppid)
+                    .or_insert_with(Vec::new)
+                    .push(process);
+            }
+        }
+        let mut tree = self.get(root).into_iter().collect::<Vec<_>>();
+        let mut seen = HashSet::new();
+        seen.insert(root);
+        let mut next = 0;
+        while next < tree.len() {
+            let pid = tree[next].pid;
+            next += 1;
+            for &child in children.get(&pid).map(|c| &c[..]).unwrap_or(&[]) {
+                // Recycled ids could form a cycle on Windows
+                if seen.insert(child.pid) {
+                    tree.push(child);
+                }
+            }
+        }
+        tree
+    }
+    /// Returns the CPU time used by `root` and all its running descendants
+    ///
+    /// With `include_reaped` the terminated and waited-for children of each
+    /// of them are added, i.e. everything the process tree used so far, as
+    /// a CI runner wants for a build command that forks compilers. Windows
+    /// doesn't report those. Returns `None` if `root` isn't in the snapshot.
+    pub fn tree_cpu_time(&self, root: u32, include_reaped: bool) -> Option<SplitTime> {
+        let tree = self.tree(root);
+        if tree.is_empty() {
+            return None;
+        }
+        Some(sum(tree, include_reaped))
+    }
+    /// Returns the processes of process group `pgid`
+    ///
+    /// Always empty on Windows.
+    pub fn group(&self, pgid: u32) -> Vec<&ProcessEntry> {
+        self.processes
+            .iter()
+            .filter(|process| process.pgid == Some(pgid))
+            .collect()
+    }
+    /// Returns the CPU time used by the running processes of process group
+    /// `pgid`
+    ///
+    /// For shell-like supervisors that start each job in its own group,
+    /// `include_reaped` works like for `tree_cpu_time()`. Returns `None` if
+    /// no process of the group is in the snapshot.
+    pub fn group_cpu_time(&self, pgid: u32, include_reaped: bool) -> Option<SplitTime> {
+        let group = self.group(pgid);
+        if group.is_empty() {
+            return None;
+        }
+        Some(sum(group, include_reaped))
+    }
+    /// Returns the CPU time used by the running processes of each user,
+    /// by effective user id
+    ///
+    /// Lightweight per-user accounting for multi-tenant hosts without
+    /// cgroups, `include_reaped` works like for `tree_cpu_time()`. Always
+    /// empty on Windows.
+    pub fn cpu_time_by_uid(&self, include_reaped: bool) -> BTreeMap<u32, SplitTime> {
+        let mut users = BTreeMap::new();
+        for process in &self.processes {
+            if let Some(uid) = process.uid {
+                let total = users.entry(uid).or_insert_with(SplitTime::default);
+                *total = total.saturating_add(usage(process, include_reaped));
+            }
+        }
+        users
+    }
+}
+
+// Own time of `process`, plus that of its reaped children if requested
+fn usage(process: &ProcessEntry, include_reaped: bool) -> SplitTime {
+    match (include_reaped, process.children) {
+        (true, Some(children)) => process.split().saturating_add(children),
+        _ => process.split(),
+    }
+}
+
+fn sum(processes: Vec<&ProcessEntry>, include_reaped: bool) -> SplitTime {
+    processes
+        .iter()
+        .fold(SplitTime::default(), |total, process| {
+            total.saturating_add(usage(process, include_reaped))
+        })
+}
+
+/// CPU time each process used between two `ProcessesSnapshot`s
+///
+/// Subtract the earlier snapshot from the later one, e.g. for the
+/// per-interval view of `top`:
+///
+/// ```rust,no_run
+/// use std::thread::sleep;
+/// use std::time::Duration;
+/// use cpu_time::ProcessesSnapshot;
+///
+/// let start = ProcessesSnapshot::capture().unwrap();
+/// sleep(Duration::from_secs(1));
+/// let delta = &ProcessesSnapshot::capture().unwrap() - &start;
+/// if let Some(process) = delta.busiest() {
+///     println!("{} used {:?} in the last second", process.name, process.cpu_time());
+/// }
+/// ```
+///
+/// It holds the processes of the later snapshot, with the times they used
+/// since the earlier one, `children` included. Processes started in
+/// between count from zero, ones that exited in between are left out.
+#[derive(Clone, PartialEq, Eq, Debug, Hash, Default)]
+pub struct ProcessesDelta {
+    processes: Vec<ProcessEntry>,
+}
+
+impl ProcessesDelta {
+    /// Returns the processes with the times they used in the interval, in
+    /// the order of the later snapshot
+    pub fn processes(&self) -> &[ProcessEntry] {
+        &self.processes
+    }
+    /// Returns the process with id `pid`
+    pub fn get(&self, pid: u32) -> Option<&ProcessEntry> {
+        self.processes.iter().find(|process| process.pid == pid)
+    }
+    /// Returns the process that used the most CPU time in the interval
+    pub fn busiest(&self) -> Option<&ProcessEntry> {
+        self.processes
+            .iter()
+            .max_by_key(|process| process.cpu_time())
+    }
+    /// Returns the CPU time all processes used in the interval
+    ///
+    /// `include_reaped` works like for `ProcessesSnapshot::tree_cpu_time()`.
+    pub fn total(&self, include_reaped: bool) -> SplitTime {
+        sum(self.processes.iter().collect(), include_reaped)
+    }
+}
+
+impl Sub<&ProcessesSnapshot> for &ProcessesSnapshot {
+    type Output = ProcessesDelta;
+
+    fn sub(self, earlier: &ProcessesSnapshot) -> ProcessesDelta {
+        let earlier = earlier
+            .processes
+            .iter()
+            .map(|process| (process.pid, process))
+            .collect::<HashMap<_, _>>();
+        let processes = self
+            .processes
+            .iter()
+            .map(|process| match earlier.get(&process.pid) {
+                // A smaller time means that the id was reused
+                Some(start) if start.user <= process.user && start.system <= process.system => {
+                    ProcessEntry {
+                        user: process.user - start.user,
+                        system: process.system - start.system,
+                        children: match (process.children, start.children) {
+                            (Some(children), Some(start)) => {
+                                Some(children.saturating_duration_since(start))
+                            }
+                            (children, _) => children,
+                        },
+                        ..process.clone()
+                    }
+                }
+                _ => process.clone(),
+            })
+            .collect();
+        ProcessesDelta { processes }
+    }
+}
+
+impl Sub for ProcessesSnapshot {
+    type Output = ProcessesDelta;
+
+    fn sub(self, earlier: ProcessesSnapshot) -> ProcessesDelta {
+        &self - &earlier
+    }
+}
+
+impl IntoIterator for ProcessesSnapshot {
+    type Item = ProcessEntry;
+    type IntoIter = vec::IntoIter<ProcessEntry>;
+    fn into_iter(self) -> vec::IntoIter<ProcessEntry> {
+        self.processes.into_iter()
+    }
+}
+
+/// Returns every visible process with its CPU time
+///
+/// Same as `ProcessesSnapshot::capture()`, as an iterator.
+pub fn processes() -> Result<vec::IntoIter<ProcessEntry>, CpuTimeError> {
+    ProcessesSnapshot::capture().map(|snapshot| snapshot.into_iter())
+}
+
+/// Returns the CPU time used by process `root` and all its descendants
+///
+/// Takes a `ProcessesSnapshot` and calls `tree_cpu_time()`, see there.
+/// Returns `CpuTimeError::ProcessGone` if there is no process `root`.
+///
+/// ```rust,no_run
+/// use std::process::Command;
+///
+/// let mut build = Command::new("make").spawn().unwrap();
+/// // ...
+/// let used = cpu_time::process_tree_time(build.id(), true).unwrap();
+/// println!("make and its compilers used {:?} so far", used.total());
+/// # build.wait().unwrap();
+/// ```
+pub fn process_tree_time(root: u32, include_reaped: bool) -> Result<SplitTime, CpuTimeError> {
+    ProcessesSnapshot::capture()?
+        .tree_cpu_time(root, include_reaped)
+        .ok_or(CpuTimeError::ProcessGone)
+}
+
+/// Returns the CPU time used by the processes of process group `pgid`
+///
+/// Takes a `ProcessesSnapshot` and calls `group_cpu_time()`, see there.
+/// Returns `CpuTimeError::ProcessGone` if the group has no processes,
+/// which is always the case on Windows.
+pub fn process_group_time(pgid: u32, include_reaped: bool) -> Result<SplitTime, CpuTimeError> {
+    ProcessesSnapshot::capture()?
+        .group_cpu_time(pgid, include_reaped)
+        .ok_or(CpuTimeError::ProcessGone)
+}
+
+/// Returns the CPU time used by the processes of each user
+///
+/// Takes a `ProcessesSnapshot` and calls `cpu_time_by_uid()`, see there.
+pub fn process_time_by_uid(include_reaped: bool) -> Result<BTreeMap<u32, SplitTime>, CpuTimeError> {
+    ProcessesSnapshot::capture().map(|snapshot| snapshot.cpu_time_by_uid(include_reaped))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn capture() -> Result<Vec<ProcessEntry>, CpuTimeError> {
+    use std::fs;
+    use std::os::unix::fs::MetadataExt;
+
+    let entries = fs::read_dir("/proc").map_err(|_| CpuTimeError::Unsupported)?;
+    let mut processes = Vec::new();
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(_) => continue,
+        };
+        // Everything else in `/proc` has a non-numeric name
+        let pid = match entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse().ok())
+        {
+            Some(pid) => pid,
+            None => continue,
+        };
+        let entry_uid = entry.metadata().ok().map(|metadata| metadata.uid());
+        let stat = match Stat::read(pid) {
+            Ok(stat) => stat,
+            Err(_) => continue,
+        };
+        let name = match fs::read_to_string(format!("/proc/{}/comm", pid)) {
+            Ok(name) => name.trim_end_matches('\n').to_string(),
+            Err(_) => continue,
+        };
+        processes.push(ProcessEntry {
+            pid,
+            ppid: stat.ppid,
+            pgid: Some(stat.pgrp),
+            // `/proc/<pid>` belongs to the effective user of the process
+            uid: entry_uid,
+            name,
+            user: stat.utime,
+            system: stat.stime,
+            children: Some(stat.children()),
+        });
+    }
+    Ok(processes)
+}
+
+#[cfg(target_os = "macos")]
+fn capture() -> Result<Vec<ProcessEntry>, CpuTimeError> {
+    mach::processes().ok_or(CpuTimeError::Unsupported)
+}
+
+#[cfg(windows)]
+fn capture() -> Result<Vec<ProcessEntry>, CpuTimeError> {
+    let snapshot = Snapshot::capture()?;
+    Ok(snapshot
+        .processes()
+        .map(|process| ProcessEntry {
+            pid: process.pid(),
+            ppid: process.inherited_from_unique_process_id as usize as u32,
+            pgid: None,
+            uid: None,
+            name: process.name(),
+            user: to_duration(process.user_time),
+            system: to_duration(process.kernel_time),
+            children: None,
+        })
+        .collect())
+}
+
+#[cfg(feature = "proptest")]
+impl ::proptest::arbitrary::Arbitrary for ProcessEntry {
+    type Parameters = ();
+    type Strategy = ::proptest::strategy::BoxedStrategy<ProcessEntry>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        use proptest::arbitrary::any;
+        use proptest::strategy::Strategy;
+
+        any::<(
+            u32,
+            u32,
+            Option<u32>,
+            Option<u32>,
+            String,
+            Duration,
+            Duration,
+            Option<SplitTime>,
+        )>()
+        .prop_map(
+            |(pid, ppid, pgid, uid, name, user, system, children)| ProcessEntry {
+                pid,
+                ppid,
+                pgid,
+                uid,
+                name,
+                user,
+                system,
+                children,
+            },
+        )
+        .boxed()
+    }
+}
+
+#[cfg(feature = "proptest")]
+impl ::proptest::arbitrary::Arbitrary for ProcessesSnapshot {
+    type Parameters = ();
+    type Strategy = ::proptest::strategy::BoxedStrategy<ProcessesSnapshot>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        use proptest::arbitrary::any;
+        use proptest::strategy::Strategy;
+
+        any::<Vec<ProcessEntry>>()
+            .prop_map(|processes| ProcessesSnapshot { processes })
+            .boxed()
+    }
+}
@@ -0,0 +1,156 @@
+//! CPU time of any process from `/proc/<pid>/stat` (Linux and Android)
+//!
+//! `ProcessTime::now_for()` falls back to this when the clock of
+//! `clock_getcpuclockid()` can't be read. It's also handy for monitoring
+//! tools on its own, as one read returns the process' own and its
+//! children's times, when it was started and which CPU ran it last:
+//!
+//! ```rust,no_run
+//! use cpu_time::procfs::Stat;
+//!
+//! let stat = Stat::read(1).unwrap();
+//! println!("init used {:?} of CPU, last on CPU {}", stat.cpu_time(), stat.processor);
+//! ```
+//!
+//! `Stat::read_thread()` and `thread_split()` read a thread of this process
+//! that is only known by its OS thread id, from `/proc/self/task/<tid>`.
+//!
+//! The kernel counts these times in clock ticks (`sysconf(_SC_CLK_TCK)`,
+//! usually 10 ms), so they are much coarser than the CPU-time clocks.
+use std::fs;
+use std::io::ErrorKind;
+use std::time::Duration;
+
+use CpuTimeError;
+use SplitTime;
+
+/// The CPU-related fields of `/proc/<pid>/stat`
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash, Default)]
+pub struct Stat {
+    /// Process id, field 1
+    pub pid: u32,
+    /// Parent process id, field 4
+    pub ppid: u32,
+    /// Process group id, field 5
+    pub pgrp: u32,
+    /// Time spent in user mode, field 14
+    pub utime: Duration,
+    /// Time spent in kernel mode, field 15
+    pub stime: Duration,
+    /// User time of the waited-for children, field 16
+    pub cutime: Duration,
+    /// System time of the waited-for children, field 17
+    pub cstime: Duration,
+    /// When the process started, after system boot, field 22
+    pub starttime: Duration,
+    /// The CPU the process last ran on, field 39
+    pub processor: u32,
+}
+
+impl Stat {
+    /// Reads `/proc/<pid>/stat`
+    ///
+    /// Returns `CpuTimeError::ProcessGone` if there is no such process and
+    /// `CpuTimeError::Unsupported` if the file can't be parsed, e.g. because
+    /// `/proc` isn't mounted by procfs.
+    pub fn read(pid: u32) -> Result<Stat, CpuTimeError> {
+        read(&format!("/proc/{}/stat", pid))
+    }
+    /// Reads `/proc/self/stat`
+    pub fn read_self() -> Result<Stat, CpuTimeError> {
+        read("/proc/self/stat")
+    }
+    /// Reads `/proc/self/task/<tid>/stat`, for a thread of this process
+    ///
+    /// `pid` is then the thread id and the times are the thread's own. The
+    /// children's times are still those of the whole process.
+    pub fn read_thread(tid: u32) -> Result<Stat, CpuTimeError> {
+        read(&format!("/proc/self/task/{}/stat", tid))
+    }
+    /// Parses the contents of a `stat` file
+    ///
+    /// Returns `None` if a field is missing or malformed.
+    pub fn parse(stat: &str) -> Option<Stat> {
+        let pid = stat.split_whitespace().next()?.parse().ok()?;
+        // The command name in parentheses may contain spaces and
+        // parentheses, the other fields start after the last `)` with field
+        // 3, the state
+        let fields = &stat[stat.rfind(')')? + 1..];
+        let fields = fields.split_whitespace().collect::<Vec<_>>();
+        let field = |number: usize| fields.get(number - 3).cloned();
+        let ticks = |number: usize| -> Option<Duration> {
+            // `cutime` and `cstime` are signed, but never negative
+            let ticks = field(number)?.parse::<i64>().ok()?;
+            Some(ticks_to_duration(if ticks < 0 { 0 } else { ticks as u64 }))
+        };
+        Some(Stat {
+            pid,
+            ppid: field(4)?.parse().ok()?,
+            pgrp: field(5)?.parse().ok()?,
+            utime: ticks(14)?,
+            stime: ticks(15)?,
+            cutime: ticks(16)?,
+            cstime: ticks(17)?,
+            starttime: field(22)?.parse::<u64>().ok().map(ticks_to_duration)?,
+            processor: field(39)?.parse().ok()?,
+        })
+    }
+    /// Returns the process' user and system time added up
+    pub fn cpu_time(&self) -> Duration {
+        self.utime.saturating_add(self.stime)
+    }
+    /// Returns the process' user and system time
+    pub fn split(&self) -> SplitTime {
+        SplitTime {
+            user: self.utime,
+            system: self.stime,
+        }
+    }
+    /// Returns the user and system time of the waited-for children
+    pub fn children(&self) -> SplitTime {
+        SplitTime {
+            user: self.cutime,
+            system: self.cstime,
+        }
+    }
+}
+
+/// Returns the user and system time of the thread with OS id `tid`
+///
+/// Meant for threads that are only known by their tid (`gettid()`), e.g.
+/// from a crash handler or a log. Returns `CpuTimeError::ProcessGone` if
+/// this process has no such thread.
+///
+/// ```rust,no_run
+/// let split = cpu_time::procfs::thread_split(1234).unwrap();
+/// println!("user {:?}, system {:?}", split.user, split.system);
+/// ```
+pub fn thread_split(tid: u32) -> Result<SplitTime, CpuTimeError> {
+    Stat::read_thread(tid).map(|stat| stat.split())
+}
+
+fn read(path: &str) -> Result<Stat, CpuTimeError> {
+    let stat = match fs::read_to_string(path) {
+        Ok(stat) => stat,
+        Err(ref error) if error.kind() == ErrorKind::NotFound => {
+            return Err(CpuTimeError::ProcessGone)
+        }
+        Err(ref error) if error.kind() == ErrorKind::PermissionDenied => {
+            return Err(CpuTimeError::PermissionDenied)
+        }
+        Err(error) => return Err(CpuTimeError::Os(error.raw_os_error().unwrap_or(0))),
+    };
+    Stat::parse(&stat).ok_or(CpuTimeError::Unsupported)
+}
+
+fn ticks_to_duration(ticks: u64) -> Duration {
+    let per_sec = match unsafe { libc::sysconf(libc::_SC_CLK_TCK) } {
+        per_sec if per_sec > 0 => per_sec as u64,
+        // `USER_HZ`, fixed on almost every architecture
+        _ => 100,
+    };
+    Duration::new(
+        ticks / per_sec,
+        ((ticks % per_sec) * 1_000_000_000 / per_sec) as u32,
+    )
+}
@@ -0,0 +1,324 @@
+//! Hierarchical CPU-time profiling of regions of code
+//!
+//! A CPU-time-only take on instrumenting profilers like tracy or puffin,
+//! for code that can't take a full profiler dependency. A `Session`
+//! records the `region()`s entered on its thread into a tree, with how
+//! often each was entered and how much CPU time it used:
+//!
+//! ```rust
+//! use cpu_time::profile::{region, Session};
+//!
+//! # fn lex() {}
+//! # fn link() {}
+//! let session = Session::start();
+//! {
+//!     let _compile = region("compile");
+//!     for _ in 0..10 {
+//!         let _lex = region("lex");
+//!         lex();
+//!     }
+//!     let _link = region("link");
+//!     link();
+//! }
+//! let report = session.finish();
+//! let lex = report.get(&["compile", "lex"]).unwrap();
+//! println!("lexing ran {} times and used {:?}", lex.calls, lex.inclusive);
+//! ```
+//!
+//! Regions with the same name under the same parent are merged, so a loop
+//! adds up into one node. The times are those of the thread's CPU clock
+//! (`ThreadTime`): a `region()` on a thread without a running session does
+//! nothing and doesn't read the clock, so instrumentation can stay in
+//! place. `Report` prints the tree as a table.
+use std::cell::RefCell;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use {Event, Region, Report, SplitTime, ThreadTime};
+
+struct Node {
+    name: &'static str,
+    calls: u64,
+    inclusive: Duration,
+    // `None` if not recorded or a reading failed
+    split: Option<SplitTime>,
+    children: Vec<usize>,
+}
+
+struct Recorder {
+    session: usize,
+    start: ThreadTime,
+    // Whether to read `ThreadTime::try_now_split()` too
+    split: bool,
+    nodes: Vec<Node>,
+    roots: Vec<usize>,
+    // The open regions, innermost last, with when they were entered and
+    // how many regions were entered before
+    stack: Vec<(usize, ThreadTime, Option<SplitTime>, usize)>,
+    entered: usize,
+    // Every region left: its place in the order of entering, name, depth,
+    // when it was entered and for how long
+    events: Vec<(usize, &'static str, usize, Duration, Duration)>,
+}
+
+impl Recorder {
+    fn now_split(&self) -> Option<SplitTime> {
+        if self.split {
+            ThreadTime::try_now_split().ok()
+        } else {
+            None
+        }
+    }
+}
+
+impl Recorder {
+    fn enter(&mut self, name: &'static str) {
+        let siblings = match self.stack.last() {
+            Some(&(parent, _, _, _)) => &self.nodes[parent].children,
+            None => &self.roots,
+        };
+        let node = match siblings
+            .iter()
+            .cloned()
+            .find(|&node| self.nodes[node].name == name)
+        {
+            Some(node) => node,
+            None => {
+                let node = self.nodes.len();
+                self.nodes.push(Node {
+                    name,
+                    calls: 0,
+                    inclusive: Duration::new(0, 0),
+                    split: if self.split {
+                        Some(SplitTime::default())
+                    } else {
+                        None
+                    },
+                    children: Vec::new(),
+                });
+                match self.stack.last() {
+                    Some(&(parent, _, _, _)) => self.nodes[parent].children.push(node),
+                    None => self.roots.push(node),
+                }
+                node
+            }
+        };
+        self.nodes[node].calls += 1;
+        let split = self.now_split();
+        self.stack
+            .push((node, ThreadTime::now(), split, self.entered));
+        self.entered += 1;
+    }
+    // Closes the open regions down to `depth`
+    fn exit(&mut self, depth: usize) {
+        if self.stack.len() <= depth {
+            return;
+        }
+        let now = ThreadTime::now();
+        let now_split = self.now_split();
+        while self.stack.len() > depth {
+            let (node, entered, entered_split, order) = self.stack.pop().unwrap();
+            let node = &mut self.nodes[node];
+            let used = now.saturating_duration_since(entered);
+            node.inclusive = node.inclusive.saturating_add(used);
+            self.events.push((
+                order,
+                node.name,
+                self.stack.len(),
+                entered.saturating_duration_since(self.start),
+                used,
+            ));
+            node.split = match (node.split, entered_split, now_split) {
+                (Some(sum), Some(entered), Some(now)) => {
+                    Some(sum.saturating_add(now.saturating_duration_since(entered)))
+                }
+                _ => None,
+            };
+        }
+    }
+    fn region(&self, node: usize) -> Region {
+        let node = &self.nodes[node];
+        let children = node
+            .children
+            .iter()
+            .map(|&child| self.region(child))
+            .collect::<Vec<_>>();
+        let nested = children.iter().fold(Duration::new(0, 0), |sum, child| {
+            sum.saturating_add(child.inclusive)
+        });
+        Region {
+            name: node.name.to_string(),
+            calls: node.calls,
+            inclusive: node.inclusive,
+            exclusive: node.inclusive.saturating_sub(nested),
+            split: node.split,
+            children,
+        }
+    }
+}
+
+thread_local! {
+    static RECORDER: RefCell<Option<Recorder>> = const { RefCell::new(None) };
+}
+
+// Tells the sessions apart, so that a guard outliving its session doesn't
+// close regions of the next one
+static SESSIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// Records the regions entered on the current thread until `finish()`
+///
+/// Only one session can run on a thread at a time. Dropping it without
+/// `finish()` discards what was recorded.
+#[derive(Debug)]
+pub struct Session {
+    id: usize,
+    // The recording belongs to the thread
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Session {
+    /// Starts recording on the current thread
+    ///
+    /// # Panics
+    ///
+    /// If a session is already running on this thread, or the thread CPU
+    /// time can't be read, like `ThreadTime::now()`.
+    pub fn start() -> Session {
+        Session::begin(false)
+    }
+    /// Starts recording on the current thread, with the user and system
+    /// time of each region
+    ///
+    /// Reads `ThreadTime::try_now_split()` along with the clock, which
+    /// makes entering and leaving a region slower and is coarser on some
+    /// platforms (microseconds from `getrusage()` on Linux). The `split` of
+    /// a region is `None` if a reading failed.
+    ///
+    /// # Panics
+    ///
+    /// Like `start()`.
+    pub fn start_split() -> Session {
+        Session::begin(true)
+    }
+    fn begin(split: bool) -> Session {
+        let id = SESSIONS.fetch_add(1, Ordering::Relaxed);
+        RECORDER.with(|recorder| {
+            let mut recorder = recorder.borrow_mut();
+            assert!(
+                recorder.is_none(),
+                "a profiling session is already running on this thread"
+            );
+            *recorder = Some(Recorder {
+                session: id,
+                start: ThreadTime::now(),
+                split,
+                nodes: Vec::new(),
+                roots: Vec::new(),
+                stack: Vec::new(),
+                entered: 0,
+                events: Vec::new(),
+            });
+        });
+        Session {
+            id,
+            _not_send: PhantomData,
+        }
+    }
+    /// Stops recording and returns the region tree, with every region
+    /// entered as an `Event`
+    ///
+    /// Regions that are still open count until now.
+    pub fn finish(self) -> Report {
+        let recorder = RECORDER.with(|recorder| recorder.borrow_mut().take());
+        let mut recorder = recorder.expect("profiling session vanished");
+        recorder.exit(0);
+        // Left innermost first, but traces are read outermost first. The
+        // starts can't tell: coarse clocks give many regions the same one
+        recorder.events.sort_by_key(|&(order, _, _, _, _)| order);
+        let events = recorder
+            .events
+            .iter()
+            .map(|&(_, name, depth, start, duration)| Event {
+                name: name.to_string(),
+                depth,
+                start,
+                duration,
+            })
+            .collect();
+        Report {
+            total: recorder.start.elapsed(),
+            regions: recorder
+                .roots
+                .iter()
+                .map(|&root| recorder.region(root))
+                .collect(),
+            events,
+        }
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        // Ignores a thread-local that is already destroyed during thread
+        // exit
+        let _ = RECORDER.try_with(|recorder| {
+            if let Ok(mut recorder) = recorder.try_borrow_mut() {
+                if recorder.as_ref().map(|recorder| recorder.session) == Some(self.id) {
+                    *recorder = None;
+                }
+            }
+        });
+    }
+}
+
+/// An open region, which ends when this guard is dropped
+///
+/// Returned by `region()`.
+#[derive(Debug)]
+pub struct RegionGuard {
+    // The session and the depth in its stack, `None` if no session was
+    // running
+    open: Option<(usize, usize)>,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for RegionGuard {
+    fn drop(&mut self) {
+        let (session, depth) = match self.open {
+            Some(open) => open,
+            None => return,
+        };
+        let _ = RECORDER.try_with(|recorder| {
+            if let Some(ref mut recorder) = *recorder.borrow_mut() {
+                if recorder.session == session {
+                    recorder.exit(depth);
+                }
+            }
+        });
+    }
+}
+
+/// Enters the region `name` until the returned guard is dropped
+///
+/// Nested calls build the tree of the thread's running `Session`. Without
+/// one this does nothing. Dropping a guard also ends the regions entered
+/// after it that are still open.
+///
+/// # Panics
+///
+/// If the thread CPU time can't be read while a session is running, like
+/// `ThreadTime::now()`.
+pub fn region(name: &'static str) -> RegionGuard {
+    let open = RECORDER.with(|recorder| {
+        let mut recorder = recorder.borrow_mut();
+        let recorder = recorder.as_mut()?;
+        let depth = recorder.stack.len();
+        recorder.enter(name);
+        Some((recorder.session, depth))
+    });
+    RegionGuard {
+        open,
+        _not_send: PhantomData,
+    }
+}
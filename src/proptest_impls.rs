@@ -0,0 +1,24 @@
+use proptest::arbitrary::{any, Arbitrary};
+use proptest::num::u64;
+use proptest::strategy::{Map, Strategy};
+
+use {ProcessTime, ThreadTime};
+
+// Timestamps are generated from nanoseconds, so they can also be serialized
+impl Arbitrary for ProcessTime {
+    type Parameters = ();
+    type Strategy = Map<u64::Any, fn(u64) -> ProcessTime>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        any::<u64>().prop_map(ProcessTime::from_nanos)
+    }
+}
+
+impl Arbitrary for ThreadTime {
+    type Parameters = ();
+    type Strategy = Map<u64::Any, fn(u64) -> ThreadTime>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        any::<u64>().prop_map(ThreadTime::from_nanos)
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::BTreeMap;
+use std::ops::AddAssign;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+/// The CPU time added up under one label of the global accumulators
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash, Default)]
+pub struct Accumulated {
+    /// How often time was added, e.g. how often a `time_block!` ran
+    pub calls: u64,
+    /// The CPU time added up
+    pub total: Duration,
+}
+
+impl Accumulated {
+    /// Returns the mean CPU time of one call, zero if there were none
+    pub fn mean(&self) -> Duration {
+        match self.calls {
+            0 => Duration::new(0, 0),
+            calls => {
+                let nanos = self.total.as_nanos() / calls as u128;
+                Duration::new(
+                    (nanos / 1_000_000_000) as u64,
+                    (nanos % 1_000_000_000) as u32,
+                )
+            }
+        }
+    }
+}
+
+static ACCUMULATORS: Mutex<BTreeMap<String, Accumulated>> = Mutex::new(BTreeMap::new());
+
+fn lock() -> MutexGuard<'static, BTreeMap<String, Accumulated>> {
+    // The map is consistent after every operation, a panic elsewhere doesn't
+    // make it unusable
+    ACCUMULATORS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Measures the thread CPU time of a block and adds it to a global
+/// accumulator
+///
+/// Attributes scattered hot spots without restructuring the code around
+/// them. The block's value is returned:
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate cpu_time;
+///
+/// # fn parse(line: &str) -> usize { line.len() }
+/// # fn main() {
+/// let mut lengths = 0;
+/// for line in ["a", "bc"].iter() {
+///     lengths += time_block!("parse", { parse(line) });
+/// }
+/// let parse = cpu_time::accumulated("parse");
+/// println!("{} parses used {:?}", parse.calls, parse.total);
+/// # }
+/// ```
+///
+/// The time is added to the accumulator of that label (see `accumulate()`)
+/// when the block is left, also by `return`, `?` or a panic.
+///
+/// # Panics
+///
+/// If the thread CPU time can't be read, like `ThreadTime::now()`.
+#[macro_export]
+macro_rules! time_block {
+    ($label:expr, $body:block $(,)*) => {{
+        let _scope = $crate::CpuScope::with_sink($label, $crate::accumulate);
+        $body
+    }};
+}
+
+/// Adds `time` to the global accumulator of `label`, counting one call
+///
+/// The accumulator is created on first use. This is what `time_block!`
+/// reports to, and a sink for `CpuScope::with_sink()`.
+pub fn accumulate(label: &str, time: Duration) {
+    let mut accumulators = lock();
+    if let Some(accumulated) = accumulators.get_mut(label) {
+        accumulated.calls += 1;
+        accumulated.total = accumulated.total.saturating_add(time);
+        return;
+    }
+    accumulators.insert(
+        label.to_string(),
+        Accumulated {
+            calls: 1,
+            total: time,
+        },
+    );
+}
+
+/// Returns the global accumulator of `label`, zero if nothing was added
+pub fn accumulated(label: &str) -> Accumulated {
+    lock().get(label).cloned().unwrap_or_default()
+}
+
+/// Returns all global accumulators, sorted by label
+pub fn accumulators() -> Vec<(String, Accumulated)> {
+    lock()
+        .iter()
+        .map(|(label, accumulated)| (label.clone(), *accumulated))
+        .collect()
+}
+
+/// Removes all global accumulators, e.g. between benchmark runs
+pub fn reset_accumulators() {
+    lock().clear();
+}
+
+/// A handle to the global accumulator of a label, returned by `counter()`
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub struct Counter<'a> {
+    label: &'a str,
+}
+
+impl<'a> Counter<'a> {
+    /// Returns the label of the accumulator
+    pub fn label(&self) -> &'a str {
+        self.label
+    }
+    /// Adds `time`, counting one call, same as `+=`
+    pub fn add(&self, time: Duration) {
+        accumulate(self.label, time);
+    }
+    /// Returns what was added so far
+    pub fn get(&self) -> Accumulated {
+        accumulated(self.label)
+    }
+}
+
+impl<'a> AddAssign<Duration> for Counter<'a> {
+    fn add_assign(&mut self, time: Duration) {
+        accumulate(self.label, time);
+    }
+}
+
+/// Returns the global accumulator of `label`, to add to from anywhere
+///
+/// For ad-hoc investigations that shouldn't need state plumbed through
+/// every layer:
+///
+/// ```rust
+/// use cpu_time::{counter, ThreadTime};
+///
+/// # fn decode() {}
+/// let start = ThreadTime::now();
+/// decode();
+/// counter("io::decode").add(start.elapsed());
+///
+/// // Or keep the handle around
+/// let mut decoding = counter("io::decode");
+/// let start = ThreadTime::now();
+/// decode();
+/// decoding += start.elapsed();
+/// ```
+///
+/// Same as `accumulate()`, see `report_at_exit()` to print all of them at
+/// the end.
+pub fn counter(label: &str) -> Counter<'_> {
+    Counter { label }
+}
+
+#[cfg(all(any(all(unix, feature = "libc"), windows), not(miri)))]
+pub use self::exit::report_at_exit;
+
+#[cfg(all(any(all(unix, feature = "libc"), windows), not(miri)))]
+mod exit {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Once;
+
+    #[cfg(unix)]
+    use libc::atexit;
+
+    use Report;
+
+    #[cfg(windows)]
+    extern "C" {
+        // From the C runtime
+        fn atexit(callback: extern "C" fn()) -> i32;
+    }
+
+    extern "C" fn print_summary() {
+        use std::io::{stderr, Write};
+
+        let report = Report::accumulators();
+        if report.regions.is_empty() {
+            return;
+        }
+        let summary = format!("CPU time by label:\n{}", report);
+        // Nothing to do about an error this late, and a panic can't unwind
+        // out of the handler
+        let _ = stderr().write_all(summary.as_bytes());
+    }
+
+    /// Prints a table of the global accumulators to stderr when the
+    /// process exits
+    ///
+    /// Registers an `atexit()` handler, on unix with the `libc` feature and
+    /// on Windows. It runs when `main()` returns or `std::process::exit()`
+    /// is called, not when the process is killed or aborts. The table is
+    /// the `Display` of `Report::accumulators()`, nothing is printed if
+    /// there are none. Calling this more than once registers the handler
+    /// only once.
+    ///
+    /// Returns `false` if the handler couldn't be registered.
+    pub fn report_at_exit() -> bool {
+        static REGISTER: Once = Once::new();
+        static REGISTERED: AtomicBool = AtomicBool::new(false);
+
+        REGISTER.call_once(|| {
+            let registered = unsafe { atexit(print_summary) } == 0;
+            REGISTERED.store(registered, Ordering::SeqCst);
+        });
+        REGISTERED.load(Ordering::SeqCst)
+    }
+}
@@ -0,0 +1,269 @@
+use std::cmp::Reverse;
+use std::fmt;
+use std::time::Duration;
+
+use registry::accumulators;
+use {Accumulated, SplitTime};
+
+/// A labelled row of a `Report`, and in profiling sessions a node of its
+/// region tree
+#[derive(Clone, PartialEq, Eq, Debug, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct Region {
+    /// The name passed to `region()`, or the label of an accumulator
+    pub name: String,
+    /// How often the region was entered
+    pub calls: u64,
+    /// CPU time spent in the region, including its children
+    #[cfg_attr(feature = "serde", serde(with = "::nanos::serde"))]
+    pub inclusive: Duration,
+    /// CPU time spent in the region itself, excluding its children
+    #[cfg_attr(feature = "serde", serde(with = "::nanos::serde"))]
+    pub exclusive: Duration,
+    /// The user and system part of `inclusive`, if it was recorded (see
+    /// `Session::start_split()`)
+    pub split: Option<SplitTime>,
+    /// The regions entered inside of this one, in the order they were
+    /// first entered
+    pub children: Vec<Region>,
+}
+
+impl Region {
+    /// Returns the child region called `name`
+    pub fn child(&self, name: &str) -> Option<&Region> {
+        self.children.iter().find(|child| child.name == name)
+    }
+    /// Returns the mean inclusive CPU time per call, zero if there were none
+    pub fn mean(&self) -> Duration {
+        match self.calls {
+            0 => Duration::new(0, 0),
+            calls => {
+                let nanos = self.inclusive.as_nanos() / calls as u128;
+                Duration::new(
+                    (nanos / 1_000_000_000) as u64,
+                    (nanos % 1_000_000_000) as u32,
+                )
+            }
+        }
+    }
+}
+
+/// One entry into a region of a profiling session
+///
+/// The times are CPU time of the session's thread, so a region that waits
+/// (e.g. sleeps) is shorter than in wall-clock time and the next one starts
+/// right after it.
+#[derive(Clone, PartialEq, Eq, Debug, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct Event {
+    /// The name passed to `region()`
+    pub name: String,
+    /// How many regions were open around it, zero for an outermost one
+    pub depth: usize,
+    /// When the region was entered, in CPU time since `Session::start()`
+    #[cfg_attr(feature = "serde", serde(with = "::nanos::serde"))]
+    pub start: Duration,
+    /// The CPU time it used until it was left, including nested regions
+    #[cfg_attr(feature = "serde", serde(with = "::nanos::serde"))]
+    pub duration: Duration,
+}
+
+/// CPU time by label, from a profiling session or the global accumulators
+///
+/// `profile::Session::finish()` returns the tree of regions it recorded,
+/// `Report::accumulators()` has one region per accumulator. `Display`
+/// prints an aligned table, nested regions indented below their parent:
+///
+/// ```text
+/// label        calls        total         mean       %
+/// compile          1     31.502ms     31.502ms   97.3%
+///   lex           10     20.113ms      2.011ms   62.1%
+///   link           1     11.204ms     11.204ms   34.6%
+/// ```
+///
+/// The percentage is of `total`. If any region has a `split`, the table
+/// also has `user` and `system` columns, `-` where it's unknown.
+#[derive(Clone, PartialEq, Eq, Debug, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct Report {
+    /// The CPU time the percentages refer to: what the thread used from
+    /// `Session::start()` to `finish()`, or the sum of the accumulators
+    #[cfg_attr(feature = "serde", serde(with = "::nanos::serde"))]
+    pub total: Duration,
+    /// The outermost regions, in the order they were first entered, or the
+    /// accumulators with the largest total first
+    pub regions: Vec<Region>,
+    /// Every region entered in a profiling session, in the order they were
+    /// entered, for timeline exports like `write_chrome_trace()`. Empty for
+    /// the accumulators.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub events: Vec<Event>,
+}
+
+impl Report {
+    /// Returns the global accumulators of `accumulate()`, `time_block!` and
+    /// `counter()`, the largest total first
+    pub fn accumulators() -> Report {
+        accumulator_report(accumulators())
+    }
+    /// Returns the region at `path`, the names from the outermost region
+    /// down
+    pub fn get(&self, path: &[&str]) -> Option<&Region> {
+        let (first, rest) = path.split_first()?;
+        let mut region = self.regions.iter().find(|region| region.name == *first)?;
+        for name in rest {
+            region = region.child(name)?;
+        }
+        Some(region)
+    }
+    /// Adds `other` to this report, e.g. to combine the reports of several
+    /// worker processes into one
+    ///
+    /// Regions at the same path are combined, adding up their calls and
+    /// times, the others are appended to their parent. A `split` stays
+    /// only if both regions have one. `total` is added up and the events
+    /// of `other` are appended, shifted by the previous `total`, so that
+    /// traces of the merged report show the sessions one after the other
+    /// rather than overlapping.
+    ///
+    /// ```rust
+    /// use cpu_time::Report;
+    ///
+    /// # let from_workers: Vec<Report> = Vec::new();
+    /// let mut combined = Report::default();
+    /// for report in &from_workers {
+    ///     combined.merge(report);
+    /// }
+    /// println!("{}", combined);
+    /// ```
+    ///
+    /// With the `serde` feature `Report` implements `Serialize` and
+    /// `Deserialize`, so workers can send theirs in any serde format. The
+    /// field names are those of the struct and stay stable, the times are
+    /// in nanoseconds and unknown fields are ignored.
+    pub fn merge(&mut self, other: &Report) {
+        // The starts of `other` are relative to its own session
+        let offset = self.total;
+        self.total = self.total.saturating_add(other.total);
+        merge_regions(&mut self.regions, &other.regions);
+        self.events.extend(other.events.iter().map(|event| Event {
+            name: event.name.clone(),
+            depth: event.depth,
+            start: offset.saturating_add(event.start),
+            duration: event.duration,
+        }));
+    }
+    /// Returns the CPU time used outside of any region
+    pub fn unaccounted(&self) -> Duration {
+        let accounted = self
+            .regions
+            .iter()
+            .fold(Duration::new(0, 0), |sum, region| {
+                sum.saturating_add(region.inclusive)
+            });
+        self.total.saturating_sub(accounted)
+    }
+}
+
+// A region per accumulator, the largest total first
+pub fn accumulator_report(accumulators: Vec<(String, Accumulated)>) -> Report {
+    let mut regions = accumulators
+        .into_iter()
+        .map(|(name, accumulated)| Region {
+            name,
+            calls: accumulated.calls,
+            inclusive: accumulated.total,
+            exclusive: accumulated.total,
+            split: None,
+            children: Vec::new(),
+        })
+        .collect::<Vec<_>>();
+    regions.sort_by_key(|region| Reverse(region.inclusive));
+    Report {
+        total: regions.iter().fold(Duration::new(0, 0), |sum, region| {
+            sum.saturating_add(region.inclusive)
+        }),
+        regions,
+        events: Vec::new(),
+    }
+}
+
+fn merge_regions(regions: &mut Vec<Region>, others: &[Region]) {
+    for other in others {
+        let region = match regions
+            .iter_mut()
+            .position(|region| region.name == other.name)
+        {
+            Some(index) => &mut regions[index],
+            None => {
+                regions.push(other.clone());
+                continue;
+            }
+        };
+        region.calls = region.calls.saturating_add(other.calls);
+        region.inclusive = region.inclusive.saturating_add(other.inclusive);
+        region.exclusive = region.exclusive.saturating_add(other.exclusive);
+        region.split = match (region.split, other.split) {
+            (Some(split), Some(other)) => Some(split.saturating_add(other)),
+            _ => None,
+        };
+        merge_regions(&mut region.children, &other.children);
+    }
+}
+
+// The regions in table order, with their depth
+fn flatten<'a>(regions: &'a [Region], depth: usize, rows: &mut Vec<(usize, &'a Region)>) {
+    for region in regions {
+        rows.push((depth, region));
+        flatten(&region.children, depth + 1, rows);
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut rows = Vec::new();
+        flatten(&self.regions, 0, &mut rows);
+        let width = rows
+            .iter()
+            .map(|&(depth, region)| 2 * depth + region.name.chars().count())
+            .fold("label".len(), |width, len| width.max(len));
+        let split = rows.iter().any(|&(_, region)| region.split.is_some());
+        write!(
+            f,
+            "{:<width$} {:>10} {:>12} {:>12} {:>7}",
+            "label",
+            "calls",
+            "total",
+            "mean",
+            "%",
+            width = width
+        )?;
+        if split {
+            write!(f, " {:>12} {:>12}", "user", "system")?;
+        }
+        writeln!(f)?;
+        for (depth, region) in rows {
+            let percent = match self.total.as_nanos() {
+                0 => 0.0,
+                total => region.inclusive.as_nanos() as f64 * 100.0 / total as f64,
+            };
+            write!(
+                f,
+                "{:<width$} {:>10} {:>12.3?} {:>12.3?} {:>6.1}%",
+                format!("{:indent$}{}", "", region.name, indent = 2 * depth),
+                region.calls,
+                region.inclusive,
+                region.mean(),
+                percent,
+                width = width
+            )?;
+            match region.split {
+                Some(time) if split => write!(f, " {:>12.3?} {:>12.3?}", time.user, time.system)?,
+                None if split => write!(f, " {:>12} {:>12}", "-", "-")?,
+                _ => {}
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
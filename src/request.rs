@@ -0,0 +1,276 @@
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use http::header::HeaderValue;
+use http::{Request, Response};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use nanos::to_nanos;
+use {report, RequestCpuTime, ThreadTime};
+
+#[derive(Debug)]
+struct Shared {
+    // The CPU time of the polls that finished, in nanoseconds
+    used: AtomicU64,
+    // The thread CPU time when the running poll started, in nanoseconds
+    polling: AtomicU64,
+    // The `thread()` running the poll, zero while none is
+    thread: AtomicUsize,
+}
+
+thread_local! {
+    static THREAD: u8 = const { 0 };
+}
+
+// Tells the threads apart, never zero
+fn thread() -> usize {
+    THREAD.with(|thread| thread as *const u8 as usize)
+}
+
+impl Shared {
+    // Counts the CPU time of `f`, as a poll of the request
+    fn measure<T, F: FnOnce() -> T>(&self, f: F) -> T {
+        let start = ThreadTime::try_now();
+        if let Ok(start) = start {
+            self.polling
+                .store(to_nanos(start.duration()), Ordering::Relaxed);
+            self.thread.store(thread(), Ordering::Relaxed);
+        }
+        let result = f();
+        self.thread.store(0, Ordering::Relaxed);
+        if let (Ok(start), Ok(now)) = (start, ThreadTime::try_now()) {
+            let used = to_nanos(now.saturating_duration_since(start));
+            self.used.fetch_add(used, Ordering::Relaxed);
+        }
+        result
+    }
+}
+
+/// The CPU time a request has used so far, in the request extensions of
+/// services wrapped with `RequestCpuLayer`
+///
+/// Handlers can check it while they run, e.g. to stop expensive work
+/// early. With the `axum` feature it's an extractor:
+///
+/// ```rust,ignore
+/// async fn search(cpu: RequestCpu) -> String {
+///     // ...
+///     format!("used {:?} so far", cpu.elapsed())
+/// }
+/// ```
+///
+/// Elsewhere, take it from the extensions with
+/// `request.extensions().get::<RequestCpu>()` (axum's `Extension` works
+/// too).
+#[derive(Clone)]
+pub struct RequestCpu(Arc<Shared>);
+
+impl RequestCpu {
+    fn new() -> RequestCpu {
+        RequestCpu(Arc::new(Shared {
+            used: AtomicU64::new(0),
+            polling: AtomicU64::new(0),
+            thread: AtomicUsize::new(0),
+        }))
+    }
+    /// Returns the CPU time the request used so far
+    ///
+    /// Includes the running poll if called on the thread running it, i.e.
+    /// from the handler. Elsewhere it includes the finished polls only.
+    pub fn elapsed(&self) -> Duration {
+        let used = Duration::from_nanos(self.0.used.load(Ordering::Relaxed));
+        // Only this thread changes them while it's the one polling
+        if self.0.thread.load(Ordering::Relaxed) != thread() {
+            return used;
+        }
+        let start = Duration::from_nanos(self.0.polling.load(Ordering::Relaxed));
+        match ThreadTime::try_now() {
+            Ok(now) => used.saturating_add(now.duration().saturating_sub(start)),
+            Err(_) => used,
+        }
+    }
+}
+
+impl fmt::Debug for RequestCpu {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("RequestCpu").field(&self.elapsed()).finish()
+    }
+}
+
+/// An HTTP middleware measuring the CPU time of each request, with a
+/// `RequestCpu` extension handlers can read while they run
+///
+/// Made for axum and hyper services, where `CpuTimeLayer` alone only
+/// tells once the response is ready:
+///
+/// ```rust,ignore
+/// let app = Router::new()
+///     .route("/search", get(search))
+///     .layer(RequestCpuLayer::new());
+/// ```
+///
+/// When the response is ready it gets an `x-cpu-time` header with the
+/// CPU time in milliseconds (e.g. `x-cpu-time: 12.345ms`) and a
+/// `RequestCpuTime` extension, and the sink, the global reporter of
+/// `set_reporter()` by default, gets the method and path of the request as
+/// the label, like `GET /search`. Set `log_reporter()` to log them. Like
+/// `CpuTimeLayer`, only the polls of the handler's future are counted,
+/// not streaming the response body.
+#[derive(Copy, Clone, Debug)]
+pub struct RequestCpuLayer {
+    sink: fn(&str, Duration),
+}
+
+impl RequestCpuLayer {
+    /// Reports the requests to the global reporter
+    pub fn new() -> RequestCpuLayer {
+        RequestCpuLayer::with_sink(report)
+    }
+    /// Reports the requests to `sink`
+    pub fn with_sink(sink: fn(&str, Duration)) -> RequestCpuLayer {
+        RequestCpuLayer { sink }
+    }
+}
+
+impl Default for RequestCpuLayer {
+    fn default() -> RequestCpuLayer {
+        RequestCpuLayer::new()
+    }
+}
+
+impl<S> Layer<S> for RequestCpuLayer {
+    type Service = RequestCpuService<S>;
+
+    fn layer(&self, inner: S) -> RequestCpuService<S> {
+        RequestCpuService {
+            inner,
+            sink: self.sink,
+        }
+    }
+}
+
+/// A service adding a `RequestCpu` to each request, created by
+/// `RequestCpuLayer`
+#[derive(Clone, Debug)]
+pub struct RequestCpuService<S> {
+    inner: S,
+    sink: fn(&str, Duration),
+}
+
+impl<S> RequestCpuService<S> {
+    /// Returns the wrapped service
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S, B, T> Service<Request<B>> for RequestCpuService<S>
+where
+    S: Service<Request<B>, Response = Response<T>>,
+{
+    type Response = Response<T>;
+    type Error = S::Error;
+    type Future = RequestCpuFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<B>) -> RequestCpuFuture<S::Future> {
+        let cpu = RequestCpu::new();
+        let label = format!("{} {}", request.method(), request.uri().path());
+        request.extensions_mut().insert(cpu.clone());
+        let inner = &mut self.inner;
+        let future = cpu.0.measure(|| inner.call(request));
+        RequestCpuFuture {
+            future,
+            cpu,
+            label,
+            sink: self.sink,
+        }
+    }
+}
+
+/// The response future of `RequestCpuService`
+#[must_use = "futures do nothing unless polled"]
+pub struct RequestCpuFuture<F> {
+    future: F,
+    cpu: RequestCpu,
+    label: String,
+    sink: fn(&str, Duration),
+}
+
+impl<F> fmt::Debug for RequestCpuFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RequestCpuFuture")
+            .field("cpu", &self.cpu)
+            .field("label", &self.label)
+            .finish()
+    }
+}
+
+// Like `12.345ms`
+fn header(time: Duration) -> HeaderValue {
+    let micros = time.as_micros();
+    let value = format!("{}.{:03}ms", micros / 1000, micros % 1000);
+    HeaderValue::from_str(&value).expect("invalid header value")
+}
+
+impl<F, T, E> Future for RequestCpuFuture<F>
+where
+    F: Future<Output = Result<Response<T>, E>>,
+{
+    type Output = Result<Response<T>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        // `future` is never moved out of a pinned `RequestCpuFuture`
+        let this = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+        let poll = this.cpu.0.measure(|| future.poll(cx));
+        match poll {
+            Poll::Ready(mut result) => {
+                let used = this.cpu.elapsed();
+                if let Ok(ref mut response) = result {
+                    response.headers_mut().insert("x-cpu-time", header(used));
+                    response.extensions_mut().insert(RequestCpuTime(used));
+                }
+                (this.sink)(&this.label, used);
+                Poll::Ready(result)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(feature = "axum")]
+mod extract {
+    use std::future::{ready, Future};
+
+    use axum_core::extract::FromRequestParts;
+    use http::request::Parts;
+    use http::StatusCode;
+
+    use super::RequestCpu;
+
+    /// Takes the `RequestCpu` of the request, or rejects it with 500
+    /// Internal Server Error if the `RequestCpuLayer` is missing
+    impl<S: Sync> FromRequestParts<S> for RequestCpu {
+        type Rejection = (StatusCode, &'static str);
+
+        fn from_request_parts(
+            parts: &mut Parts,
+            _: &S,
+        ) -> impl Future<Output = Result<RequestCpu, Self::Rejection>> + Send {
+            ready(parts.extensions.get::<RequestCpu>().cloned().ok_or((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "RequestCpuLayer is missing",
+            )))
+        }
+    }
+}
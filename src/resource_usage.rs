@@ -0,0 +1,185 @@
+use std::time::Duration;
+
+use libc::rusage as raw_rusage;
+
+use rusage::{self, to_duration};
+use CpuTimeError;
+use SplitTime;
+
+/// Resource Usage as Reported by `getrusage()`
+///
+/// CPU time together with memory and scheduling statistics, all read in one
+/// call, so a benchmark harness can tell e.g. page-fault storms or
+/// context-switch churn apart from actual computation. Counters the
+/// platform doesn't maintain are zero (Linux, for instance, doesn't count
+/// all of them).
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(::rkyv::Archive, ::rkyv::Serialize, ::rkyv::Deserialize)
+)]
+#[cfg_attr(feature = "bincode", derive(::bincode::Encode, ::bincode::Decode))]
+#[cfg_attr(feature = "arbitrary", derive(::arbitrary::Arbitrary))]
+pub struct ResourceUsage {
+    /// User CPU time
+    #[cfg_attr(feature = "serde", serde(with = "::nanos::serde"))]
+    pub user: Duration,
+    /// System CPU time
+    #[cfg_attr(feature = "serde", serde(with = "::nanos::serde"))]
+    pub system: Duration,
+    /// Peak resident set size in bytes
+    pub max_rss: u64,
+    /// Page faults served without I/O
+    pub minor_faults: u64,
+    /// Page faults that needed I/O
+    pub major_faults: u64,
+    /// Context switches because the process waited, e.g. for I/O or a lock
+    pub voluntary_context_switches: u64,
+    /// Context switches because the time slice ran out or a higher
+    /// priority task became runnable
+    pub involuntary_context_switches: u64,
+    /// Block input operations
+    pub block_input: u64,
+    /// Block output operations
+    pub block_output: u64,
+}
+
+fn counter(value: libc::c_long) -> u64 {
+    if value < 0 {
+        0
+    } else {
+        value as u64
+    }
+}
+
+// Apple platforms report `ru_maxrss` in bytes, everyone else in kilobytes
+#[cfg(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "tvos",
+    target_os = "watchos"
+))]
+const MAX_RSS_UNIT: u64 = 1;
+#[cfg(not(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "tvos",
+    target_os = "watchos"
+)))]
+const MAX_RSS_UNIT: u64 = 1024;
+
+impl From<raw_rusage> for ResourceUsage {
+    fn from(usage: raw_rusage) -> ResourceUsage {
+        ResourceUsage {
+            user: to_duration(usage.ru_utime),
+            system: to_duration(usage.ru_stime),
+            max_rss: counter(usage.ru_maxrss).saturating_mul(MAX_RSS_UNIT),
+            minor_faults: counter(usage.ru_minflt),
+            major_faults: counter(usage.ru_majflt),
+            voluntary_context_switches: counter(usage.ru_nvcsw),
+            involuntary_context_switches: counter(usage.ru_nivcsw),
+            block_input: counter(usage.ru_inblock),
+            block_output: counter(usage.ru_oublock),
+        }
+    }
+}
+
+impl ResourceUsage {
+    /// Get the resource usage of the whole process
+    pub fn process() -> Result<ResourceUsage, CpuTimeError> {
+        rusage::process_raw()
+            .map(ResourceUsage::from)
+            .ok_or(CpuTimeError::Unsupported)
+    }
+    /// Get the resource usage of the terminated and waited-for children
+    ///
+    /// `max_rss` is the peak of the largest child, not their sum.
+    pub fn children() -> Result<ResourceUsage, CpuTimeError> {
+        rusage::children_raw()
+            .map(ResourceUsage::from)
+            .ok_or(CpuTimeError::Unsupported)
+    }
+    /// Get the resource usage of the current thread
+    ///
+    /// Only available where `RUSAGE_THREAD` exists: Linux, Android,
+    /// FreeBSD, OpenBSD and AIX. `max_rss` is still the process' peak.
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "openbsd",
+        target_os = "aix"
+    ))]
+    pub fn thread() -> Result<ResourceUsage, CpuTimeError> {
+        rusage::thread_raw()
+            .map(ResourceUsage::from)
+            .ok_or(CpuTimeError::Unsupported)
+    }
+    /// Returns the user and system CPU time
+    pub fn cpu(&self) -> SplitTime {
+        SplitTime {
+            user: self.user,
+            system: self.system,
+        }
+    }
+    /// Returns the usage from the previous reading to this one
+    ///
+    /// Counters and times are subtracted (saturating at zero), `max_rss`
+    /// keeps this reading's value, as a peak can't be subtracted.
+    pub fn saturating_sub(&self, earlier: ResourceUsage) -> ResourceUsage {
+        let cpu = self.cpu().saturating_duration_since(earlier.cpu());
+        ResourceUsage {
+            user: cpu.user,
+            system: cpu.system,
+            max_rss: self.max_rss,
+            minor_faults: self.minor_faults.saturating_sub(earlier.minor_faults),
+            major_faults: self.major_faults.saturating_sub(earlier.major_faults),
+            voluntary_context_switches: self
+                .voluntary_context_switches
+                .saturating_sub(earlier.voluntary_context_switches),
+            involuntary_context_switches: self
+                .involuntary_context_switches
+                .saturating_sub(earlier.involuntary_context_switches),
+            block_input: self.block_input.saturating_sub(earlier.block_input),
+            block_output: self.block_output.saturating_sub(earlier.block_output),
+        }
+    }
+}
+
+#[cfg(feature = "proptest")]
+impl ::proptest::arbitrary::Arbitrary for ResourceUsage {
+    type Parameters = ();
+    type Strategy = ::proptest::strategy::BoxedStrategy<ResourceUsage>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        use proptest::arbitrary::any;
+        use proptest::strategy::Strategy;
+
+        any::<(Duration, Duration, u64, u64, u64, u64, u64, u64, u64)>()
+            .prop_map(
+                |(
+                    user,
+                    system,
+                    max_rss,
+                    minor_faults,
+                    major_faults,
+                    voluntary_context_switches,
+                    involuntary_context_switches,
+                    block_input,
+                    block_output,
+                )| ResourceUsage {
+                    user,
+                    system,
+                    max_rss,
+                    minor_faults,
+                    major_faults,
+                    voluntary_context_switches,
+                    involuntary_context_switches,
+                    block_input,
+                    block_output,
+                },
+            )
+            .boxed()
+    }
+}
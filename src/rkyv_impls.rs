@@ -0,0 +1,52 @@
+use rkyv::primitive::ArchivedU64;
+use rkyv::rancor::Fallible;
+use rkyv::{Archive, Deserialize, Place, Serialize};
+
+use nanos::to_nanos;
+use {ProcessTime, ThreadTime};
+
+/// Archived as the number of nanoseconds of CPU time
+impl Archive for ProcessTime {
+    type Archived = ArchivedU64;
+    type Resolver = ();
+
+    fn resolve(&self, resolver: (), out: Place<ArchivedU64>) {
+        to_nanos(self.duration()).resolve(resolver, out)
+    }
+}
+
+impl<S: Fallible + ?Sized> Serialize<S> for ProcessTime {
+    fn serialize(&self, _serializer: &mut S) -> Result<(), S::Error> {
+        Ok(())
+    }
+}
+
+/// The pid of a `ProcessTime::now_for()` timestamp isn't archived, so the
+/// deserialized timestamp belongs to the current process
+impl<D: Fallible + ?Sized> Deserialize<ProcessTime, D> for ArchivedU64 {
+    fn deserialize(&self, _deserializer: &mut D) -> Result<ProcessTime, D::Error> {
+        Ok(ProcessTime::from_nanos(self.to_native()))
+    }
+}
+
+/// Archived as the number of nanoseconds of CPU time
+impl Archive for ThreadTime {
+    type Archived = ArchivedU64;
+    type Resolver = ();
+
+    fn resolve(&self, resolver: (), out: Place<ArchivedU64>) {
+        to_nanos(self.duration()).resolve(resolver, out)
+    }
+}
+
+impl<S: Fallible + ?Sized> Serialize<S> for ThreadTime {
+    fn serialize(&self, _serializer: &mut S) -> Result<(), S::Error> {
+        Ok(())
+    }
+}
+
+impl<D: Fallible + ?Sized> Deserialize<ThreadTime, D> for ArchivedU64 {
+    fn deserialize(&self, _deserializer: &mut D) -> Result<ThreadTime, D::Error> {
+        Ok(ThreadTime::from_nanos(self.to_native()))
+    }
+}
@@ -0,0 +1,55 @@
+use std::time::Duration;
+
+/// How durations are rounded to a coarser tick
+///
+/// On Windows CPU time is counted in 100 ns ticks. Readings are converted
+/// to `Duration` exactly, but converting arbitrary durations (deltas,
+/// averages, `from_duration()` timestamps) back to ticks has to drop the
+/// nanoseconds in between. Truncating every sample biases a sum of many
+/// samples downwards, `Nearest` doesn't:
+///
+/// ```rust
+/// use std::time::Duration;
+/// use cpu_time::Rounding;
+///
+/// let sample = Duration::from_nanos(1_250);
+/// assert_eq!(Rounding::Truncate.ticks(sample, 10_000_000), 12);
+/// assert_eq!(Rounding::Nearest.ticks(sample, 10_000_000), 13);
+/// assert_eq!(Rounding::Up.ticks(sample, 10_000_000), 13);
+/// ```
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Rounding {
+    /// Drop the partial tick, like `as_raw_ticks()` does
+    #[default]
+    Truncate,
+    /// Round to the closest tick, a half tick rounds up
+    Nearest,
+    /// Count any partial tick as a whole one
+    Up,
+}
+
+impl Rounding {
+    /// Converts `duration` to ticks of `1 / ticks_per_sec` seconds
+    ///
+    /// Saturates at `u64::MAX`.
+    pub fn ticks(self, duration: Duration, ticks_per_sec: u64) -> u64 {
+        let scaled = match duration.as_nanos().checked_mul(ticks_per_sec as u128) {
+            Some(scaled) => scaled,
+            None => return u64::MAX,
+        };
+        let (ticks, remainder) = (scaled / 1_000_000_000, scaled % 1_000_000_000);
+        let ticks = match self {
+            Rounding::Truncate => ticks,
+            Rounding::Nearest if remainder >= 500_000_000 => ticks + 1,
+            Rounding::Nearest => ticks,
+            Rounding::Up if remainder > 0 => ticks + 1,
+            Rounding::Up => ticks,
+        };
+        if ticks > u64::MAX as u128 {
+            u64::MAX
+        } else {
+            ticks as u64
+        }
+    }
+}
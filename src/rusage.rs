@@ -0,0 +1,97 @@
+use std::mem;
+use std::time::Duration;
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "aix"
+))]
+use libc::RUSAGE_THREAD;
+
+// Bionic headers only gained RUSAGE_THREAD recently, but the kernel has
+// supported it since linux 2.6.26
+#[cfg(target_os = "android")]
+const RUSAGE_THREAD: libc::c_int = 1;
+use libc::{getrusage, rusage, timeval, RUSAGE_CHILDREN, RUSAGE_SELF};
+
+pub fn to_duration(time: timeval) -> Duration {
+    Duration::new(time.tv_sec as u64, time.tv_usec as u32 * 1000)
+}
+
+fn raw(who: libc::c_int) -> Option<rusage> {
+    let mut usage: rusage = unsafe { mem::zeroed() };
+    if unsafe { getrusage(who, &mut usage) } == -1 {
+        return None;
+    }
+    Some(usage)
+}
+
+// User and system time
+fn usage(who: libc::c_int) -> Option<(Duration, Duration)> {
+    raw(who).map(|usage| (to_duration(usage.ru_utime), to_duration(usage.ru_stime)))
+}
+
+fn total((user, system): (Duration, Duration)) -> Duration {
+    user.saturating_add(system)
+}
+
+/// CPU time of the whole process as reported by `getrusage(RUSAGE_SELF)`
+pub fn process() -> Option<Duration> {
+    usage(RUSAGE_SELF).map(total)
+}
+
+/// User and system CPU time of the whole process
+pub fn process_split() -> Option<(Duration, Duration)> {
+    usage(RUSAGE_SELF)
+}
+
+/// User and system CPU time of the terminated and waited-for children
+pub fn children_split() -> Option<(Duration, Duration)> {
+    usage(RUSAGE_CHILDREN)
+}
+
+/// CPU time of the current thread as reported by `getrusage(RUSAGE_THREAD)`
+#[cfg(any(
+    target_os = "openbsd",
+    target_os = "aix",
+    target_os = "android",
+    all(target_os = "linux", feature = "rusage-thread")
+))]
+pub fn thread() -> Option<Duration> {
+    usage(RUSAGE_THREAD).map(total)
+}
+
+/// User and system CPU time of the current thread
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "aix"
+))]
+pub fn thread_split() -> Option<(Duration, Duration)> {
+    usage(RUSAGE_THREAD)
+}
+
+/// Everything `getrusage(RUSAGE_SELF)` reports
+pub fn process_raw() -> Option<rusage> {
+    raw(RUSAGE_SELF)
+}
+
+/// Everything `getrusage(RUSAGE_CHILDREN)` reports
+pub fn children_raw() -> Option<rusage> {
+    raw(RUSAGE_CHILDREN)
+}
+
+/// Everything `getrusage(RUSAGE_THREAD)` reports
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "aix"
+))]
+pub fn thread_raw() -> Option<rusage> {
+    raw(RUSAGE_THREAD)
+}
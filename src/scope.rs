@@ -0,0 +1,182 @@
+use std::fmt;
+use std::mem;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use clock::CpuClock;
+use {ProcessTime, ThreadTime};
+
+// A `fn(&str, Duration)` set with `set_reporter()`, 0 if none
+static REPORTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Sets the global reporter, which receives the label and CPU time of every
+/// `CpuScope` created without a sink of its own
+///
+/// Until one is set, measurements are printed to stderr as `label: time`
+/// (with the `std` feature, without it they are dropped). Set it once at
+/// startup, e.g. to `log_reporter()` or a function feeding a metrics
+/// library:
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// fn record(label: &str, time: Duration) {
+///     // metrics::histogram!(...)
+///     # let _ = (label, time);
+/// }
+///
+/// cpu_time::set_reporter(record);
+/// ```
+pub fn set_reporter(reporter: fn(&str, Duration)) {
+    REPORTER.store(reporter as usize, Ordering::SeqCst);
+}
+
+/// Passes a measurement to the reporter of `set_reporter()`
+///
+/// This is the sink of `CpuScope::new()` and `CpuScope::process()`, for
+/// code that measures by other means but wants to report the same way.
+pub fn report(label: &str, time: Duration) {
+    let reporter = REPORTER.load(Ordering::SeqCst);
+    if reporter != 0 {
+        let reporter: fn(&str, Duration) = unsafe { mem::transmute(reporter) };
+        reporter(label, time);
+    } else {
+        #[cfg(feature = "std")]
+        eprintln!("{}: {:?}", label, time);
+    }
+}
+
+/// A reporter that emits measurements as `log` records at info level, with
+/// `cpu_time` as the target
+///
+/// Pass it to `set_reporter()` or as the sink of a single `CpuScope`.
+#[cfg(feature = "log")]
+pub fn log_reporter(label: &str, time: Duration) {
+    log::info!(target: "cpu_time", "{}: {:?}", label, time);
+}
+
+/// Measures the CPU time between its creation and drop
+///
+/// Instrumenting a block is one line:
+///
+/// ```rust
+/// use cpu_time::CpuScope;
+///
+/// # fn parse() {}
+/// {
+///     let _scope = CpuScope::new("parse");
+///     parse();
+/// } // reports "parse" and the thread CPU time of `parse()`
+/// ```
+///
+/// `new()` measures the current thread and `process()` the whole process,
+/// both deliver the time to the global reporter of `set_reporter()`.
+/// `with_sink()` and `process_with_sink()` take a closure instead:
+///
+/// ```rust
+/// use std::cell::Cell;
+/// use std::time::Duration;
+/// use cpu_time::CpuScope;
+///
+/// let total = Cell::new(Duration::new(0, 0));
+/// for _ in 0..10 {
+///     let _scope = CpuScope::with_sink("step", |_, time| total.set(total.get() + time));
+/// }
+/// println!("steps used {:?}", total.get());
+/// ```
+///
+/// If the clock can't be read at drop, nothing is reported, so that a
+/// scope never panics while unwinding.
+pub struct CpuScope<C: CpuClock = ThreadTime, S: FnOnce(&str, Duration) = fn(&str, Duration)> {
+    label: &'static str,
+    start: C,
+    // Taken on drop
+    sink: Option<S>,
+}
+
+impl CpuScope<ThreadTime> {
+    /// Starts measuring the current thread, for the global reporter
+    ///
+    /// # Panics
+    ///
+    /// If the thread CPU time can't be read, like `ThreadTime::now()`.
+    pub fn new(label: &'static str) -> CpuScope<ThreadTime> {
+        CpuScope::with_sink(label, report)
+    }
+}
+
+impl CpuScope<ProcessTime> {
+    /// Starts measuring the process, for the global reporter
+    ///
+    /// # Panics
+    ///
+    /// If the process CPU time can't be read, like `ProcessTime::now()`.
+    pub fn process(label: &'static str) -> CpuScope<ProcessTime> {
+        CpuScope::process_with_sink(label, report)
+    }
+}
+
+impl<S: FnOnce(&str, Duration)> CpuScope<ThreadTime, S> {
+    /// Starts measuring the current thread, for `sink`
+    ///
+    /// # Panics
+    ///
+    /// If the thread CPU time can't be read, like `ThreadTime::now()`.
+    pub fn with_sink(label: &'static str, sink: S) -> CpuScope<ThreadTime, S> {
+        CpuScope::start(label, sink)
+    }
+}
+
+impl<S: FnOnce(&str, Duration)> CpuScope<ProcessTime, S> {
+    /// Starts measuring the process, for `sink`
+    ///
+    /// # Panics
+    ///
+    /// If the process CPU time can't be read, like `ProcessTime::now()`.
+    pub fn process_with_sink(label: &'static str, sink: S) -> CpuScope<ProcessTime, S> {
+        CpuScope::start(label, sink)
+    }
+}
+
+impl<C: CpuClock, S: FnOnce(&str, Duration)> CpuScope<C, S> {
+    fn start(label: &'static str, sink: S) -> CpuScope<C, S> {
+        CpuScope {
+            label,
+            start: C::now(),
+            sink: Some(sink),
+        }
+    }
+    /// Returns the label passed to the sink
+    pub fn label(&self) -> &'static str {
+        self.label
+    }
+    /// Returns the CPU time used since the scope was created
+    ///
+    /// # Panics
+    ///
+    /// If the CPU time can't be read, like `now()` of the clock.
+    pub fn elapsed(&self) -> Duration {
+        C::now().saturating_duration_since(self.start)
+    }
+}
+
+impl<C: CpuClock, S: FnOnce(&str, Duration)> Drop for CpuScope<C, S> {
+    fn drop(&mut self) {
+        let now = match C::try_now() {
+            Ok(now) => now,
+            Err(_) => return,
+        };
+        if let Some(sink) = self.sink.take() {
+            sink(self.label, now.saturating_duration_since(self.start));
+        }
+    }
+}
+
+impl<C: CpuClock + fmt::Debug, S: FnOnce(&str, Duration)> fmt::Debug for CpuScope<C, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CpuScope")
+            .field("label", &self.label)
+            .field("start", &self.start)
+            .finish()
+    }
+}
@@ -0,0 +1,36 @@
+use serde::de::{Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+
+use nanos::to_nanos;
+use {ProcessTime, ThreadTime};
+
+/// Serialized as the number of nanoseconds of CPU time
+impl Serialize for ProcessTime {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(to_nanos(self.duration()))
+    }
+}
+
+/// Deserialized from the number of nanoseconds of CPU time
+///
+/// The pid of a `ProcessTime::now_for()` timestamp isn't serialized, so the
+/// deserialized timestamp belongs to the current process.
+impl<'de> Deserialize<'de> for ProcessTime {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<ProcessTime, D::Error> {
+        u64::deserialize(deserializer).map(ProcessTime::from_nanos)
+    }
+}
+
+/// Serialized as the number of nanoseconds of CPU time
+impl Serialize for ThreadTime {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(to_nanos(self.duration()))
+    }
+}
+
+/// Deserialized from the number of nanoseconds of CPU time
+impl<'de> Deserialize<'de> for ThreadTime {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<ThreadTime, D::Error> {
+        u64::deserialize(deserializer).map(ThreadTime::from_nanos)
+    }
+}
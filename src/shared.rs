@@ -0,0 +1,257 @@
+use std::cell::UnsafeCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::hint;
+use std::io;
+use std::mem;
+use std::ptr;
+use std::slice;
+use std::str;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::Duration;
+
+use libc::{MAP_ANON, MAP_FAILED, MAP_SHARED, PROT_READ, PROT_WRITE};
+
+use nanos::to_nanos;
+use report::accumulator_report;
+use {Accumulated, Report};
+
+// Longer labels are cut, on a character boundary
+const LABEL_LEN: usize = 56;
+
+// How often to look again at a slot another process is claiming before
+// giving up on it, in case that process died while writing the label
+const CLAIM_SPINS: usize = 1 << 16;
+
+const FREE: u32 = 0;
+const CLAIMED: u32 = 1;
+const READY: u32 = 2;
+
+// Lives in the shared mapping, which starts out zeroed: `FREE`
+#[repr(C)]
+struct Slot {
+    state: AtomicU32,
+    // The label, written by the process that claimed the slot before it
+    // sets `READY`, and never changed after
+    len: UnsafeCell<u32>,
+    label: UnsafeCell<[u8; LABEL_LEN]>,
+    calls: AtomicU64,
+    nanos: AtomicU64,
+}
+
+impl Slot {
+    // Only once `READY` was seen
+    fn label(&self) -> &[u8] {
+        unsafe {
+            let len = *self.len.get() as usize;
+            let label: &[u8; LABEL_LEN] = &*self.label.get();
+            &label[..len]
+        }
+    }
+    fn add(&self, time: Duration) {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        self.nanos.fetch_add(to_nanos(time), Ordering::Relaxed);
+    }
+}
+
+// FNV-1a, to spread the labels over the arena
+fn hash(label: &[u8]) -> usize {
+    label
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325, |hash: u64, &byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        }) as usize
+}
+
+fn truncate(label: &str) -> &[u8] {
+    let mut len = label.len().min(LABEL_LEN);
+    while !label.is_char_boundary(len) {
+        len -= 1;
+    }
+    &label.as_bytes()[..len]
+}
+
+/// CPU time by label in memory shared with forked child processes
+///
+/// For pre-fork servers: the parent creates the arena before forking its
+/// workers, which inherit it and add their CPU time to it, while the
+/// parent reads the totals of all workers at any time:
+///
+/// ```rust,no_run
+/// extern crate libc;
+/// extern crate cpu_time;
+///
+/// use cpu_time::{CpuScope, SharedCounters};
+///
+/// # fn serve() {}
+/// # fn main() {
+/// let counters = SharedCounters::new(256).unwrap();
+/// for _ in 0..4 {
+///     if unsafe { libc::fork() } == 0 {
+///         loop {
+///             let _scope = CpuScope::with_sink("serve", |label, time| {
+///                 counters.add(label, time);
+///             });
+///             serve();
+///         }
+///     }
+/// }
+/// // Later, in the parent
+/// println!("{}", counters.report());
+/// # }
+/// ```
+///
+/// Every label takes a slot of the fixed-size arena, holding up to 56
+/// bytes of it (longer labels are cut). Adding is two atomic additions
+/// without locks or allocation, so a worker that crashes or is killed
+/// can't leave the arena inconsistent: what it added stays counted. A slot
+/// it was claiming for a new label at that moment is skipped.
+pub struct SharedCounters {
+    slots: *mut Slot,
+    len: usize,
+}
+
+// The slots are only accessed atomically after they were published
+unsafe impl Send for SharedCounters {}
+unsafe impl Sync for SharedCounters {}
+
+impl SharedCounters {
+    /// Maps an arena of `labels` slots, shared with the processes forked
+    /// after this
+    ///
+    /// Each slot takes 80 bytes.
+    pub fn new(labels: usize) -> io::Result<SharedCounters> {
+        let len = labels.max(1);
+        let size = len
+            .checked_mul(mem::size_of::<Slot>())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "too many labels"))?;
+        let slots = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                size,
+                PROT_READ | PROT_WRITE,
+                MAP_SHARED | MAP_ANON,
+                -1,
+                0,
+            )
+        };
+        if slots == MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(SharedCounters {
+            slots: slots as *mut Slot,
+            len,
+        })
+    }
+    fn slots(&self) -> &[Slot] {
+        unsafe { slice::from_raw_parts(self.slots, self.len) }
+    }
+    /// Returns how many labels fit into the arena
+    pub fn capacity(&self) -> usize {
+        self.len
+    }
+    /// Adds `time` to `label`, counting one call
+    ///
+    /// Async-signal-safe. Returns `false` if `label` is new and the arena
+    /// is full.
+    pub fn add(&self, label: &str, time: Duration) -> bool {
+        let label = truncate(label);
+        let slots = self.slots();
+        let start = hash(label) % slots.len();
+        for slot in slots[start..].iter().chain(&slots[..start]) {
+            let mut state = slot.state.load(Ordering::Acquire);
+            if state == FREE {
+                match slot.state.compare_exchange(
+                    FREE,
+                    CLAIMED,
+                    Ordering::Acquire,
+                    Ordering::Acquire,
+                ) {
+                    Ok(_) => unsafe {
+                        let bytes: &mut [u8; LABEL_LEN] = &mut *slot.label.get();
+                        bytes[..label.len()].copy_from_slice(label);
+                        *slot.len.get() = label.len() as u32;
+                        slot.add(time);
+                        slot.state.store(READY, Ordering::Release);
+                        return true;
+                    },
+                    Err(current) => state = current,
+                }
+            }
+            let mut spins = 0;
+            while state == CLAIMED && spins < CLAIM_SPINS {
+                hint::spin_loop();
+                state = slot.state.load(Ordering::Acquire);
+                spins += 1;
+            }
+            if state == READY && slot.label() == label {
+                slot.add(time);
+                return true;
+            }
+        }
+        false
+    }
+    /// Returns the CPU time added to `label` by all processes, zero if
+    /// nothing was
+    pub fn get(&self, label: &str) -> Accumulated {
+        let label = truncate(label);
+        self.ready()
+            .filter(|slot| slot.label() == label)
+            .fold(Accumulated::default(), add)
+    }
+    /// Returns the CPU time of all labels, added by all processes, sorted
+    /// by label
+    pub fn accumulators(&self) -> Vec<(String, Accumulated)> {
+        let mut accumulators = BTreeMap::new();
+        for slot in self.ready() {
+            // The label was cut on a character boundary
+            let label = str::from_utf8(slot.label()).unwrap_or("");
+            let sum = accumulators
+                .entry(label.to_string())
+                .or_insert_with(Accumulated::default);
+            *sum = add(*sum, slot);
+        }
+        accumulators.into_iter().collect()
+    }
+    /// Returns the labels as a `Report`, the largest total first, like
+    /// `Report::accumulators()`
+    pub fn report(&self) -> Report {
+        accumulator_report(self.accumulators())
+    }
+    fn ready(&self) -> impl Iterator<Item = &Slot> {
+        self.slots()
+            .iter()
+            .filter(|slot| slot.state.load(Ordering::Acquire) == READY)
+    }
+}
+
+// A label can take more than one slot if a process gave up waiting for
+// another one to claim it
+fn add(sum: Accumulated, slot: &Slot) -> Accumulated {
+    Accumulated {
+        calls: sum.calls.saturating_add(slot.calls.load(Ordering::Relaxed)),
+        total: sum
+            .total
+            .saturating_add(Duration::from_nanos(slot.nanos.load(Ordering::Relaxed))),
+    }
+}
+
+impl Drop for SharedCounters {
+    fn drop(&mut self) {
+        // Only unmaps it in this process
+        unsafe {
+            libc::munmap(
+                self.slots as *mut libc::c_void,
+                self.len * mem::size_of::<Slot>(),
+            );
+        }
+    }
+}
+
+impl fmt::Debug for SharedCounters {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SharedCounters")
+            .field("capacity", &self.len)
+            .finish()
+    }
+}
@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+/// CPU time split into time spent in user space and in the kernel
+///
+/// Returned by `ProcessTime::now_split()` and `ThreadTime::now_split()`.
+/// Unlike the timestamps this is a plain pair of durations, subtract two
+/// readings with `saturating_duration_since()` to get the split of an
+/// interval.
+///
+/// With the `serde` feature it's serialized as a struct of `user` and
+/// `system`, in nanoseconds.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(::rkyv::Archive, ::rkyv::Serialize, ::rkyv::Deserialize)
+)]
+#[cfg_attr(feature = "bincode", derive(::bincode::Encode, ::bincode::Decode))]
+#[cfg_attr(feature = "arbitrary", derive(::arbitrary::Arbitrary))]
+pub struct SplitTime {
+    /// CPU time spent running user code
+    #[cfg_attr(feature = "serde", serde(with = "::nanos::serde"))]
+    pub user: Duration,
+    /// CPU time the kernel spent on behalf of the process or thread, e.g.
+    /// in syscalls
+    #[cfg_attr(feature = "serde", serde(with = "::nanos::serde"))]
+    pub system: Duration,
+}
+
+impl SplitTime {
+    /// Returns the user and system time added up
+    pub fn total(&self) -> Duration {
+        self.user.saturating_add(self.system)
+    }
+    /// Adds up two splits part by part, e.g. of several processes,
+    /// saturating at `Duration::MAX`
+    pub fn saturating_add(&self, other: SplitTime) -> SplitTime {
+        SplitTime {
+            user: self.user.saturating_add(other.user),
+            system: self.system.saturating_add(other.system),
+        }
+    }
+    /// Returns the CPU time used from the previous reading, each part is
+    /// zero if that reading's part is larger
+    pub fn saturating_duration_since(&self, earlier: SplitTime) -> SplitTime {
+        SplitTime {
+            user: self
+                .user
+                .checked_sub(earlier.user)
+                .unwrap_or_else(|| Duration::new(0, 0)),
+            system: self
+                .system
+                .checked_sub(earlier.system)
+                .unwrap_or_else(|| Duration::new(0, 0)),
+        }
+    }
+}
+
+#[cfg(feature = "proptest")]
+impl ::proptest::arbitrary::Arbitrary for SplitTime {
+    type Parameters = ();
+    type Strategy = ::proptest::strategy::BoxedStrategy<SplitTime>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        use proptest::arbitrary::any;
+        use proptest::strategy::Strategy;
+
+        any::<(Duration, Duration)>()
+            .prop_map(|(user, system)| SplitTime { user, system })
+            .boxed()
+    }
+}
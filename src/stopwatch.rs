@@ -0,0 +1,157 @@
+use std::time::Duration;
+
+use clock::CpuClock;
+use {ProcessTime, ThreadTime};
+
+/// Accumulates CPU time over several intervals
+///
+/// Measures only some phases of a long computation without keeping track
+/// of many timestamps:
+///
+/// ```rust,no_run
+/// use cpu_time::CpuStopwatch;
+///
+/// # fn parse() {}
+/// # fn wait_for_input() {}
+/// let mut parsing = CpuStopwatch::thread();
+/// for _ in 0..10 {
+///     parsing.resume();
+///     parse();
+///     parsing.pause();
+///     wait_for_input();
+/// }
+/// println!("parsing used {:?}", parsing.elapsed());
+/// ```
+///
+/// `C` is the clock, `ProcessTime` or `ThreadTime`. Like `ThreadTime` a
+/// thread stopwatch can't be sent to another thread, as it would then mix
+/// up the times of two threads.
+///
+/// With the `std` feature `lap()` records the time of each iteration of an
+/// algorithm:
+///
+/// ```rust,no_run
+/// use cpu_time::CpuStopwatch;
+///
+/// # fn iterate() {}
+/// let mut stopwatch = CpuStopwatch::thread();
+/// stopwatch.start();
+/// for _ in 0..100 {
+///     iterate();
+///     stopwatch.lap_indexed();
+/// }
+/// let slowest = stopwatch.laps().iter().max_by_key(|&&(_, time)| time).unwrap();
+/// println!("iteration {} took longest, {:?}", slowest.0, slowest.1);
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct CpuStopwatch<C: CpuClock> {
+    elapsed: Duration,
+    // Start of the current interval, if running
+    started: Option<C>,
+    // `elapsed()` at the last lap
+    #[cfg(feature = "std")]
+    lapped: Duration,
+    #[cfg(feature = "std")]
+    laps: Vec<(String, Duration)>,
+}
+
+impl CpuStopwatch<ProcessTime> {
+    /// Returns a stopped stopwatch on the process clock
+    pub fn process() -> CpuStopwatch<ProcessTime> {
+        CpuStopwatch::new()
+    }
+}
+
+impl CpuStopwatch<ThreadTime> {
+    /// Returns a stopped stopwatch on the current thread's clock
+    pub fn thread() -> CpuStopwatch<ThreadTime> {
+        CpuStopwatch::new()
+    }
+}
+
+impl<C: CpuClock> CpuStopwatch<C> {
+    /// Returns a stopped stopwatch with no time on it
+    pub fn new() -> CpuStopwatch<C> {
+        CpuStopwatch {
+            elapsed: Duration::new(0, 0),
+            started: None,
+            #[cfg(feature = "std")]
+            lapped: Duration::new(0, 0),
+            #[cfg(feature = "std")]
+            laps: Vec::new(),
+        }
+    }
+    /// Clears the time and the laps and starts measuring
+    pub fn start(&mut self) {
+        self.reset();
+        self.started = Some(C::now());
+    }
+    /// Stops measuring, keeping the time so far
+    ///
+    /// Does nothing if the stopwatch isn't running.
+    pub fn pause(&mut self) {
+        if let Some(started) = self.started.take() {
+            self.elapsed += C::now().saturating_duration_since(started);
+        }
+    }
+    /// Continues measuring, adding to the time so far
+    ///
+    /// Does nothing if the stopwatch is already running.
+    pub fn resume(&mut self) {
+        if self.started.is_none() {
+            self.started = Some(C::now());
+        }
+    }
+    /// Stops measuring and clears the time and the laps
+    pub fn reset(&mut self) {
+        self.elapsed = Duration::new(0, 0);
+        self.started = None;
+        #[cfg(feature = "std")]
+        {
+            self.lapped = Duration::new(0, 0);
+            self.laps.clear();
+        }
+    }
+    /// Returns `true` between `start()` or `resume()` and `pause()`
+    pub fn is_running(&self) -> bool {
+        self.started.is_some()
+    }
+    /// Returns the CPU time of all intervals, including the current one if
+    /// running
+    pub fn elapsed(&self) -> Duration {
+        match self.started {
+            Some(started) => self.elapsed + C::now().saturating_duration_since(started),
+            None => self.elapsed,
+        }
+    }
+    /// Records a lap named `label` and returns its time
+    ///
+    /// A lap's time is what the stopwatch measured since the previous lap,
+    /// or since it was started, without the paused intervals. The
+    /// stopwatch keeps running (or stays paused).
+    #[cfg(feature = "std")]
+    pub fn lap<L: Into<String>>(&mut self, label: L) -> Duration {
+        let elapsed = self.elapsed();
+        let lap = elapsed - self.lapped;
+        self.lapped = elapsed;
+        self.laps.push((label.into(), lap));
+        lap
+    }
+    /// Records a lap labeled with its index, `"0"` for the first one
+    #[cfg(feature = "std")]
+    pub fn lap_indexed(&mut self) -> Duration {
+        let label = self.laps.len().to_string();
+        self.lap(label)
+    }
+    /// Returns the recorded laps, with their labels and times
+    #[cfg(feature = "std")]
+    pub fn laps(&self) -> &[(String, Duration)] {
+        &self.laps
+    }
+}
+
+impl<C: CpuClock> Default for CpuStopwatch<C> {
+    fn default() -> CpuStopwatch<C> {
+        CpuStopwatch::new()
+    }
+}
@@ -0,0 +1,137 @@
+use std::ops::Sub;
+use std::time::Duration;
+
+use winapi::um::errhandlingapi::GetLastError;
+use winapi::um::processthreadsapi::GetSystemTimes;
+
+use windows::{error, to_duration, zero};
+use CpuTimeError;
+
+/// Machine-Wide CPU Time, as Reported by `GetSystemTimes`
+///
+/// The times are summed over all processors. Take two snapshots and use
+/// `utilization()` on their difference to get the CPU usage of the whole
+/// machine:
+///
+/// ```rust,no_run
+/// use std::thread::sleep;
+/// use std::time::Duration;
+/// use cpu_time::SystemTimes;
+///
+/// let start = SystemTimes::now();
+/// sleep(Duration::from_secs(1));
+/// let usage = (SystemTimes::now() - start).utilization();
+/// println!("CPU usage: {:.1}%", usage * 100.0);
+/// ```
+///
+/// Only available on Windows.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(::rkyv::Archive, ::rkyv::Serialize, ::rkyv::Deserialize)
+)]
+#[cfg_attr(feature = "bincode", derive(::bincode::Encode, ::bincode::Decode))]
+#[cfg_attr(feature = "arbitrary", derive(::arbitrary::Arbitrary))]
+pub struct SystemTimes {
+    /// Time the processors were idle
+    #[cfg_attr(feature = "serde", serde(with = "::nanos::serde"))]
+    pub idle: Duration,
+    /// Time spent in the kernel, unlike in `GetSystemTimes` without the
+    /// idle time
+    #[cfg_attr(feature = "serde", serde(with = "::nanos::serde"))]
+    pub kernel: Duration,
+    /// Time spent in user mode
+    #[cfg_attr(feature = "serde", serde(with = "::nanos::serde"))]
+    pub user: Duration,
+}
+
+impl SystemTimes {
+    /// Get the current CPU times of the machine
+    ///
+    /// # Panics
+    ///
+    /// If `GetSystemTimes` fails. Use `try_now()` to handle this case.
+    pub fn now() -> SystemTimes {
+        match SystemTimes::try_now() {
+            Ok(times) => times,
+            Err(_) => panic!("Can't get system times"),
+        }
+    }
+    /// Get the current CPU times of the machine
+    pub fn try_now() -> Result<SystemTimes, CpuTimeError> {
+        let mut idle_time = zero();
+        let mut kernel_time = zero();
+        let mut user_time = zero();
+        let ok = unsafe { GetSystemTimes(&mut idle_time, &mut kernel_time, &mut user_time) };
+        if ok == 0 {
+            return Err(error(unsafe { GetLastError() }));
+        }
+        let idle = to_duration(idle_time, zero());
+        Ok(SystemTimes {
+            idle,
+            kernel: to_duration(kernel_time, zero())
+                .checked_sub(idle)
+                .unwrap_or_else(|| Duration::new(0, 0)),
+            user: to_duration(zero(), user_time),
+        })
+    }
+    /// Returns the time the processors were busy, kernel and user time
+    pub fn busy(&self) -> Duration {
+        self.kernel.saturating_add(self.user)
+    }
+    /// Returns the idle, kernel and user time added up
+    pub fn total(&self) -> Duration {
+        self.idle.saturating_add(self.busy())
+    }
+    /// Returns the share of `total()` the processors were busy, from `0.0`
+    /// to `1.0`, or `0.0` if no time passed
+    ///
+    /// Meant for the difference of two snapshots, see `saturating_sub()`
+    /// and `-`.
+    pub fn utilization(&self) -> f64 {
+        let total = self.total().as_secs_f64();
+        if total == 0.0 {
+            return 0.0;
+        }
+        self.busy().as_secs_f64() / total
+    }
+    /// Returns the times from the previous snapshot to this one, each value
+    /// is zero if that snapshot's value is larger
+    pub fn saturating_sub(&self, earlier: SystemTimes) -> SystemTimes {
+        fn sub(later: Duration, earlier: Duration) -> Duration {
+            later
+                .checked_sub(earlier)
+                .unwrap_or_else(|| Duration::new(0, 0))
+        }
+        SystemTimes {
+            idle: sub(self.idle, earlier.idle),
+            kernel: sub(self.kernel, earlier.kernel),
+            user: sub(self.user, earlier.user),
+        }
+    }
+}
+
+impl Sub for SystemTimes {
+    type Output = SystemTimes;
+
+    /// Same as `saturating_sub()`
+    fn sub(self, earlier: SystemTimes) -> SystemTimes {
+        self.saturating_sub(earlier)
+    }
+}
+
+#[cfg(feature = "proptest")]
+impl ::proptest::arbitrary::Arbitrary for SystemTimes {
+    type Parameters = ();
+    type Strategy = ::proptest::strategy::BoxedStrategy<SystemTimes>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        use proptest::arbitrary::any;
+        use proptest::strategy::Strategy;
+
+        any::<(Duration, Duration, Duration)>()
+            .prop_map(|(idle, kernel, user)| SystemTimes { idle, kernel, user })
+            .boxed()
+    }
+}
@@ -0,0 +1,344 @@
+//! CPU time of async tasks, on any executor
+//!
+//! On a busy executor the CPU time of its threads tells that it's busy,
+//! not which tasks keep it busy. A future wrapped with `instrument()`
+//! before it's spawned adds up the thread CPU time of its polls under a
+//! `TaskId`, readable from anywhere while it runs:
+//!
+//! ```rust
+//! use cpu_time::task;
+//!
+//! # fn spawn<F>(future: F) { drop(future) }
+//! # let work = std::future::ready(());
+//! let work = task::instrument(work);
+//! let id = work.id();
+//! spawn(work); // async_std::task::spawn, smol::spawn, ...
+//!
+//! for task in task::tasks().iter().take(10) {
+//!     println!("task {} used {:?} in {} polls", task.id, task.total, task.polls);
+//! }
+//! # assert_eq!(task::get(id), None);
+//! ```
+//!
+//! A task is listed from its first poll until it completes or is dropped.
+//! That's all it takes from the executor, so this works the same for
+//! async-std, smol and custom executors.
+//!
+//! With the `tokio` feature, `spawn()` spawns a Tokio task that is listed
+//! under its `tokio::task::Id` (converted with `TaskId::from()`). With
+//! `--cfg tokio_unstable` as well, `hooks()` installs the runtime's task
+//! poll hooks instead, which measure every task of the runtime without
+//! wrapping it.
+use std::cell::Cell;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+#[cfg(feature = "tokio")]
+use tokio::task::JoinHandle;
+
+use nanos::to_nanos;
+use ThreadTime;
+
+/// Identifies a measured task
+///
+/// `instrument()` assigns a new one to each future. It prints as a number,
+/// or as `tokio-` and a number for the id of a Tokio task.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+pub struct TaskId(Repr);
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum Repr {
+    Instrumented(u64),
+    #[cfg(feature = "tokio")]
+    Tokio(tokio::task::Id),
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+impl TaskId {
+    fn next() -> TaskId {
+        TaskId(Repr::Instrumented(NEXT_ID.fetch_add(1, Ordering::Relaxed)))
+    }
+}
+
+impl fmt::Display for TaskId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Repr::Instrumented(id) => write!(f, "{}", id),
+            #[cfg(feature = "tokio")]
+            Repr::Tokio(id) => write!(f, "tokio-{}", id),
+        }
+    }
+}
+
+/// The id a Tokio task is measured under by `spawn()` and `hooks()`
+#[cfg(feature = "tokio")]
+impl From<tokio::task::Id> for TaskId {
+    fn from(id: tokio::task::Id) -> TaskId {
+        TaskId(Repr::Tokio(id))
+    }
+}
+
+// Shared by the task's future and the map
+#[derive(Default)]
+struct Counters {
+    polls: AtomicU64,
+    nanos: AtomicU64,
+}
+
+impl Counters {
+    fn add(&self, time: Duration) {
+        self.polls.fetch_add(1, Ordering::Relaxed);
+        self.nanos.fetch_add(to_nanos(time), Ordering::Relaxed);
+    }
+}
+
+impl fmt::Debug for Counters {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Counters")
+            .field("polls", &self.polls.load(Ordering::Relaxed))
+            .field("nanos", &self.nanos.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+static TASKS: Mutex<BTreeMap<TaskId, Arc<Counters>>> = Mutex::new(BTreeMap::new());
+
+fn lock() -> MutexGuard<'static, BTreeMap<TaskId, Arc<Counters>>> {
+    // Every operation leaves the map consistent
+    TASKS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn register(id: TaskId) -> Arc<Counters> {
+    lock().entry(id).or_default().clone()
+}
+
+thread_local! {
+    // The instrumented task the thread is polling
+    static CURRENT: Cell<Option<TaskId>> = const { Cell::new(None) };
+}
+
+/// The CPU time a task used so far
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub struct TaskCpu {
+    /// The task's id
+    pub id: TaskId,
+    /// How often it was polled
+    pub polls: u64,
+    /// The thread CPU time of all its polls
+    pub total: Duration,
+}
+
+impl TaskCpu {
+    fn read(id: TaskId, counters: &Counters) -> TaskCpu {
+        TaskCpu {
+            id,
+            polls: counters.polls.load(Ordering::Relaxed),
+            total: Duration::from_nanos(counters.nanos.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// Returns the CPU time of the running task `id`, `None` if it isn't
+/// measured, or isn't running anymore
+pub fn get(id: TaskId) -> Option<TaskCpu> {
+    lock().get(&id).map(|counters| TaskCpu::read(id, counters))
+}
+
+/// Returns the CPU time of all measured tasks that are still running, the
+/// largest total first
+pub fn tasks() -> Vec<TaskCpu> {
+    let mut tasks = lock()
+        .iter()
+        .map(|(&id, counters)| TaskCpu::read(id, counters))
+        .collect::<Vec<_>>();
+    tasks.sort_by_key(|task| Reverse(task.total));
+    tasks
+}
+
+/// Returns what the current task used so far, not counting the poll that
+/// is running, `None` outside of a measured task
+pub fn current() -> Option<TaskCpu> {
+    if let Some(id) = CURRENT.with(Cell::get) {
+        return get(id);
+    }
+    // Measured by `hooks()`
+    #[cfg(feature = "tokio")]
+    {
+        if let Some(id) = tokio::task::try_id() {
+            return get(id.into());
+        }
+    }
+    None
+}
+
+/// A future that adds the CPU time of its polls to its task, returned by
+/// `instrument()`
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct Instrumented<F> {
+    future: F,
+    id: TaskId,
+    // Whether to take the id of the Tokio task on the first poll
+    #[cfg(feature = "tokio")]
+    tokio: bool,
+    // Set on the first poll
+    counters: Option<Arc<Counters>>,
+}
+
+impl<F> Instrumented<F> {
+    /// Returns the id the task is listed under
+    pub fn id(&self) -> TaskId {
+        self.id
+    }
+}
+
+/// Measures `future` as the task it is spawned as
+///
+/// Only wrap the future a task is spawned with: polls of futures wrapped
+/// inside of it would be counted twice.
+pub fn instrument<F: Future>(future: F) -> Instrumented<F> {
+    Instrumented {
+        future,
+        id: TaskId::next(),
+        #[cfg(feature = "tokio")]
+        tokio: false,
+        counters: None,
+    }
+}
+
+/// Spawns `future` as a Tokio task that is measured under its
+/// `tokio::task::Id`
+///
+/// `task::get(handle.id().into())` returns what it used.
+///
+/// # Panics
+///
+/// Outside of a Tokio runtime, like `tokio::spawn()`.
+#[cfg(feature = "tokio")]
+pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let mut future = instrument(future);
+    future.tokio = true;
+    tokio::spawn(future)
+}
+
+// Makes `id` the current task until dropped
+struct Polling(Option<TaskId>);
+
+impl Polling {
+    fn enter(id: TaskId) -> Polling {
+        Polling(CURRENT.with(|current| current.replace(Some(id))))
+    }
+}
+
+impl Drop for Polling {
+    fn drop(&mut self) {
+        CURRENT.with(|current| current.set(self.0));
+    }
+}
+
+impl<F: Future> Future for Instrumented<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<F::Output> {
+        // `future` is never moved out of a pinned `Instrumented`
+        let this = unsafe { self.get_unchecked_mut() };
+        if this.counters.is_none() {
+            #[cfg(feature = "tokio")]
+            {
+                if let (true, Some(id)) = (this.tokio, tokio::task::try_id()) {
+                    this.id = id.into();
+                }
+            }
+            this.counters = Some(register(this.id));
+        }
+        let _polling = Polling::enter(this.id);
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+        let start = ThreadTime::try_now();
+        let poll = future.poll(cx);
+        if let (Some(counters), Ok(start), Ok(now)) =
+            (this.counters.as_ref(), start, ThreadTime::try_now())
+        {
+            counters.add(now.saturating_duration_since(start));
+        }
+        poll
+    }
+}
+
+impl<F> Drop for Instrumented<F> {
+    fn drop(&mut self) {
+        if let Some(ref counters) = self.counters {
+            let mut tasks = lock();
+            match tasks.get(&self.id) {
+                Some(entry) if Arc::ptr_eq(entry, counters) => {
+                    tasks.remove(&self.id);
+                }
+                // Not a later task reusing the id
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(all(feature = "tokio", tokio_unstable))]
+pub use self::hooks::hooks;
+
+#[cfg(all(feature = "tokio", tokio_unstable))]
+mod hooks {
+    use std::cell::RefCell;
+    use std::sync::Arc;
+
+    use tokio::runtime::Builder;
+
+    use super::{lock, register, Counters, TaskId};
+    use ThreadTime;
+
+    thread_local! {
+        // The task the thread is polling, and when it started
+        static POLLING: RefCell<Option<(ThreadTime, Arc<Counters>)>> = const {
+            RefCell::new(None)
+        };
+    }
+
+    /// Measures every task of the runtime built by `builder`, with its
+    /// task poll hooks
+    ///
+    /// Needs `--cfg tokio_unstable`. Replaces the builder's
+    /// `on_before_task_poll`, `on_after_task_poll` and `on_task_terminate`
+    /// callbacks. Tasks of such a runtime mustn't also be `instrument()`ed,
+    /// they would be counted twice.
+    pub fn hooks(builder: &mut Builder) -> &mut Builder {
+        builder
+            .on_before_task_poll(|meta| {
+                // Takes the lock on every poll, unlike `instrument()`
+                let polling = ThreadTime::try_now()
+                    .ok()
+                    .map(|start| (start, register(TaskId::from(meta.id()))));
+                POLLING.with(|current| *current.borrow_mut() = polling);
+            })
+            .on_after_task_poll(|_| {
+                let polling = POLLING.with(|current| current.borrow_mut().take());
+                if let (Some((start, counters)), Ok(now)) = (polling, ThreadTime::try_now()) {
+                    counters.add(now.saturating_duration_since(start));
+                }
+            })
+            // Called before the last `on_after_task_poll`, which then adds to
+            // counters that aren't listed anymore
+            .on_task_terminate(|meta| {
+                lock().remove(&TaskId::from(meta.id()));
+            })
+    }
+}
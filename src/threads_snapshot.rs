@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+use std::ops::Sub;
+use std::time::Duration;
+
+#[cfg(windows)]
+use winapi::um::processthreadsapi::GetCurrentProcessId;
+
+#[cfg(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "tvos",
+    target_os = "watchos"
+))]
+use mach;
+#[cfg(windows)]
+use nt::{to_duration, Snapshot};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use procfs::Stat;
+use CpuTimeError;
+use SplitTime;
+
+/// CPU time of one thread in a `ThreadsSnapshot`
+#[derive(Clone, PartialEq, Eq, Debug, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(::rkyv::Archive, ::rkyv::Serialize, ::rkyv::Deserialize)
+)]
+#[cfg_attr(feature = "bincode", derive(::bincode::Encode, ::bincode::Decode))]
+#[cfg_attr(feature = "arbitrary", derive(::arbitrary::Arbitrary))]
+pub struct ThreadEntry {
+    /// OS thread id: `gettid()` on Linux, `pthread_threadid_np()` on Apple
+    /// platforms and `GetCurrentThreadId()` on Windows
+    pub tid: u64,
+    /// Name of the thread, if the OS reports one (never on Windows)
+    pub name: Option<String>,
+    /// CPU time spent running user code
+    #[cfg_attr(feature = "serde", serde(with = "::nanos::serde"))]
+    pub user: Duration,
+    /// CPU time the kernel spent on behalf of the thread
+    #[cfg_attr(feature = "serde", serde(with = "::nanos::serde"))]
+    pub system: Duration,
+}
+
+impl ThreadEntry {
+    /// Returns the user and system time added up
+    pub fn cpu_time(&self) -> Duration {
+        self.user.saturating_add(self.system)
+    }
+    /// Returns the user and system time
+    pub fn split(&self) -> SplitTime {
+        SplitTime {
+            user: self.user,
+            system: self.system,
+        }
+    }
+}
+
+/// CPU time of every thread of the current process, at one moment
+///
+/// For "which thread is burning CPU" debugging without a profiler:
+///
+/// ```rust,no_run
+/// use cpu_time::ThreadsSnapshot;
+///
+/// let snapshot = ThreadsSnapshot::capture().unwrap();
+/// if let Some(thread) = snapshot.busiest() {
+///     println!("{:?} ({}) used {:?}", thread.name, thread.tid, thread.cpu_time());
+/// }
+/// ```
+///
+/// Linux and Android read `/proc/self/task`, Apple platforms
+/// `task_threads()` and Windows `NtQuerySystemInformation`. The threads
+/// aren't read atomically, ones that start or exit meanwhile may be
+/// missing. On Linux the times are in clock ticks, usually 10 ms.
+#[derive(Clone, PartialEq, Eq, Debug, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(::rkyv::Archive, ::rkyv::Serialize, ::rkyv::Deserialize)
+)]
+#[cfg_attr(feature = "bincode", derive(::bincode::Encode, ::bincode::Decode))]
+#[cfg_attr(feature = "arbitrary", derive(::arbitrary::Arbitrary))]
+pub struct ThreadsSnapshot {
+    threads: Vec<ThreadEntry>,
+}
+
+impl ThreadsSnapshot {
+    /// Reads all threads of the current process
+    pub fn capture() -> Result<ThreadsSnapshot, CpuTimeError> {
+        capture().map(|threads| ThreadsSnapshot { threads })
+    }
+    /// Returns the threads, in the order the OS listed them
+    pub fn threads(&self) -> &[ThreadEntry] {
+        &self.threads
+    }
+    /// Returns the thread with OS id `tid`
+    pub fn get(&self, tid: u64) -> Option<&ThreadEntry> {
+        self.threads.iter().find(|thread| thread.tid == tid)
+    }
+    /// Returns the thread that used the most CPU time so far
+    pub fn busiest(&self) -> Option<&ThreadEntry> {
+        self.threads.iter().max_by_key(|thread| thread.cpu_time())
+    }
+}
+
+/// CPU time each thread used between two `ThreadsSnapshot`s
+///
+/// Subtract the earlier snapshot from the later one:
+///
+/// ```rust,no_run
+/// use std::thread::sleep;
+/// use std::time::Duration;
+/// use cpu_time::ThreadsSnapshot;
+///
+/// let start = ThreadsSnapshot::capture().unwrap();
+/// sleep(Duration::from_secs(1));
+/// let delta = &ThreadsSnapshot::capture().unwrap() - &start;
+/// for thread in delta.threads() {
+///     println!("{:?} used {:?} in the last second", thread.name, thread.cpu_time());
+/// }
+/// ```
+///
+/// It holds the threads of the later snapshot, with the times they used
+/// since the earlier one. Threads started in between count from zero, ones
+/// that exited in between are left out.
+#[derive(Clone, PartialEq, Eq, Debug, Hash, Default)]
+pub struct ThreadsDelta {
+    threads: Vec<ThreadEntry>,
+}
+
+impl ThreadsDelta {
+    /// Returns the threads with the times they used in the interval, in the
+    /// order of the later snapshot
+    pub fn threads(&self) -> &[ThreadEntry] {
+        &self.threads
+    }
+    /// Returns the thread with OS id `tid`
+    pub fn get(&self, tid: u64) -> Option<&ThreadEntry> {
+        self.threads.iter().find(|thread| thread.tid == tid)
+    }
+    /// Returns the thread that used the most CPU time in the interval
+    pub fn busiest(&self) -> Option<&ThreadEntry> {
+        self.threads.iter().max_by_key(|thread| thread.cpu_time())
+    }
+    /// Returns the CPU time all threads used in the interval
+    pub fn total(&self) -> SplitTime {
+        self.threads
+            .iter()
+            .fold(SplitTime::default(), |total, thread| {
+                total.saturating_add(thread.split())
+            })
+    }
+}
+
+impl Sub<&ThreadsSnapshot> for &ThreadsSnapshot {
+    type Output = ThreadsDelta;
+
+    fn sub(self, earlier: &ThreadsSnapshot) -> ThreadsDelta {
+        let earlier = earlier
+            .threads
+            .iter()
+            .map(|thread| (thread.tid, thread.split()))
+            .collect::<HashMap<_, _>>();
+        let threads = self
+            .threads
+            .iter()
+            .map(|thread| {
+                let split = thread.split();
+                let used = match earlier.get(&thread.tid) {
+                    // A smaller time means that the id was reused
+                    Some(&start) if start.user <= split.user && start.system <= split.system => {
+                        split.saturating_duration_since(start)
+                    }
+                    _ => split,
+                };
+                ThreadEntry {
+                    user: used.user,
+                    system: used.system,
+                    ..thread.clone()
+                }
+            })
+            .collect();
+        ThreadsDelta { threads }
+    }
+}
+
+impl Sub for ThreadsSnapshot {
+    type Output = ThreadsDelta;
+
+    fn sub(self, earlier: ThreadsSnapshot) -> ThreadsDelta {
+        &self - &earlier
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn capture() -> Result<Vec<ThreadEntry>, CpuTimeError> {
+    use std::fs;
+
+    let tasks = fs::read_dir("/proc/self/task").map_err(|_| CpuTimeError::Unsupported)?;
+    let mut threads = Vec::new();
+    for task in tasks {
+        let tid = match task
+            .ok()
+            .and_then(|task| task.file_name().to_str()?.parse().ok())
+        {
+            Some(tid) => tid,
+            None => continue,
+        };
+        let stat = match Stat::read_thread(tid) {
+            Ok(stat) => stat,
+            // Exited in between
+            Err(CpuTimeError::ProcessGone) => continue,
+            Err(error) => return Err(error),
+        };
+        let name = fs::read_to_string(format!("/proc/self/task/{}/comm", tid))
+            .ok()
+            .map(|name| name.trim_end_matches('\n').to_string());
+        threads.push(ThreadEntry {
+            tid: tid as u64,
+            name,
+            user: stat.utime,
+            system: stat.stime,
+        });
+    }
+    Ok(threads)
+}
+
+#[cfg(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "tvos",
+    target_os = "watchos"
+))]
+fn capture() -> Result<Vec<ThreadEntry>, CpuTimeError> {
+    mach::threads().ok_or(CpuTimeError::Unsupported)
+}
+
+#[cfg(windows)]
+fn capture() -> Result<Vec<ThreadEntry>, CpuTimeError> {
+    let snapshot = Snapshot::capture()?;
+    let process = snapshot
+        .process(unsafe { GetCurrentProcessId() })
+        .ok_or(CpuTimeError::ProcessGone)?;
+    Ok(process
+        .threads()
+        .iter()
+        .map(|thread| ThreadEntry {
+            tid: thread.unique_thread as usize as u64,
+            name: None,
+            user: to_duration(thread.user_time),
+            system: to_duration(thread.kernel_time),
+        })
+        .collect())
+}
+
+#[cfg(feature = "proptest")]
+impl ::proptest::arbitrary::Arbitrary for ThreadEntry {
+    type Parameters = ();
+    type Strategy = ::proptest::strategy::BoxedStrategy<ThreadEntry>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        use proptest::arbitrary::any;
+        use proptest::strategy::Strategy;
+
+        any::<(u64, Option<String>, Duration, Duration)>()
+            .prop_map(|(tid, name, user, system)| ThreadEntry {
+                tid,
+                name,
+                user,
+                system,
+            })
+            .boxed()
+    }
+}
+
+#[cfg(feature = "proptest")]
+impl ::proptest::arbitrary::Arbitrary for ThreadsSnapshot {
+    type Parameters = ();
+    type Strategy = ::proptest::strategy::BoxedStrategy<ThreadsSnapshot>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        use proptest::arbitrary::any;
+        use proptest::strategy::Strategy;
+
+        any::<Vec<ThreadEntry>>()
+            .prop_map(|threads| ThreadsSnapshot { threads })
+            .boxed()
+    }
+}
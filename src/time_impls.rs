@@ -0,0 +1,22 @@
+use std::convert::TryFrom;
+
+use time::error::ConversionRange;
+use time::Duration;
+
+use CpuDuration;
+
+impl TryFrom<CpuDuration> for Duration {
+    type Error = ConversionRange;
+    /// Fails for durations above `time::Duration::MAX`, `i64::MAX` seconds
+    fn try_from(duration: CpuDuration) -> Result<Duration, ConversionRange> {
+        Duration::try_from(duration.0)
+    }
+}
+
+impl TryFrom<Duration> for CpuDuration {
+    type Error = ConversionRange;
+    /// Fails for negative durations
+    fn try_from(duration: Duration) -> Result<CpuDuration, ConversionRange> {
+        std::time::Duration::try_from(duration).map(CpuDuration)
+    }
+}
@@ -0,0 +1,133 @@
+use std::mem;
+use std::time::Duration;
+
+use libc::{clock_t, sysconf, times, tms, _SC_CLK_TCK};
+
+use CpuTimeError;
+use SplitTime;
+
+/// CPU Time of The Process and Its Children, as Reported by `times()`
+///
+/// All four values come from a single `times()` call, like the `time(1)`
+/// command uses. They are counted in clock ticks (`sysconf(_SC_CLK_TCK)`,
+/// usually 100 per second), so this is much coarser than `ProcessTime`.
+/// The children values only include terminated and waited-for children,
+/// see `ChildrenTime`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(::rkyv::Archive, ::rkyv::Serialize, ::rkyv::Deserialize)
+)]
+#[cfg_attr(feature = "bincode", derive(::bincode::Encode, ::bincode::Decode))]
+#[cfg_attr(feature = "arbitrary", derive(::arbitrary::Arbitrary))]
+pub struct CpuTimes {
+    /// User CPU time of the process
+    #[cfg_attr(feature = "serde", serde(with = "::nanos::serde"))]
+    pub user: Duration,
+    /// System CPU time of the process
+    #[cfg_attr(feature = "serde", serde(with = "::nanos::serde"))]
+    pub system: Duration,
+    /// User CPU time of the reaped children
+    #[cfg_attr(feature = "serde", serde(with = "::nanos::serde"))]
+    pub children_user: Duration,
+    /// System CPU time of the reaped children
+    #[cfg_attr(feature = "serde", serde(with = "::nanos::serde"))]
+    pub children_system: Duration,
+}
+
+// `clock_t` is signed on some platforms and `u64` on Apple ones
+#[allow(clippy::unnecessary_cast)]
+fn to_duration(ticks: clock_t, ticks_per_sec: u64) -> Duration {
+    let ticks = ticks as u64;
+    Duration::new(
+        ticks / ticks_per_sec,
+        ((ticks % ticks_per_sec) * 1_000_000_000 / ticks_per_sec) as u32,
+    )
+}
+
+impl CpuTimes {
+    /// Get current CPU times of the process and its children
+    ///
+    /// # Panics
+    ///
+    /// If `times()` can't be used. Use `try_now()` to handle this case.
+    pub fn now() -> CpuTimes {
+        match CpuTimes::try_now() {
+            Ok(times) => times,
+            Err(_) => panic!("CPU times are not supported"),
+        }
+    }
+    /// Get current CPU times of the process and its children
+    pub fn try_now() -> Result<CpuTimes, CpuTimeError> {
+        let ticks_per_sec = unsafe { sysconf(_SC_CLK_TCK) };
+        if ticks_per_sec <= 0 {
+            return Err(CpuTimeError::Unsupported);
+        }
+        let ticks_per_sec = ticks_per_sec as u64;
+        let mut buf: tms = unsafe { mem::zeroed() };
+        // The return value is the elapsed real time, which may legitimately
+        // wrap to `-1`, and the call can't fail with a valid buffer
+        unsafe { times(&mut buf) };
+        Ok(CpuTimes {
+            user: to_duration(buf.tms_utime, ticks_per_sec),
+            system: to_duration(buf.tms_stime, ticks_per_sec),
+            children_user: to_duration(buf.tms_cutime, ticks_per_sec),
+            children_system: to_duration(buf.tms_cstime, ticks_per_sec),
+        })
+    }
+    /// Returns the user and system time of the process itself
+    pub fn process(&self) -> SplitTime {
+        SplitTime {
+            user: self.user,
+            system: self.system,
+        }
+    }
+    /// Returns the user and system time of the reaped children
+    pub fn children(&self) -> SplitTime {
+        SplitTime {
+            user: self.children_user,
+            system: self.children_system,
+        }
+    }
+    /// Returns all four values added up
+    pub fn total(&self) -> Duration {
+        self.process()
+            .total()
+            .saturating_add(self.children().total())
+    }
+    /// Returns the CPU times used from the previous reading, each value is
+    /// zero if that reading's value is larger
+    pub fn saturating_duration_since(&self, earlier: CpuTimes) -> CpuTimes {
+        let process = self.process().saturating_duration_since(earlier.process());
+        let children = self
+            .children()
+            .saturating_duration_since(earlier.children());
+        CpuTimes {
+            user: process.user,
+            system: process.system,
+            children_user: children.user,
+            children_system: children.system,
+        }
+    }
+}
+
+#[cfg(feature = "proptest")]
+impl ::proptest::arbitrary::Arbitrary for CpuTimes {
+    type Parameters = ();
+    type Strategy = ::proptest::strategy::BoxedStrategy<CpuTimes>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        use proptest::arbitrary::any;
+        use proptest::strategy::Strategy;
+
+        any::<(Duration, Duration, Duration, Duration)>()
+            .prop_map(|(user, system, children_user, children_system)| CpuTimes {
+                user,
+                system,
+                children_user,
+                children_system,
+            })
+            .boxed()
+    }
+}
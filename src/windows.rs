@@ -1,21 +1,104 @@
 use std::marker::PhantomData;
-use std::rc::Rc;
 use std::time::Duration;
 
-use winapi::shared::minwindef::FILETIME;
-use winapi::um::processthreadsapi::OpenProcess;
+use winapi::shared::minwindef::{DWORD, FILETIME};
+use winapi::shared::winerror::{ERROR_ACCESS_DENIED, ERROR_INVALID_PARAMETER};
+use winapi::um::errhandlingapi::GetLastError;
+use winapi::um::handleapi::CloseHandle;
+use winapi::um::minwinbase::STILL_ACTIVE;
 use winapi::um::processthreadsapi::{GetCurrentProcess, GetCurrentThread};
+use winapi::um::processthreadsapi::{GetExitCodeProcess, OpenProcess, OpenThread};
 use winapi::um::processthreadsapi::{GetProcessTimes, GetThreadTimes};
-use winapi::um::winnt::PROCESS_QUERY_INFORMATION;
+use winapi::um::winnt::{
+    HANDLE, PROCESS_QUERY_LIMITED_INFORMATION, THREAD_QUERY_LIMITED_INFORMATION,
+};
+
+use nanos::to_nanos;
+use CpuTimeError;
+use Rounding;
+use SplitTime;
 
 /// CPU Time Used by The Whole Process
 ///
 /// This is an opaque type similar to `std::time::Instant`.
 /// Use `elapsed()` or `duration_since()` to get meaningful time deltas.
-#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
 pub struct ProcessTime {
     duration: Duration,
-    process: winapi::um::winnt::HANDLE,
+    // id of the measured process, 0 for the current one
+    pid: u32,
+}
+
+/// An open handle of another process, closed on drop
+///
+/// `ProcessTime::now_for()` opens the process for each reading, which also
+/// means that a recycled process id silently refers to another process.
+/// The handle keeps referring to the original process, even after it has
+/// exited:
+///
+/// ```rust,no_run
+/// use cpu_time::{ProcessHandle, ProcessTime};
+///
+/// let handle = ProcessHandle::open(1234).unwrap();
+/// let start = ProcessTime::now_for_handle(&handle).unwrap();
+/// // ...
+/// let used = ProcessTime::now_for_handle(&handle).unwrap().duration_since(start);
+/// ```
+#[derive(Debug)]
+pub struct ProcessHandle {
+    handle: HANDLE,
+    pid: u32,
+}
+
+// Process handles can be used from any thread
+unsafe impl Send for ProcessHandle {}
+unsafe impl Sync for ProcessHandle {}
+
+impl ProcessHandle {
+    /// Opens process `pid` with
+    /// `OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION)`
+    ///
+    /// That right is all `GetProcessTimes()` needs, and unlike
+    /// `PROCESS_QUERY_INFORMATION` it's granted for protected and elevated
+    /// processes too.
+    ///
+    /// Returns `CpuTimeError::ProcessGone` if there is no process with this
+    /// id and `CpuTimeError::PermissionDenied` if it belongs to another
+    /// user.
+    pub fn open(pid: u32) -> Result<ProcessHandle, CpuTimeError> {
+        let handle = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false as i32, pid) };
+        if handle.is_null() {
+            // `OpenProcess` rejects ids of processes that don't exist
+            return Err(match unsafe { GetLastError() } {
+                ERROR_INVALID_PARAMETER => CpuTimeError::ProcessGone,
+                code => error(code),
+            });
+        }
+        Ok(ProcessHandle { handle, pid })
+    }
+    /// Returns the id of the process, as passed to `open()`
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+    /// Returns `true` until the process has exited
+    ///
+    /// A process that exited with code 259 (`STILL_ACTIVE`) is reported as
+    /// alive, `GetExitCodeProcess` can't tell them apart.
+    pub fn is_alive(&self) -> bool {
+        let mut code = 0;
+        let ok = unsafe { GetExitCodeProcess(self.handle, &mut code) };
+        ok != 0 && code == STILL_ACTIVE
+    }
+    /// Returns the raw handle, which stays owned by `self`
+    pub fn as_raw_handle(&self) -> HANDLE {
+        self.handle
+    }
+}
+
+impl Drop for ProcessHandle {
+    fn drop(&mut self) {
+        unsafe { CloseHandle(self.handle) };
+    }
 }
 
 /// CPU Time Used by The Current Thread
@@ -26,143 +109,543 @@ pub struct ProcessTime {
 /// This type is non-thread-shareable (!Sync, !Send) because otherwise it's
 /// to easy to mess up times from different threads. However, you can freely
 /// send Duration's returned by `elapsed()` and `duration_since()`.
-#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
 pub struct ThreadTime(
     Duration,
     // makes type non-sync and non-send
-    PhantomData<Rc<()>>,
+    PhantomData<*const ()>,
 );
 
-fn to_duration(kernel_time: FILETIME, user_time: FILETIME) -> Duration {
+// What `GetCurrentProcess()` returns, usable in constants
+const CURRENT_PROCESS: HANDLE = -1isize as HANDLE;
+
+pub fn to_duration(kernel_time: FILETIME, user_time: FILETIME) -> Duration {
     // resolution: 100ns
     let kns100 = ((kernel_time.dwHighDateTime as u64) << 32) + kernel_time.dwLowDateTime as u64;
     let uns100 = ((user_time.dwHighDateTime as u64) << 32) + user_time.dwLowDateTime as u64;
-    Duration::new(
-        (kns100 + uns100) / 10_000_000,
-        (((kns100 + uns100) * 100) % 1_000_000_000) as u32,
-    )
+    let ticks = kns100.saturating_add(uns100);
+    Duration::new(ticks / 10_000_000, ((ticks % 10_000_000) * 100) as u32)
 }
 
-fn zero() -> FILETIME {
+// The inverse of `to_duration()`, without going through nanoseconds, which
+// overflow `u64` long before the ticks do
+fn to_ticks(duration: Duration) -> u64 {
+    duration
+        .as_secs()
+        .checked_mul(10_000_000)
+        .and_then(|ticks| ticks.checked_add(u64::from(duration.subsec_nanos() / 100)))
+        .unwrap_or(u64::MAX)
+}
+
+pub fn zero() -> FILETIME {
     FILETIME {
         dwLowDateTime: 0,
         dwHighDateTime: 0,
     }
 }
 
+pub fn error(code: DWORD) -> CpuTimeError {
+    match code {
+        ERROR_ACCESS_DENIED => CpuTimeError::PermissionDenied,
+        _ => CpuTimeError::Os(code as i32),
+    }
+}
+
+// Kernel and user time
+fn process_times(process: HANDLE) -> Result<(FILETIME, FILETIME), CpuTimeError> {
+    let mut kernel_time = zero();
+    let mut user_time = zero();
+    let ok = unsafe {
+        GetProcessTimes(
+            process,
+            &mut zero(),
+            &mut zero(),
+            &mut kernel_time,
+            &mut user_time,
+        )
+    };
+    if ok == 0 {
+        return Err(error(unsafe { GetLastError() }));
+    }
+    Ok((kernel_time, user_time))
+}
+
+fn process_time(process: HANDLE) -> Result<Duration, CpuTimeError> {
+    process_times(process).map(|(kernel_time, user_time)| to_duration(kernel_time, user_time))
+}
+
+pub fn process_split(process: HANDLE) -> Result<SplitTime, CpuTimeError> {
+    process_times(process).map(|(kernel_time, user_time)| SplitTime {
+        user: to_duration(zero(), user_time),
+        system: to_duration(kernel_time, zero()),
+    })
+}
+
+// Kernel and user time
+fn thread_times(thread: HANDLE) -> Result<(FILETIME, FILETIME), CpuTimeError> {
+    let mut kernel_time = zero();
+    let mut user_time = zero();
+    let ok = unsafe {
+        GetThreadTimes(
+            thread,
+            &mut zero(),
+            &mut zero(),
+            &mut kernel_time,
+            &mut user_time,
+        )
+    };
+    if ok == 0 {
+        return Err(error(unsafe { GetLastError() }));
+    }
+    Ok((kernel_time, user_time))
+}
+
+fn thread_time() -> Result<Duration, CpuTimeError> {
+    thread_times(unsafe { GetCurrentThread() })
+        .map(|(kernel_time, user_time)| to_duration(kernel_time, user_time))
+}
+
+fn thread_split() -> Result<SplitTime, CpuTimeError> {
+    thread_times(unsafe { GetCurrentThread() }).map(|(kernel_time, user_time)| SplitTime {
+        user: to_duration(zero(), user_time),
+        system: to_duration(kernel_time, zero()),
+    })
+}
+
+// CPU time of any thread of the system, by id
+fn thread_time_for(id: u32) -> Result<Duration, CpuTimeError> {
+    let thread = unsafe { OpenThread(THREAD_QUERY_LIMITED_INFORMATION, false as i32, id) };
+    if thread.is_null() {
+        // `OpenThread` rejects ids of threads that don't exist
+        return Err(match unsafe { GetLastError() } {
+            ERROR_INVALID_PARAMETER => CpuTimeError::ProcessGone,
+            code => error(code),
+        });
+    }
+    let times = thread_times(thread);
+    unsafe { CloseHandle(thread) };
+    times.map(|(kernel_time, user_time)| to_duration(kernel_time, user_time))
+}
+
 impl ProcessTime {
+    /// Zero CPU time, e.g. to initialize statics without reading the clock
+    pub const ZERO: ProcessTime = ProcessTime::from_nanos(0);
+    /// Resolution of `as_raw_ticks()`, Windows counts CPU time in 100 ns units
+    pub const TICKS_PER_SEC: u64 = 10_000_000;
+
+    /// Timestamp of `secs` seconds of CPU time used
+    pub const fn from_secs(secs: u64) -> ProcessTime {
+        ProcessTime::from_duration(Duration::from_secs(secs))
+    }
+    /// Timestamp of `duration` of CPU time used
+    ///
+    /// Meant for tests of code that stores and compares timestamps.
+    /// The timestamp belongs to the current process.
+    pub const fn from_duration(duration: Duration) -> ProcessTime {
+        ProcessTime { duration, pid: 0 }
+    }
+    /// Timestamp of `nanos` nanoseconds of CPU time used
+    pub const fn from_nanos(nanos: u64) -> ProcessTime {
+        ProcessTime::from_duration(Duration::from_nanos(nanos))
+    }
     /// Get current CPU time used by the current process
     ///
     /// # Panics
     ///
     /// If `GetProcessTimes` fails (not sure if it can happen)
     pub fn now() -> ProcessTime {
-        let mut kernel_time = zero();
-        let mut user_time = zero();
-        let process = unsafe { GetCurrentProcess() };
-        let ok = unsafe {
-            GetProcessTimes(
-                process,
-                &mut zero(),
-                &mut zero(),
-                &mut kernel_time,
-                &mut user_time,
-            )
-        };
-        if ok == 0 {
-            panic!("Can't get process times");
-        }
-        ProcessTime {
-            duration: to_duration(kernel_time, user_time),
-            process,
+        match ProcessTime::try_now() {
+            Ok(time) => time,
+            Err(_) => panic!("Can't get process times"),
         }
     }
+    /// Get current CPU time used by the current process
+    ///
+    /// Returns an error instead of panicking if `GetProcessTimes` fails.
+    pub fn try_now() -> Result<ProcessTime, CpuTimeError> {
+        process_time(unsafe { GetCurrentProcess() })
+            .map(|duration| ProcessTime { duration, pid: 0 })
+    }
     /// Get current CPU time used by a given process
     ///
+    /// The process is opened with `ProcessHandle::open()` and closed again
+    /// after the reading, see there for the errors. `elapsed()` of the
+    /// result opens it again by id.
+    pub fn now_for(id: u32) -> Result<ProcessTime, CpuTimeError> {
+        let handle = ProcessHandle::open(id)?;
+        process_time(handle.handle).map(|duration| ProcessTime { duration, pid: id })
+    }
+    /// Same as `now_for()`
+    pub fn try_now_for(id: u32) -> Result<ProcessTime, CpuTimeError> {
+        ProcessTime::now_for(id)
+    }
+    /// Get current CPU time used by an open process
+    ///
+    /// Unlike `now_for()` this can't read another process that got the same
+    /// id. After the process has exited this returns its final CPU time.
+    pub fn now_for_handle(handle: &ProcessHandle) -> Result<ProcessTime, CpuTimeError> {
+        process_time(handle.handle).map(|duration| ProcessTime {
+            duration,
+            pid: handle.pid,
+        })
+    }
+    /// Get current CPU time used by the current process, split into user
+    /// and system time
+    ///
+    /// Both parts come from the same `GetProcessTimes` call as `now()`.
+    pub fn try_now_split() -> Result<SplitTime, CpuTimeError> {
+        process_split(CURRENT_PROCESS)
+    }
+    /// Get current CPU time used by the current process, split into user
+    /// and system time
+    ///
     /// # Panics
     ///
-    /// If `GetProcessTimes` fails (not sure if it can happen)
-    pub fn now_for(id: u32) -> ProcessTime {
-        let mut kernel_time = zero();
-        let mut user_time = zero();
-        let process = unsafe { OpenProcess(PROCESS_QUERY_INFORMATION, false as i32, id) };
-        let ok = unsafe {
-            GetProcessTimes(
-                process,
-                &mut zero(),
-                &mut zero(),
-                &mut kernel_time,
-                &mut user_time,
-            )
-        };
-        if ok == 0 {
-            panic!("Can't get process times");
-        }
-        ProcessTime {
-            duration: to_duration(kernel_time, user_time),
-            process,
+    /// If the split can't be read. Use `try_now_split()` to handle this
+    /// case.
+    pub fn now_split() -> SplitTime {
+        match ProcessTime::try_now_split() {
+            Ok(split) => split,
+            Err(_) => panic!("Process CPU time split is not supported"),
         }
     }
+    /// Returns `true` if process CPU time can be measured in the current
+    /// environment, i.e. if `now()` won't panic
+    ///
+    /// This actually calls `GetProcessTimes`.
+    pub fn is_supported() -> bool {
+        process_time(unsafe { GetCurrentProcess() }).is_ok()
+    }
     /// Returns the amount of CPU time used from the previous timestamp to now.
+    ///
+    /// A timestamp later than now gives zero.
     pub fn elapsed(&self) -> Duration {
-        let mut kernel_time = zero();
-        let mut user_time = zero();
-        let ok = unsafe {
-            GetProcessTimes(
-                self.process,
-                &mut zero(),
-                &mut zero(),
-                &mut kernel_time,
-                &mut user_time,
-            )
-        };
-        if ok == 0 {
-            panic!("Can't get process times");
+        match self.try_elapsed() {
+            Ok(duration) => duration,
+            Err(error) => panic!("can't read the process CPU time: {}", error),
         }
-        to_duration(kernel_time, user_time) - self.duration
+    }
+    /// Returns the amount of CPU time used from the previous timestamp to now.
+    ///
+    /// Unlike `elapsed()` this returns an error if the clock can't be read.
+    /// It never panics, a timestamp later than now gives zero.
+    pub fn try_elapsed(&self) -> Result<Duration, CpuTimeError> {
+        let now = match self.pid {
+            0 => ProcessTime::try_now()?,
+            pid => ProcessTime::now_for(pid)?,
+        };
+        Ok(now
+            .duration
+            .checked_sub(self.duration)
+            .unwrap_or_else(|| Duration::new(0, 0)))
+    }
+    /// Runs `f` and returns its result with the CPU time the process used
+    /// meanwhile
+    ///
+    /// Saves the two timestamps around a single call:
+    ///
+    /// ```rust
+    /// use cpu_time::ProcessTime;
+    ///
+    /// let (sum, time) = ProcessTime::measure(|| (0..1000u64).sum::<u64>());
+    /// println!("{} took {:?}", sum, time);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If the CPU time can't be read, like `now()`.
+    pub fn measure<R, F: FnOnce() -> R>(f: F) -> (R, Duration) {
+        let start = ProcessTime::now();
+        let result = f();
+        (result, start.elapsed())
     }
     /// Returns the amount of CPU time used from the previous timestamp.
     pub fn duration_since(&self, timestamp: ProcessTime) -> Duration {
         self.duration - timestamp.duration
     }
+    /// Timestamp of `ticks` of CPU time used, in the units of the OS counter
+    /// (see `TICKS_PER_SEC`)
+    pub const fn from_raw_ticks(ticks: u64) -> ProcessTime {
+        ProcessTime::from_duration(Duration::new(
+            ticks / 10_000_000,
+            (ticks % 10_000_000) as u32 * 100,
+        ))
+    }
+    /// Returns the amount of CPU time used in the units of the OS counter,
+    /// rounded as requested
+    ///
+    /// Timestamps read from the OS are whole ticks, so this only matters
+    /// for ones built from a `Duration`.
+    pub fn as_raw_ticks_rounded(&self, rounding: Rounding) -> u64 {
+        rounding.ticks(self.duration, ProcessTime::TICKS_PER_SEC)
+    }
+    /// Returns the amount of CPU time used from the previous timestamp to now
+    /// as seconds.
+    pub fn elapsed_secs_f64(&self) -> f64 {
+        self.elapsed().as_secs_f64()
+    }
+    /// Returns the amount of CPU time used from the previous timestamp to now
+    /// as seconds.
+    pub fn elapsed_secs_f32(&self) -> f32 {
+        self.elapsed().as_secs_f32()
+    }
+    /// Returns the timestamp `duration` of CPU time later than this one, or
+    /// `None` if it can't be represented.
+    pub fn checked_add(&self, duration: Duration) -> Option<ProcessTime> {
+        self.duration
+            .checked_add(duration)
+            .map(|duration| ProcessTime {
+                duration,
+                pid: self.pid,
+            })
+    }
+    /// Returns the timestamp `duration` of CPU time earlier than this one, or
+    /// `None` if that would be before the process started.
+    pub fn checked_sub(&self, duration: Duration) -> Option<ProcessTime> {
+        self.duration
+            .checked_sub(duration)
+            .map(|duration| ProcessTime {
+                duration,
+                pid: self.pid,
+            })
+    }
+    /// Returns the amount of CPU time used from the previous timestamp, or
+    /// zero if that timestamp is later than this one.
+    pub fn saturating_duration_since(&self, timestamp: ProcessTime) -> Duration {
+        self.checked_duration_since(timestamp)
+            .unwrap_or_else(|| Duration::new(0, 0))
+    }
+    /// Returns the amount of CPU time used from the previous timestamp, or
+    /// `None` if that timestamp is later than this one.
+    pub fn checked_duration_since(&self, timestamp: ProcessTime) -> Option<Duration> {
+        self.duration.checked_sub(timestamp.duration)
+    }
     /// Returns the amount of CPU time used.
     pub fn duration(&self) -> Duration {
         self.duration
     }
+    /// Returns the amount of CPU time used in nanoseconds
+    ///
+    /// Saturates at `u64::MAX`, which is ~584 years.
+    pub fn as_nanos_u64(&self) -> u64 {
+        to_nanos(self.duration)
+    }
+    /// Returns the amount of CPU time used in the units of the OS
+    /// counter, see `TICKS_PER_SEC`
+    ///
+    /// Saturates at `u64::MAX`, which is ~58,000 years.
+    pub fn as_raw_ticks(&self) -> u64 {
+        to_ticks(self.duration)
+    }
+    /// Returns the handle of the process read by `now()`, e.g. to pass it
+    /// to `WaitForSingleObject()`
+    ///
+    /// This is the `GetCurrentProcess()` pseudo handle, which always refers
+    /// to the process using it. Timestamps of other processes don't keep a
+    /// handle, as they can be copied freely; use a `ProcessHandle` for those.
+    pub fn handle() -> HANDLE {
+        CURRENT_PROCESS
+    }
+    /// Returns `true` if no CPU time was used, e.g. for `ZERO`
+    pub fn is_zero(&self) -> bool {
+        self.duration == Duration::from_secs(0)
+    }
 }
 
 impl ThreadTime {
-    /// Get current CPU time used by a process process
+    /// Zero CPU time, e.g. to initialize statics without reading the clock
+    pub const ZERO: ThreadTime = ThreadTime::from_nanos(0);
+    /// Resolution of `as_raw_ticks()`, Windows counts CPU time in 100 ns units
+    pub const TICKS_PER_SEC: u64 = 10_000_000;
+
+    /// Timestamp of `secs` seconds of CPU time used
+    pub const fn from_secs(secs: u64) -> ThreadTime {
+        ThreadTime::from_duration(Duration::from_secs(secs))
+    }
+    /// Timestamp of `duration` of CPU time used
+    ///
+    /// Meant for tests of code that stores and compares timestamps.
+    pub const fn from_duration(duration: Duration) -> ThreadTime {
+        ThreadTime(duration, PhantomData)
+    }
+    /// Timestamp of `nanos` nanoseconds of CPU time used
+    pub const fn from_nanos(nanos: u64) -> ThreadTime {
+        ThreadTime::from_duration(Duration::from_nanos(nanos))
+    }
+    /// Get current CPU time used by the current thread
     ///
     /// # Panics
     ///
     /// If `GetThreadTimes` fails (not sure if it can happen)
     pub fn now() -> ThreadTime {
-        let mut kernel_time = zero();
-        let mut user_time = zero();
-        let thread = unsafe { GetCurrentThread() };
-        let ok = unsafe {
-            GetThreadTimes(
-                thread,
-                &mut zero(),
-                &mut zero(),
-                &mut kernel_time,
-                &mut user_time,
-            )
-        };
-        if ok == 0 {
-            panic!("Can't get trhad times");
+        match ThreadTime::try_now() {
+            Ok(time) => time,
+            Err(_) => panic!("Can't get thread times"),
+        }
+    }
+    /// Get current CPU time used by the current thread
+    ///
+    /// Returns an error instead of panicking if `GetThreadTimes` fails.
+    pub fn try_now() -> Result<ThreadTime, CpuTimeError> {
+        thread_time().map(|duration| ThreadTime(duration, PhantomData))
+    }
+    /// Get current CPU time used by a given thread
+    ///
+    /// Opens the thread with `THREAD_QUERY_LIMITED_INFORMATION`, reads it
+    /// with `GetThreadTimes` and closes it again, so a watchdog can sample
+    /// workers by the id from `GetCurrentThreadId()`. The thread can belong
+    /// to any process.
+    ///
+    /// The result can be compared with other readings of the same thread,
+    /// but `elapsed()` always measures the *current* thread. Returns
+    /// `CpuTimeError::ProcessGone` if there is no thread with this id and
+    /// `CpuTimeError::PermissionDenied` if it can't be opened.
+    pub fn now_for_id(id: u32) -> Result<ThreadTime, CpuTimeError> {
+        thread_time_for(id).map(|duration| ThreadTime(duration, PhantomData))
+    }
+    /// Get current CPU time used by the current thread, split into user
+    /// and system time
+    ///
+    /// Both parts come from the same `GetThreadTimes` call as `now()`.
+    pub fn try_now_split() -> Result<SplitTime, CpuTimeError> {
+        thread_split()
+    }
+    /// Get current CPU time used by the current thread, split into user
+    /// and system time
+    ///
+    /// # Panics
+    ///
+    /// If the split can't be read. Use `try_now_split()` to handle this
+    /// case.
+    pub fn now_split() -> SplitTime {
+        match ThreadTime::try_now_split() {
+            Ok(split) => split,
+            Err(_) => panic!("Thread CPU time split is not supported"),
         }
-        ThreadTime(to_duration(kernel_time, user_time), PhantomData)
+    }
+    /// Returns `true` if thread CPU time can be measured in the current
+    /// environment, i.e. if `now()` won't panic
+    ///
+    /// This actually calls `GetThreadTimes`.
+    pub fn is_supported() -> bool {
+        thread_time().is_ok()
     }
     /// Returns the amount of CPU time used by the current thread
     /// from the previous timestamp to now.
+    ///
+    /// A timestamp later than now gives zero.
     pub fn elapsed(&self) -> Duration {
-        ThreadTime::now().duration_since(*self)
+        ThreadTime::now().saturating_duration_since(*self)
+    }
+    /// Returns the amount of CPU time used by the current thread
+    /// from the previous timestamp to now.
+    ///
+    /// Unlike `elapsed()` this returns an error if the clock can't be read.
+    /// It never panics, a timestamp later than now gives zero.
+    pub fn try_elapsed(&self) -> Result<Duration, CpuTimeError> {
+        ThreadTime::try_now().map(|now| now.saturating_duration_since(*self))
+    }
+    /// Runs `f` and returns its result with the CPU time the current thread used
+    /// meanwhile
+    ///
+    /// Saves the two timestamps around a single call:
+    ///
+    /// ```rust
+    /// use cpu_time::ThreadTime;
+    ///
+    /// let (sum, time) = ThreadTime::measure(|| (0..1000u64).sum::<u64>());
+    /// println!("{} took {:?}", sum, time);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If the CPU time can't be read, like `now()`.
+    pub fn measure<R, F: FnOnce() -> R>(f: F) -> (R, Duration) {
+        let start = ThreadTime::now();
+        let result = f();
+        (result, start.elapsed())
     }
     /// Returns the amount of CPU time used by the current thread
     /// from the previous timestamp.
     pub fn duration_since(&self, timestamp: ThreadTime) -> Duration {
         self.0 - timestamp.0
     }
+    /// Timestamp of `ticks` of CPU time used by the current thread, in the
+    /// units of the OS counter (see `TICKS_PER_SEC`)
+    pub const fn from_raw_ticks(ticks: u64) -> ThreadTime {
+        ThreadTime::from_duration(Duration::new(
+            ticks / 10_000_000,
+            (ticks % 10_000_000) as u32 * 100,
+        ))
+    }
+    /// Returns the amount of CPU time used by the current thread in the
+    /// units of the OS counter, rounded as requested
+    pub fn as_raw_ticks_rounded(&self, rounding: Rounding) -> u64 {
+        rounding.ticks(self.0, ThreadTime::TICKS_PER_SEC)
+    }
+    /// Returns the amount of CPU time used by the current thread
+    /// from the previous timestamp to now as seconds.
+    pub fn elapsed_secs_f64(&self) -> f64 {
+        self.elapsed().as_secs_f64()
+    }
+    /// Returns the amount of CPU time used by the current thread
+    /// from the previous timestamp to now as seconds.
+    pub fn elapsed_secs_f32(&self) -> f32 {
+        self.elapsed().as_secs_f32()
+    }
+    /// Returns the timestamp `duration` of CPU time later than this one, or
+    /// `None` if it can't be represented.
+    pub fn checked_add(&self, duration: Duration) -> Option<ThreadTime> {
+        self.0
+            .checked_add(duration)
+            .map(|d| ThreadTime(d, PhantomData))
+    }
+    /// Returns the timestamp `duration` of CPU time earlier than this one, or
+    /// `None` if that would be before the thread started.
+    pub fn checked_sub(&self, duration: Duration) -> Option<ThreadTime> {
+        self.0
+            .checked_sub(duration)
+            .map(|d| ThreadTime(d, PhantomData))
+    }
+    /// Returns the amount of CPU time used by the current thread
+    /// from the previous timestamp, or zero if that timestamp is later than
+    /// this one.
+    pub fn saturating_duration_since(&self, timestamp: ThreadTime) -> Duration {
+        self.checked_duration_since(timestamp)
+            .unwrap_or_else(|| Duration::new(0, 0))
+    }
+    /// Returns the amount of CPU time used by the current thread
+    /// from the previous timestamp, or `None` if that timestamp is later
+    /// than this one.
+    pub fn checked_duration_since(&self, timestamp: ThreadTime) -> Option<Duration> {
+        self.0.checked_sub(timestamp.0)
+    }
+    /// Returns the amount of CPU time used by the current thread.
+    pub fn duration(&self) -> Duration {
+        self.0
+    }
+    /// Returns the amount of CPU time used by the current thread in nanoseconds
+    ///
+    /// Saturates at `u64::MAX`, which is ~584 years.
+    pub fn as_nanos_u64(&self) -> u64 {
+        to_nanos(self.0)
+    }
+    /// Returns the amount of CPU time used by the current thread in the
+    /// units of the OS counter, see `TICKS_PER_SEC`
+    ///
+    /// Saturates at `u64::MAX`, which is ~58,000 years.
+    pub fn as_raw_ticks(&self) -> u64 {
+        to_ticks(self.0)
+    }
+    /// Returns the handle of the thread read by `now()`
+    ///
+    /// This is the `GetCurrentThread()` pseudo handle, which always refers to
+    /// the thread using it.
+    pub fn handle() -> HANDLE {
+        unsafe { GetCurrentThread() }
+    }
+    /// Returns `true` if no CPU time was used, e.g. for `ZERO`
+    pub fn is_zero(&self) -> bool {
+        self.0 == Duration::from_secs(0)
+    }
 }
@@ -1,9 +1,53 @@
+#[cfg(feature = "arbitrary")]
+extern crate arbitrary;
+#[cfg(feature = "axum")]
+extern crate axum_core;
+#[cfg(feature = "bincode")]
+extern crate bincode;
+#[cfg(feature = "chrono")]
+extern crate chrono;
+extern crate cpu_time;
+#[cfg(feature = "futures-core")]
+extern crate futures_core;
+#[cfg(feature = "tower")]
+extern crate http;
+#[cfg(all(unix, feature = "libc"))]
+extern crate libc;
+#[cfg(feature = "proptest")]
+extern crate proptest;
+#[cfg(feature = "rkyv")]
+extern crate rkyv;
+#[cfg(feature = "serde")]
+extern crate serde_json;
+#[cfg(feature = "serde")]
+extern crate serde_test;
+#[cfg(feature = "time")]
+extern crate time;
+#[cfg(feature = "tokio")]
+extern crate tokio;
+#[cfg(feature = "tower")]
+extern crate tower_layer;
+#[cfg(feature = "tower")]
+extern crate tower_service;
+
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use cpu_time::{ProcessTime, ThreadTime};
 
+// Burns at least `time` of CPU in the current thread
+#[allow(dead_code)]
+fn spin(time: Duration) {
+    let start = ThreadTime::now();
+    let wall = Instant::now();
+    while start.elapsed() < time {
+        assert!(wall.elapsed() < Duration::from_secs(1));
+    }
+}
+
+// Emscripten emulates CPU clocks with wall-clock time
 #[test]
+#[cfg_attr(target_os = "emscripten", ignore)]
 fn process_time() {
     let time = ProcessTime::now();
     sleep(Duration::new(1, 0));
@@ -12,9 +56,2361 @@ fn process_time() {
 }
 
 #[test]
+#[cfg_attr(target_os = "emscripten", ignore)]
 fn thread_time() {
     let time = ThreadTime::now();
     sleep(Duration::new(1, 0));
     let elapsed = time.elapsed();
     assert!(elapsed < Duration::from_millis(100));
 }
+
+#[test]
+fn duration() {
+    let process = ProcessTime::now();
+    let thread = ThreadTime::now();
+    let later = ThreadTime::now();
+    assert!(ProcessTime::now().duration() >= process.duration());
+    assert_eq!(
+        later.duration() - thread.duration(),
+        later.duration_since(thread)
+    );
+}
+
+#[test]
+fn checked_duration_since() {
+    let earlier = ProcessTime::now();
+    let later = ProcessTime::now();
+    assert_eq!(
+        later.checked_duration_since(earlier),
+        Some(later.duration_since(earlier))
+    );
+    if later != earlier {
+        assert_eq!(earlier.checked_duration_since(later), None);
+    }
+}
+
+#[test]
+fn saturating_duration_since() {
+    let earlier = ThreadTime::now();
+    let later = ThreadTime::now();
+    assert_eq!(
+        later.saturating_duration_since(earlier),
+        later.duration_since(earlier)
+    );
+    assert_eq!(
+        earlier.saturating_duration_since(later),
+        Duration::new(0, 0)
+    );
+}
+
+#[test]
+fn checked_add_sub() {
+    let time = ProcessTime::now();
+    let later = time.checked_add(Duration::from_millis(5)).unwrap();
+    assert_eq!(later.duration_since(time), Duration::from_millis(5));
+    assert_eq!(later.checked_sub(Duration::from_millis(5)), Some(time));
+    assert_eq!(
+        time.checked_sub(time.duration() + Duration::new(1, 0)),
+        None
+    );
+    let thread = ThreadTime::now();
+    assert_eq!(
+        thread.checked_add(Duration::new(1, 0)).unwrap().duration(),
+        thread.duration() + Duration::new(1, 0)
+    );
+}
+
+#[test]
+fn add_sub_duration() {
+    let time = ProcessTime::now();
+    let mut later = time + Duration::from_millis(5);
+    assert_eq!(later - Duration::from_millis(5), time);
+    later -= Duration::from_millis(5);
+    assert_eq!(later, time);
+    let mut thread = ThreadTime::now();
+    let start = thread;
+    thread += Duration::new(1, 0);
+    assert_eq!(thread.duration_since(start), Duration::new(1, 0));
+}
+
+#[test]
+fn ordering() {
+    let earlier = ThreadTime::now();
+    let later = earlier + Duration::from_millis(1);
+    assert!(earlier < later);
+    assert_eq!(earlier.max(later), later);
+    let mut times = std::collections::BTreeSet::new();
+    times.insert(ProcessTime::now() + Duration::new(1, 0));
+    times.insert(ProcessTime::now());
+    assert!(times.iter().next() < times.iter().next_back());
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn serde_as_nanos() {
+    use serde_test::{assert_de_tokens, assert_tokens, Token};
+
+    let time = ThreadTime::now();
+    let nanos = time.duration().as_secs() * 1_000_000_000 + time.duration().subsec_nanos() as u64;
+    assert_tokens(&time, &[Token::U64(nanos)]);
+    let time = ProcessTime::now();
+    let nanos = time.duration().as_secs() * 1_000_000_000 + time.duration().subsec_nanos() as u64;
+    assert_tokens(&time, &[Token::U64(nanos)]);
+    // Like TOML, which only has signed integers
+    assert_de_tokens(&time, &[Token::I64(nanos as i64)]);
+    assert_de_tokens(
+        &cpu_time::SplitTime {
+            user: Duration::from_nanos(3),
+            system: Duration::from_nanos(4),
+        },
+        &[
+            Token::Struct {
+                name: "SplitTime",
+                len: 2,
+            },
+            Token::Str("user"),
+            Token::I8(3),
+            Token::Str("system"),
+            Token::U32(4),
+            Token::StructEnd,
+        ],
+    );
+}
+
+#[test]
+#[cfg(feature = "rkyv")]
+fn rkyv_round_trip() {
+    use rkyv::rancor::Error;
+
+    let time = ThreadTime::now();
+    let bytes = rkyv::to_bytes::<Error>(&time).unwrap();
+    let archived = unsafe { rkyv::access_unchecked::<rkyv::Archived<ThreadTime>>(&bytes) };
+    let restored: ThreadTime = rkyv::deserialize::<_, Error>(archived).unwrap();
+    assert_eq!(restored, time);
+}
+
+#[test]
+#[cfg(feature = "bincode")]
+fn bincode_round_trip() {
+    let config = bincode::config::standard();
+    let time = ProcessTime::now();
+    let bytes = bincode::encode_to_vec(time, config).unwrap();
+    let (restored, _): (ProcessTime, _) = bincode::decode_from_slice(&bytes, config).unwrap();
+    assert_eq!(restored, time);
+}
+
+#[test]
+#[cfg(all(
+    any(feature = "serde", feature = "rkyv", feature = "bincode"),
+    any(
+        all(target_os = "linux", feature = "libc", not(miri)),
+        all(any(target_os = "macos", windows), not(miri))
+    )
+))]
+fn now_for_round_trip() {
+    let time = ProcessTime::now_for(std::process::id()).unwrap();
+    // Without the pid, which isn't serialized
+    let current = ProcessTime::from_duration(time.duration());
+    assert_ne!(current, time);
+    #[cfg(feature = "serde")]
+    {
+        let json = serde_json::to_string(&time).unwrap();
+        assert_eq!(serde_json::from_str::<ProcessTime>(&json).unwrap(), current);
+    }
+    #[cfg(feature = "rkyv")]
+    {
+        use rkyv::rancor::Error;
+
+        let bytes = rkyv::to_bytes::<Error>(&time).unwrap();
+        let archived = unsafe { rkyv::access_unchecked::<rkyv::Archived<ProcessTime>>(&bytes) };
+        let restored: ProcessTime = rkyv::deserialize::<_, Error>(archived).unwrap();
+        assert_eq!(restored, current);
+    }
+    #[cfg(feature = "bincode")]
+    {
+        let config = bincode::config::standard();
+        let bytes = bincode::encode_to_vec(time, config).unwrap();
+        let (restored, _): (ProcessTime, _) = bincode::decode_from_slice(&bytes, config).unwrap();
+        assert_eq!(restored, current);
+    }
+}
+
+#[test]
+#[cfg(all(feature = "serde", not(miri)))]
+fn snapshots_serde() {
+    // Only used on the platforms with snapshots
+    #[allow(unused_macros)]
+    macro_rules! round_trip {
+        ($value:expr, $type:ty) => {
+            let value = $value;
+            let json = serde_json::to_string(&value).unwrap();
+            assert_eq!(serde_json::from_str::<$type>(&json).unwrap(), value);
+        };
+    }
+
+    #[cfg(any(
+        all(target_os = "linux", feature = "libc"),
+        target_os = "macos",
+        windows
+    ))]
+    {
+        round_trip!(
+            cpu_time::ThreadsSnapshot::capture().unwrap(),
+            cpu_time::ThreadsSnapshot
+        );
+        round_trip!(
+            cpu_time::ProcessesSnapshot::capture().unwrap(),
+            cpu_time::ProcessesSnapshot
+        );
+    }
+    #[cfg(any(
+        all(any(target_os = "linux", target_os = "macos"), feature = "libc"),
+        windows
+    ))]
+    round_trip!(
+        cpu_time::ContextSwitches::process().unwrap(),
+        cpu_time::ContextSwitches
+    );
+    #[cfg(all(any(target_os = "linux", target_os = "macos"), feature = "libc"))]
+    {
+        round_trip!(
+            cpu_time::ResourceUsage::process().unwrap(),
+            cpu_time::ResourceUsage
+        );
+        round_trip!(cpu_time::CpuTimes::now(), cpu_time::CpuTimes);
+        // Times in nanoseconds, like the timestamps
+        let json = serde_json::to_string(&cpu_time::CpuTimes {
+            user: Duration::from_millis(5),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(
+            json,
+            "{\"user\":5000000,\"system\":0,\"children_user\":0,\"children_system\":0}"
+        );
+    }
+    #[cfg(windows)]
+    round_trip!(cpu_time::SystemTimes::now(), cpu_time::SystemTimes);
+}
+
+#[test]
+#[cfg(all(any(feature = "rkyv", feature = "bincode"), not(miri)))]
+fn snapshots_rkyv_bincode() {
+    macro_rules! round_trip {
+        ($value:expr, $type:ty) => {
+            let value = $value;
+            #[cfg(feature = "rkyv")]
+            {
+                use rkyv::rancor::Error;
+
+                let bytes = rkyv::to_bytes::<Error>(&value).unwrap();
+                let archived = unsafe { rkyv::access_unchecked::<rkyv::Archived<$type>>(&bytes) };
+                let restored: $type = rkyv::deserialize::<_, Error>(archived).unwrap();
+                assert_eq!(restored, value);
+            }
+            #[cfg(feature = "bincode")]
+            {
+                let config = bincode::config::standard();
+                let bytes = bincode::encode_to_vec(&value, config).unwrap();
+                let (restored, _): ($type, _) = bincode::decode_from_slice(&bytes, config).unwrap();
+                assert_eq!(restored, value);
+            }
+        };
+    }
+
+    round_trip!(ProcessTime::now_split(), cpu_time::SplitTime);
+    #[cfg(any(
+        all(target_os = "linux", feature = "libc"),
+        target_os = "macos",
+        windows
+    ))]
+    {
+        round_trip!(
+            cpu_time::ThreadsSnapshot::capture().unwrap(),
+            cpu_time::ThreadsSnapshot
+        );
+        round_trip!(
+            cpu_time::ProcessesSnapshot::capture().unwrap(),
+            cpu_time::ProcessesSnapshot
+        );
+    }
+    #[cfg(any(
+        all(any(target_os = "linux", target_os = "macos"), feature = "libc"),
+        windows
+    ))]
+    round_trip!(
+        cpu_time::ContextSwitches::process().unwrap(),
+        cpu_time::ContextSwitches
+    );
+    #[cfg(all(any(target_os = "linux", target_os = "macos"), feature = "libc"))]
+    {
+        round_trip!(
+            cpu_time::ResourceUsage::process().unwrap(),
+            cpu_time::ResourceUsage
+        );
+        round_trip!(cpu_time::CpuTimes::now(), cpu_time::CpuTimes);
+    }
+    #[cfg(windows)]
+    round_trip!(cpu_time::SystemTimes::now(), cpu_time::SystemTimes);
+}
+
+#[test]
+fn cpu_duration_display() {
+    use cpu_time::CpuDuration;
+
+    assert_eq!(CpuDuration(Duration::new(2, 0)).to_string(), "2.000 s");
+    assert_eq!(CpuDuration(Duration::from_millis(87)).to_string(), "87 ms");
+    assert_eq!(
+        CpuDuration(Duration::from_micros(412)).to_string(),
+        "412 µs"
+    );
+    assert_eq!(CpuDuration(Duration::new(0, 5)).to_string(), "5 ns");
+    assert_eq!(
+        format!("{:.2}", CpuDuration(Duration::from_micros(1500))),
+        "1.50 ms"
+    );
+}
+
+#[test]
+fn cpu_duration_display_rounding() {
+    use cpu_time::CpuDuration;
+
+    assert_eq!(
+        CpuDuration(Duration::from_micros(999_600)).to_string(),
+        "1.000 s"
+    );
+    assert_eq!(
+        format!("{:.1}", CpuDuration(Duration::from_micros(999_600))),
+        "999.6 ms"
+    );
+    assert_eq!(
+        format!("{:.0}", CpuDuration(Duration::from_micros(999_600))),
+        "1 s"
+    );
+    assert_eq!(CpuDuration(Duration::new(0, 999_999)).to_string(), "1 ms");
+    assert_eq!(CpuDuration(Duration::new(0, 999_499)).to_string(), "999 µs");
+    assert_eq!(CpuDuration(Duration::new(0, 999)).to_string(), "999 ns");
+    assert_eq!(
+        CpuDuration(Duration::new(1, 999_500_000)).to_string(),
+        "2.000 s"
+    );
+    assert_eq!(
+        format!("{:.12}", CpuDuration(Duration::new(1, 5))),
+        "1.000000005000 s"
+    );
+    assert_eq!(
+        CpuDuration(Duration::MAX).to_string(),
+        "18446744073709551616.000 s"
+    );
+}
+
+#[test]
+#[cfg(feature = "arbitrary")]
+fn arbitrary_from_nanos() {
+    use arbitrary::{Arbitrary, Unstructured};
+
+    let mut u = Unstructured::new(&[1, 0, 0, 0, 0, 0, 0, 0]);
+    let time = ProcessTime::arbitrary(&mut u).unwrap();
+    assert_eq!(time, ProcessTime::from_nanos(1));
+}
+
+#[test]
+#[cfg(feature = "proptest")]
+fn proptest_timestamps() {
+    use proptest::arbitrary::any;
+    use proptest::test_runner::TestRunner;
+
+    TestRunner::default()
+        .run(&any::<ThreadTime>(), |time| {
+            assert_eq!(
+                time.checked_duration_since(ThreadTime::ZERO),
+                Some(time.duration())
+            );
+            Ok(())
+        })
+        .unwrap();
+}
+
+#[test]
+#[cfg(feature = "arbitrary")]
+fn arbitrary_snapshots() {
+    use arbitrary::{Arbitrary, Unstructured};
+
+    let mut u = Unstructured::new(&[0; 32]);
+    let split = cpu_time::SplitTime::arbitrary(&mut u).unwrap();
+    assert_eq!(split, cpu_time::SplitTime::default());
+    #[cfg(any(
+        all(target_os = "linux", feature = "libc"),
+        target_os = "macos",
+        windows
+    ))]
+    {
+        let mut u = Unstructured::new(&[]);
+        let snapshot = cpu_time::ThreadsSnapshot::arbitrary(&mut u).unwrap();
+        assert!(snapshot.threads().is_empty());
+    }
+}
+
+#[test]
+#[cfg(feature = "proptest")]
+fn proptest_snapshots() {
+    use proptest::arbitrary::any;
+    use proptest::test_runner::TestRunner;
+
+    TestRunner::default()
+        .run(&any::<cpu_time::SplitTime>(), |split| {
+            assert_eq!(
+                split.saturating_duration_since(split),
+                cpu_time::SplitTime::default()
+            );
+            Ok(())
+        })
+        .unwrap();
+    #[cfg(any(
+        all(target_os = "linux", feature = "libc"),
+        target_os = "macos",
+        windows
+    ))]
+    TestRunner::default()
+        .run(&any::<cpu_time::ThreadsSnapshot>(), |snapshot| {
+            let delta = &snapshot - &snapshot;
+            assert_eq!(delta.threads().len(), snapshot.threads().len());
+            Ok(())
+        })
+        .unwrap();
+}
+
+#[test]
+fn sub_timestamps() {
+    let start = ProcessTime::now();
+    let end = ProcessTime::now();
+    assert_eq!(end - start, end.duration_since(start));
+    let start = ThreadTime::now();
+    let end = ThreadTime::now();
+    assert_eq!(end - start, end.duration_since(start));
+}
+
+#[test]
+fn elapsed_secs() {
+    let time = ProcessTime::now();
+    assert!(time.elapsed_secs_f64() >= 0.0);
+    assert!(time.elapsed_secs_f64() < 1.0);
+    let time = ThreadTime::now();
+    assert!(time.elapsed_secs_f32() < 1.0);
+}
+
+#[test]
+fn free_functions() {
+    let process = ProcessTime::now();
+    assert!(cpu_time::process_cpu_time() >= process.duration());
+    let thread = ThreadTime::now();
+    assert!(cpu_time::thread_cpu_time() >= thread.duration());
+}
+
+#[test]
+fn from_duration() {
+    let start = ProcessTime::from_duration(Duration::from_millis(10));
+    let end: ProcessTime = Duration::from_millis(25).into();
+    assert_eq!(end.duration_since(start), Duration::from_millis(15));
+    let thread = ThreadTime::from_duration(Duration::new(3, 0));
+    assert_eq!(thread, ThreadTime::from_secs(3));
+    assert_eq!(ThreadTime::from(Duration::new(3, 0)), thread);
+}
+
+#[test]
+fn zero() {
+    static START: ProcessTime = ProcessTime::ZERO;
+    assert!(START.is_zero());
+    assert!(ProcessTime::now() >= START);
+    assert!(!ThreadTime::from_secs(1).is_zero());
+    assert_eq!(
+        ThreadTime::from_nanos(1_500_000_000).duration_since(ThreadTime::ZERO),
+        Duration::from_millis(1500)
+    );
+}
+
+#[test]
+fn as_nanos() {
+    let time = ProcessTime::from_duration(Duration::new(2, 300));
+    assert_eq!(time.as_nanos_u64(), 2_000_000_300);
+    let ticks = ThreadTime::from_secs(3).as_raw_ticks();
+    assert_eq!(ticks, 3 * ThreadTime::TICKS_PER_SEC);
+}
+
+#[test]
+fn fallible() {
+    use cpu_time::fallible;
+
+    if let Ok(start) = fallible::process_time() {
+        let later = ProcessTime::from_duration(start.duration() + Duration::from_secs(1));
+        assert_eq!(fallible::process_elapsed(later), Ok(Duration::new(0, 0)));
+        assert_eq!(
+            fallible::process_duration_between(later, start),
+            Duration::new(0, 0)
+        );
+        assert!(fallible::process_cpu_time().unwrap() >= start.duration());
+    }
+    if let Ok(start) = fallible::thread_time() {
+        let later = ThreadTime::from_duration(start.duration() + Duration::from_secs(1));
+        assert_eq!(fallible::thread_elapsed(later), Ok(Duration::new(0, 0)));
+        assert_eq!(
+            fallible::thread_duration_between(start, later),
+            Duration::from_secs(1)
+        );
+    }
+}
+
+#[test]
+fn rounding() {
+    use cpu_time::Rounding;
+
+    let half = Duration::from_nanos(1_500_000_000);
+    assert_eq!(Rounding::Truncate.ticks(half, 1), 1);
+    assert_eq!(Rounding::Nearest.ticks(half, 1), 2);
+    assert_eq!(
+        Rounding::Nearest.ticks(Duration::from_nanos(1_499_999_999), 1),
+        1
+    );
+    assert_eq!(
+        Rounding::Up.ticks(Duration::from_nanos(1_000_000_001), 1),
+        2
+    );
+    assert_eq!(Rounding::Up.ticks(Duration::from_secs(1), 1), 1);
+    assert_eq!(Rounding::default(), Rounding::Truncate);
+    assert_eq!(
+        Rounding::Up.ticks(Duration::from_secs(u64::MAX), 1_000),
+        u64::MAX
+    );
+
+    let ticks = 12_345;
+    assert_eq!(ProcessTime::from_raw_ticks(ticks).as_raw_ticks(), ticks);
+    assert_eq!(ThreadTime::from_raw_ticks(ticks).as_raw_ticks(), ticks);
+    // Windows ticks outlast the nanoseconds
+    #[cfg(windows)]
+    {
+        let ticks = u64::MAX - 1;
+        assert_eq!(ProcessTime::from_raw_ticks(ticks).as_raw_ticks(), ticks);
+        assert_eq!(ThreadTime::from_raw_ticks(ticks).as_raw_ticks(), ticks);
+        let time = ThreadTime::from_duration(Duration::MAX);
+        assert_eq!(time.as_raw_ticks(), u64::MAX);
+    }
+    let time = ProcessTime::from_duration(Duration::from_nanos(1_250));
+    assert_eq!(
+        time.as_raw_ticks_rounded(Rounding::Up),
+        Rounding::Up.ticks(time.duration(), ProcessTime::TICKS_PER_SEC)
+    );
+    assert_eq!(
+        time.as_raw_ticks_rounded(Rounding::Truncate),
+        time.as_raw_ticks()
+    );
+}
+
+#[test]
+#[cfg(feature = "chrono")]
+fn chrono_conversions() {
+    use cpu_time::CpuDuration;
+    use std::convert::TryFrom;
+
+    let duration = CpuDuration(Duration::from_millis(1_500));
+    let converted = chrono::Duration::try_from(duration).unwrap();
+    assert_eq!(converted, chrono::Duration::milliseconds(1_500));
+    assert_eq!(CpuDuration::try_from(converted), Ok(duration));
+    assert!(CpuDuration::try_from(chrono::Duration::seconds(-1)).is_err());
+    assert!(chrono::Duration::try_from(CpuDuration(Duration::from_secs(u64::MAX))).is_err());
+    assert_eq!(duration.iso8601().to_string(), "PT1.5S");
+}
+
+#[test]
+#[cfg(feature = "time")]
+fn time_conversions() {
+    use cpu_time::CpuDuration;
+    use std::convert::TryFrom;
+
+    let duration = CpuDuration(Duration::from_millis(1_500));
+    let converted = time::Duration::try_from(duration).unwrap();
+    assert_eq!(converted, time::Duration::milliseconds(1_500));
+    assert_eq!(CpuDuration::try_from(converted), Ok(duration));
+    assert!(CpuDuration::try_from(time::Duration::seconds(-1)).is_err());
+    assert!(time::Duration::try_from(CpuDuration(Duration::from_secs(u64::MAX))).is_err());
+}
+
+#[test]
+fn process_split() {
+    use cpu_time::SplitTime;
+
+    let split = SplitTime {
+        user: Duration::from_millis(3),
+        system: Duration::from_millis(2),
+    };
+    assert_eq!(split.total(), Duration::from_millis(5));
+    let earlier = SplitTime {
+        user: Duration::from_millis(1),
+        system: Duration::from_millis(4),
+    };
+    assert_eq!(
+        split.saturating_duration_since(earlier),
+        SplitTime {
+            user: Duration::from_millis(2),
+            system: Duration::new(0, 0),
+        }
+    );
+
+    if let Ok(start) = ProcessTime::try_now_split() {
+        let later = ProcessTime::now_split();
+        assert!(later.user >= start.user);
+        assert!(later.system >= start.system);
+    }
+}
+
+#[test]
+fn thread_split() {
+    if let Ok(start) = ThreadTime::try_now_split() {
+        let later = ThreadTime::now_split();
+        assert!(later.user >= start.user);
+        assert!(later.system >= start.system);
+    }
+    #[cfg(all(target_os = "linux", feature = "libc", not(miri)))]
+    {
+        let split = ThreadTime::now_split();
+        assert!(split.total() <= ThreadTime::now().duration() + Duration::from_millis(10));
+    }
+}
+
+#[test]
+#[cfg(all(
+    any(target_os = "linux", target_os = "macos"),
+    feature = "libc",
+    not(miri)
+))]
+fn children_time() {
+    use cpu_time::ChildrenTime;
+    use std::process::Command;
+
+    let start = ChildrenTime::now();
+    let split = ChildrenTime::now_split();
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg("i=0; while [ $i -lt 100000 ]; do i=$((i+1)); done")
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert!(start.elapsed() > Duration::new(0, 0));
+    assert!(ChildrenTime::now_split().total() > split.total());
+    assert!(ChildrenTime::ZERO <= start);
+}
+
+#[test]
+#[cfg(all(unix, feature = "libc", not(miri)))]
+fn cpu_times() {
+    use cpu_time::CpuTimes;
+
+    let start = CpuTimes::now();
+    spin(Duration::from_millis(30));
+    let used = CpuTimes::now().saturating_duration_since(start);
+    assert_eq!(
+        used.total(),
+        used.process().total() + used.children().total()
+    );
+    assert!(used.process().total() <= Duration::from_secs(1));
+    assert_eq!(
+        CpuTimes::default().saturating_duration_since(start),
+        CpuTimes::default()
+    );
+}
+
+#[test]
+#[cfg(all(
+    any(target_os = "linux", target_os = "macos"),
+    feature = "libc",
+    not(miri)
+))]
+fn resource_usage() {
+    use cpu_time::ResourceUsage;
+
+    let start = ResourceUsage::process().unwrap();
+    let buf = vec![1u8; 4 << 20];
+    assert_eq!(buf.iter().map(|&b| b as usize).sum::<usize>(), 4 << 20);
+    let usage = ResourceUsage::process().unwrap();
+    assert!(usage.max_rss >= 4 << 20);
+    let delta = usage.saturating_sub(start);
+    assert_eq!(delta.max_rss, usage.max_rss);
+    assert!(delta.minor_faults > 0);
+    assert!(delta.cpu().total() <= Duration::from_secs(1));
+    assert!(ResourceUsage::children().is_ok());
+    #[cfg(target_os = "linux")]
+    assert!(ResourceUsage::thread().unwrap().cpu().total() <= usage.cpu().total());
+}
+
+#[test]
+#[cfg(any(
+    all(any(target_os = "linux", target_os = "macos"), feature = "libc"),
+    windows
+))]
+#[cfg(not(miri))]
+fn context_switches() {
+    use cpu_time::ContextSwitches;
+
+    let start = ContextSwitches::process().unwrap();
+    sleep(Duration::from_millis(5));
+    let switches = ContextSwitches::process().unwrap();
+    assert!(switches.total >= start.total);
+    let delta = switches.saturating_sub(start);
+    assert_eq!(delta.total, switches.total - start.total);
+    #[cfg(unix)]
+    assert_eq!(
+        delta.voluntary.unwrap() + delta.involuntary.unwrap(),
+        delta.total
+    );
+    #[cfg(any(target_os = "linux", windows))]
+    assert!(ContextSwitches::thread().unwrap().total <= switches.total);
+}
+
+#[test]
+#[cfg(all(target_os = "linux", feature = "rusage-thread", not(miri)))]
+fn rusage_thread_backend() {
+    // `getrusage` counts in microseconds
+    spin(Duration::from_millis(1));
+    assert_eq!(ThreadTime::now().as_nanos_u64() % 1_000, 0);
+}
+
+#[test]
+#[cfg(all(windows, not(miri)))]
+fn process_cycles() {
+    use cpu_time::ProcessCycles;
+
+    let start = ProcessCycles::now();
+    spin(Duration::from_millis(1));
+    assert!(start.elapsed() > 0);
+    assert_eq!(ProcessCycles::from_cycles(5).cycles_since(start), 0);
+}
+
+#[test]
+#[cfg(all(windows, not(miri)))]
+fn thread_cycles() {
+    use cpu_time::{CycleRate, ThreadCycles};
+
+    let start = ThreadCycles::now();
+    spin(Duration::from_millis(1));
+    assert!(start.elapsed() > 0);
+    let rate = CycleRate::calibrate(Duration::from_millis(20)).unwrap();
+    assert!(rate.cycles_per_sec() > 0);
+    let rate = CycleRate::from_cycles_per_sec(2_000_000_000);
+    assert_eq!(
+        rate.to_duration(3_000_000_000),
+        Duration::from_millis(1_500)
+    );
+    assert_eq!(
+        CycleRate::from_cycles_per_sec(0).to_duration(5),
+        Duration::new(0, 0)
+    );
+}
+
+#[test]
+#[cfg(all(windows, not(miri)))]
+fn system_times() {
+    use cpu_time::SystemTimes;
+
+    let start = SystemTimes::now();
+    spin(Duration::from_millis(20));
+    let delta = SystemTimes::now().saturating_sub(start);
+    assert!(delta.busy() <= delta.total());
+    assert_eq!(delta - delta, SystemTimes::default());
+    let utilization = delta.utilization();
+    assert!((0.0..=1.0).contains(&utilization));
+    assert_eq!(SystemTimes::default().utilization(), 0.0);
+}
+
+#[test]
+#[cfg(any(target_os = "openbsd", target_os = "aix", target_os = "android"))]
+fn rusage_thread_time_advances() {
+    let time = ThreadTime::now();
+    spin(Duration::from_millis(1));
+    assert!(time.elapsed() >= Duration::from_millis(1));
+    assert!(ThreadTime::now() > time);
+}
+
+#[test]
+#[cfg(target_os = "netbsd")]
+fn netbsd_thread_time_is_per_lwp() {
+    let time = ThreadTime::now();
+    std::thread::spawn(|| spin(Duration::from_millis(20)))
+        .join()
+        .unwrap();
+    assert!(time.elapsed() < Duration::from_millis(20));
+}
+
+#[test]
+#[cfg(target_os = "dragonfly")]
+fn dragonfly_process_time_advances() {
+    let time = ProcessTime::now();
+    spin(Duration::from_millis(1));
+    assert!(time.elapsed() >= Duration::from_millis(1));
+}
+
+#[test]
+#[cfg(any(target_os = "illumos", target_os = "solaris"))]
+fn solarish_thread_time_advances() {
+    let time = ThreadTime::now();
+    spin(Duration::from_millis(1));
+    assert!(time.elapsed() >= Duration::from_millis(1));
+}
+
+#[test]
+fn try_now() {
+    use cpu_time::CpuTimeError;
+
+    match ProcessTime::try_now() {
+        Ok(time) => assert!(time.try_elapsed().unwrap() < Duration::from_secs(1)),
+        Err(e) => assert_ne!(e, CpuTimeError::ProcessGone),
+    }
+    match ThreadTime::try_now() {
+        Ok(time) => assert!(time.try_elapsed().unwrap() < Duration::from_secs(1)),
+        Err(e) => assert_ne!(e, CpuTimeError::ProcessGone),
+    }
+}
+
+#[test]
+fn elapsed_of_later_timestamp() {
+    let later = Duration::new(3600, 0);
+    let time = ProcessTime::now();
+    let time = ProcessTime::from_duration(time.duration() + later);
+    assert_eq!(time.elapsed(), Duration::new(0, 0));
+    assert_eq!(time.try_elapsed().unwrap(), Duration::new(0, 0));
+    let time = ThreadTime::from_duration(ThreadTime::now().duration() + later);
+    assert_eq!(time.elapsed(), Duration::new(0, 0));
+    assert_eq!(time.try_elapsed().unwrap(), Duration::new(0, 0));
+}
+
+#[test]
+#[cfg(windows)]
+fn try_now_for() {
+    use cpu_time::ProcessHandle;
+
+    let time = ProcessTime::try_now_for(std::process::id()).unwrap();
+    assert!(time.try_elapsed().unwrap() < Duration::from_secs(1));
+    // The System Idle Process can't be opened
+    assert!(ProcessTime::try_now_for(0).is_err());
+    // No process has an id that large
+    assert_eq!(
+        ProcessTime::try_now_for(u32::MAX - 2).unwrap_err(),
+        cpu_time::CpuTimeError::ProcessGone
+    );
+
+    let mut child = std::process::Command::new("cmd")
+        .args(["/C", "exit"])
+        .spawn()
+        .unwrap();
+    let handle = ProcessHandle::open(child.id()).unwrap();
+    child.wait().unwrap();
+    assert!(!handle.is_alive());
+    // The handle still refers to the exited process
+    let end = ProcessTime::now_for_handle(&handle).unwrap();
+    assert_eq!(ProcessTime::now_for_handle(&handle).unwrap(), end);
+}
+
+#[test]
+#[cfg(any(
+    all(target_os = "linux", feature = "libc", not(miri)),
+    all(target_os = "macos", not(miri))
+))]
+fn unix_try_now_for() {
+    let time = ProcessTime::try_now_for(std::process::id()).unwrap();
+    assert!(time <= ProcessTime::now_for(std::process::id()).unwrap());
+    assert!(time.try_elapsed().unwrap() < Duration::from_secs(1));
+    // Above Linux's `PID_MAX_LIMIT` and macOS's `PID_MAX`
+    assert_eq!(
+        ProcessTime::try_now_for(0x3fff_ffff).unwrap_err(),
+        cpu_time::CpuTimeError::ProcessGone
+    );
+}
+
+#[test]
+#[cfg(all(target_os = "linux", feature = "libc", not(miri)))]
+fn for_thread() {
+    use std::sync::mpsc::channel;
+
+    let (stop, stopped) = channel::<()>();
+    let worker = std::thread::spawn(move || {
+        let start = ThreadTime::now();
+        while stopped.try_recv().is_err() && start.elapsed() < Duration::from_secs(5) {}
+    });
+    let start = ThreadTime::for_thread(&worker).unwrap();
+    let end = loop {
+        let now = ThreadTime::for_thread(&worker).unwrap();
+        if now.duration_since(start) >= Duration::from_millis(10) {
+            break now;
+        }
+    };
+    assert!(end > start);
+    stop.send(()).unwrap();
+    worker.join().unwrap();
+}
+
+#[test]
+#[cfg(all(target_os = "linux", feature = "libc", not(miri)))]
+fn process_handle() {
+    use cpu_time::{CpuTimeError, ProcessHandle};
+
+    let handle = match ProcessHandle::open(std::process::id()) {
+        Ok(handle) => handle,
+        // Kernel before 5.3 or a seccomp filter
+        Err(CpuTimeError::Unsupported) => return,
+        Err(e) => panic!("{}", e),
+    };
+    assert!(handle.is_alive());
+    let start = ProcessTime::now_for_handle(&handle).unwrap();
+    assert!(ProcessTime::now_for_handle(&handle).unwrap() >= start);
+
+    let mut child = std::process::Command::new("true").spawn().unwrap();
+    let handle = ProcessHandle::open(child.id()).unwrap();
+    child.wait().unwrap();
+    assert!(!handle.is_alive());
+    assert_eq!(
+        ProcessTime::now_for_handle(&handle).unwrap_err(),
+        CpuTimeError::ProcessGone
+    );
+}
+
+#[test]
+#[cfg(any(
+    all(target_os = "linux", feature = "libc", not(miri)),
+    all(any(target_os = "macos", windows), not(miri))
+))]
+fn threads_snapshot() {
+    use cpu_time::ThreadsSnapshot;
+
+    let worker = std::thread::Builder::new()
+        .name("spinner".to_string())
+        .spawn(|| {
+            let start = ThreadTime::now();
+            while start.elapsed() < Duration::from_millis(50) {}
+            std::thread::park();
+        })
+        .unwrap();
+    std::thread::sleep(Duration::from_millis(100));
+    let snapshot = ThreadsSnapshot::capture().unwrap();
+    assert!(snapshot.threads().len() >= 2);
+    let busiest = snapshot.busiest().unwrap();
+    assert_eq!(snapshot.get(busiest.tid), Some(busiest));
+    if !cfg!(windows) {
+        assert!(snapshot
+            .threads()
+            .iter()
+            .any(|thread| thread.name.as_ref().map(|name| &name[..]) == Some("spinner")));
+    }
+    let later = ThreadsSnapshot::capture().unwrap();
+    let delta = &later - &snapshot;
+    let own = delta.get(busiest.tid).unwrap();
+    assert!(own.cpu_time() <= later.get(busiest.tid).unwrap().cpu_time());
+    assert!(delta.total().total() >= delta.busiest().unwrap().cpu_time());
+    assert_eq!(
+        (snapshot.clone() - ThreadsSnapshot::default()).threads(),
+        snapshot.threads()
+    );
+    worker.thread().unpark();
+    worker.join().unwrap();
+}
+
+#[test]
+#[cfg(all(target_os = "linux", feature = "libc", not(miri)))]
+fn wait_with_cpu_time() {
+    use cpu_time::ChildExt;
+    use std::process::Command;
+
+    let child = Command::new("sh")
+        .args([
+            "-c",
+            "i=0; while [ $i -lt 50000 ]; do i=$((i+1)); done; exit 3",
+        ])
+        .spawn()
+        .unwrap();
+    let (status, split) = child.wait_with_cpu_time().unwrap();
+    assert_eq!(status.code(), Some(3));
+    assert!(split.total() > Duration::from_secs(0));
+
+    let mut child = Command::new("sleep").arg("0.1").spawn().unwrap();
+    assert_eq!(cpu_time::try_wait4(Some(child.id())).unwrap(), None);
+    let reaped = cpu_time::wait4(Some(child.id())).unwrap();
+    assert_eq!(reaped.pid, child.id());
+    assert!(reaped.status.success());
+    assert!(reaped.usage.max_rss > 0);
+    assert!(child.try_wait().is_err());
+    assert!(cpu_time::wait4(Some(0)).is_err());
+}
+
+#[test]
+#[cfg(all(target_os = "linux", feature = "libc", not(miri)))]
+fn cpu_limit() {
+    use cpu_time::CommandExt;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::Command;
+
+    let status = Command::new("sh")
+        .args(["-c", "while :; do :; done"])
+        .cpu_limit(Duration::from_millis(1))
+        .status()
+        .unwrap();
+    assert!(status.signal() == Some(libc::SIGXCPU) || status.signal() == Some(libc::SIGKILL));
+    let mut child = Command::new("true")
+        .spawn_with_cpu_limit(Duration::from_secs(10))
+        .unwrap();
+    assert!(child.wait().unwrap().success());
+    // Lowered to the hard limit
+    let mut child = Command::new("true")
+        .spawn_with_cpu_limit(Duration::MAX)
+        .unwrap();
+    assert!(child.wait().unwrap().success());
+}
+
+#[test]
+#[cfg(all(target_os = "linux", feature = "libc", not(miri)))]
+fn bench_command() {
+    use std::process::Command;
+
+    let mut command = Command::new("sh");
+    command.args(["-c", "i=0; while [ $i -lt 10000 ]; do i=$((i+1)); done"]);
+    let bench = cpu_time::bench_command(&mut command, 3).unwrap();
+    assert_eq!(bench.runs().len(), 3);
+    assert!(bench.min() <= bench.median() && bench.median() <= bench.max());
+    assert!(bench.min() <= bench.mean() && bench.mean() <= bench.max());
+    assert!(bench.stddev() <= bench.max() - bench.min());
+    assert!(cpu_time::bench_command(&mut Command::new("false"), 1).is_err());
+    let empty = cpu_time::bench_command(&mut command, 0).unwrap();
+    assert_eq!(empty.mean(), Duration::from_secs(0));
+}
+
+#[test]
+#[cfg(all(target_os = "linux", feature = "libc", not(miri)))]
+fn cpu_limit_current() {
+    use cpu_time::CpuLimit;
+
+    let limit = CpuLimit::current().unwrap();
+    if let (Some(soft), Some(hard)) = (limit.soft, limit.hard) {
+        assert!(soft <= hard);
+    }
+    match (limit.soft, cpu_time::remaining_cpu_budget().unwrap()) {
+        (None, None) => {}
+        (Some(soft), Some(left)) => assert!(left <= soft),
+        other => panic!("unexpected {:?}", other),
+    }
+    limit.set().unwrap();
+    assert_eq!(CpuLimit::current().unwrap(), limit);
+
+    cpu_time::catch_sigxcpu().unwrap();
+    assert!(!cpu_time::sigxcpu_received());
+    unsafe { libc::raise(libc::SIGXCPU) };
+    assert!(cpu_time::sigxcpu_received());
+    assert!(!cpu_time::sigxcpu_received());
+}
+
+#[test]
+#[cfg(all(target_os = "linux", feature = "libc", not(miri)))]
+fn cpu_alarm() {
+    use cpu_time::CpuAlarm;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    static CALLED: AtomicBool = AtomicBool::new(false);
+    fn callback() {
+        CALLED.store(true, Ordering::SeqCst);
+    }
+
+    let alarm = CpuAlarm::thread(Duration::from_millis(20)).unwrap();
+    unsafe { alarm.on_fire(callback) };
+    let idle = CpuAlarm::thread(Duration::from_secs(3600)).unwrap();
+    assert!(!alarm.fired());
+    assert!(alarm.remaining() <= Duration::from_millis(20));
+    let start = ThreadTime::now();
+    while !alarm.fired() {}
+    assert!(start.elapsed() >= Duration::from_millis(19));
+    assert_eq!(alarm.remaining(), Duration::from_secs(0));
+    // The signal may be handled on any thread, but `fired()` calls the
+    // callback too
+    assert!(CALLED.load(Ordering::SeqCst));
+    assert!(!idle.fired());
+    // More than fit into the table, each one freeing its slot again
+    for _ in 0..40 {
+        assert_eq!(
+            CpuAlarm::process(Duration::MAX).unwrap_err(),
+            cpu_time::CpuTimeError::Os(libc::EINVAL)
+        );
+    }
+    assert!(CpuAlarm::process(Duration::from_millis(1)).is_ok());
+}
+
+#[test]
+#[cfg(any(
+    all(target_os = "linux", feature = "libc", not(miri)),
+    all(windows, not(miri))
+))]
+fn child_set() {
+    use cpu_time::ChildSet;
+    use std::process::Command;
+
+    let mut children = ChildSet::new();
+    let mut pids = Vec::new();
+    for code in 0..3 {
+        let mut command = if cfg!(windows) {
+            let mut command = Command::new("cmd");
+            command.args(["/C", &format!("exit {}", code)]);
+            command
+        } else {
+            let mut command = Command::new("sh");
+            command.args(["-c", &format!("exit {}", code)]);
+            command
+        };
+        pids.push(children.spawn(&mut command).unwrap());
+    }
+    assert_eq!(children.running(), 3);
+    let total = children.wait_all().unwrap();
+    assert_eq!(children.running(), 0);
+    assert_eq!(children.reap().unwrap(), None);
+    let mut finished = children.finished().to_vec();
+    finished.sort_by_key(|child| child.status.code());
+    for (code, child) in finished.iter().enumerate() {
+        assert_eq!(child.pid, pids[code]);
+        assert_eq!(child.status.code(), Some(code as i32));
+    }
+    assert_eq!(total, children.total());
+}
+
+#[test]
+#[cfg(all(windows, not(miri)))]
+fn wait_with_cpu_time() {
+    use cpu_time::ChildExt;
+    use std::process::Command;
+
+    let child = Command::new("cmd").args(["/C", "exit 3"]).spawn().unwrap();
+    let (status, _) = child.wait_with_cpu_time().unwrap();
+    assert_eq!(status.code(), Some(3));
+    let mut child = Command::new("cmd").args(["/C", "exit"]).spawn().unwrap();
+    assert!(child.wait().unwrap().success());
+    // The handle stays open after `wait()`
+    assert!(child.cpu_time().is_ok());
+}
+
+#[test]
+#[cfg(all(windows, not(miri)))]
+fn spawn_with_cpu_limit() {
+    use cpu_time::CommandExt;
+    use std::process::Command;
+
+    let mut command = Command::new("cmd");
+    command.args(["/C", "exit 3"]);
+    let mut child = command
+        .spawn_with_cpu_limit(Duration::from_secs(10))
+        .unwrap();
+    assert_eq!(child.wait().unwrap().code(), Some(3));
+    // Not left suspended
+    assert_eq!(command.status().unwrap().code(), Some(3));
+    let error = command.spawn_with_cpu_limit(Duration::MAX).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+#[cfg(any(
+    all(target_os = "linux", feature = "libc", not(miri)),
+    all(any(target_os = "macos", windows), not(miri))
+))]
+fn processes() {
+    let snapshot = cpu_time::ProcessesSnapshot::capture().unwrap();
+    let own = snapshot.get(std::process::id()).unwrap();
+    assert!(!own.name.is_empty());
+    assert_eq!(own.cpu_time(), own.split().total());
+    assert!(cpu_time::processes()
+        .unwrap()
+        .any(|process| process.pid == std::process::id()));
+    let later = cpu_time::ProcessesSnapshot::capture().unwrap();
+    let delta = &later - &snapshot;
+    let used = delta.get(std::process::id()).unwrap();
+    assert!(used.cpu_time() <= later.get(std::process::id()).unwrap().cpu_time());
+    assert!(delta.total(true).total() >= used.cpu_time());
+    let empty = cpu_time::ProcessesSnapshot::default();
+    assert_eq!((snapshot.clone() - empty).processes(), snapshot.processes());
+}
+
+#[test]
+#[cfg(any(
+    all(target_os = "linux", feature = "libc", not(miri)),
+    all(any(target_os = "macos", windows), not(miri))
+))]
+fn process_tree_time() {
+    use std::process::{Command, Stdio};
+
+    let mut child = if cfg!(windows) {
+        Command::new("cmd")
+            .args(["/C", "pause"])
+            .stdin(Stdio::piped())
+            .spawn()
+    } else {
+        Command::new("cat").stdin(Stdio::piped()).spawn()
+    }
+    .unwrap();
+    let snapshot = cpu_time::ProcessesSnapshot::capture().unwrap();
+    let tree = snapshot.tree(std::process::id());
+    assert_eq!(tree[0].pid, std::process::id());
+    assert!(tree.iter().any(|process| process.pid == child.id()));
+    let own = snapshot.get(std::process::id()).unwrap().split();
+    let total = snapshot.tree_cpu_time(std::process::id(), true).unwrap();
+    assert!(total.total() >= own.total());
+    assert_eq!(snapshot.tree_cpu_time(0x3fff_ffff, false), None);
+    assert!(cpu_time::process_tree_time(std::process::id(), false).is_ok());
+    #[cfg(target_os = "linux")]
+    {
+        let pgid = unsafe { libc::getpgid(0) } as u32;
+        let group = snapshot.group(pgid);
+        assert!(group
+            .iter()
+            .any(|process| process.pid == std::process::id()));
+        assert!(snapshot.group_cpu_time(pgid, false).unwrap().total() >= own.total());
+        let uid = unsafe { libc::geteuid() };
+        let users = snapshot.cpu_time_by_uid(true);
+        assert!(users[&uid].total() >= own.total());
+        assert!(cpu_time::process_time_by_uid(false)
+            .unwrap()
+            .contains_key(&uid));
+    }
+    drop(child.stdin.take());
+    child.wait().unwrap();
+}
+
+#[test]
+#[cfg(all(target_os = "linux", feature = "libc", not(miri)))]
+fn procfs_stat() {
+    use cpu_time::procfs::Stat;
+
+    let stat = Stat::read_self().unwrap();
+    assert_eq!(stat.pid, std::process::id());
+    assert_eq!(stat.cpu_time(), stat.split().total());
+    assert_eq!(Stat::read(stat.pid).unwrap().starttime, stat.starttime);
+    assert_eq!(
+        Stat::read(0x3fff_ffff).unwrap_err(),
+        cpu_time::CpuTimeError::ProcessGone
+    );
+
+    let line = "42 (a) b (c) R 1 42 42 0 -1 4194560 10 0 0 0 250 50 -1 7 20 0 1 0 \
+                1000 0 0 18446744073709551615 0 0 0 0 0 0 0 0 0 0 0 0 17 3 0 0";
+    let stat = Stat::parse(line).unwrap();
+    assert_eq!(stat.pid, 42);
+    assert!(stat.utime > stat.stime);
+    assert_eq!(stat.cutime, Duration::from_secs(0));
+    assert!(stat.cstime > Duration::from_secs(0));
+    assert_eq!(stat.processor, 3);
+    assert_eq!(Stat::parse("42 (a) R 1"), None);
+}
+
+#[test]
+#[cfg(all(target_os = "linux", feature = "libc", not(miri)))]
+fn procfs_thread_split() {
+    let tid = unsafe { libc::syscall(libc::SYS_gettid) } as u32;
+    let split = cpu_time::procfs::thread_split(tid).unwrap();
+    assert!(cpu_time::procfs::thread_split(tid).unwrap().total() >= split.total());
+    assert_eq!(cpu_time::procfs::Stat::read_thread(tid).unwrap().pid, tid);
+    assert_eq!(
+        cpu_time::procfs::thread_split(0x3fff_ffff).unwrap_err(),
+        cpu_time::CpuTimeError::ProcessGone
+    );
+}
+
+#[test]
+#[cfg(all(target_os = "linux", feature = "libc", not(miri)))]
+fn clock_id() {
+    let mut time = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    assert_eq!(
+        unsafe { libc::clock_gettime(ProcessTime::clock_id(), &mut time) },
+        0
+    );
+    assert_eq!(
+        unsafe { libc::clock_gettime(ThreadTime::clock_id(), &mut time) },
+        0
+    );
+
+    // Past 2^31 seconds, where a 32-bit `time_t` goes negative
+    let time = libc::timespec {
+        tv_sec: (1u64 << 31) as libc::time_t,
+        tv_nsec: 5,
+    };
+    assert_eq!(
+        ProcessTime::from_timespec(time).duration(),
+        Duration::new(1 << 31, 5)
+    );
+    assert_eq!(
+        ThreadTime::from_timespec(time).duration(),
+        Duration::new(1 << 31, 5)
+    );
+    assert_eq!(
+        cpu_time::CpuDuration(ProcessTime::from_timespec(time).duration()).to_string(),
+        "2147483648.000 s"
+    );
+
+    // The thread clock is the one of the thread reading it
+    let id = ThreadTime::clock_id();
+    spin(Duration::from_millis(50));
+    std::thread::spawn(move || {
+        let read = || {
+            let mut time = libc::timespec {
+                tv_sec: 0,
+                tv_nsec: 0,
+            };
+            assert_eq!(unsafe { libc::clock_gettime(id, &mut time) }, 0);
+            Duration::new(time.tv_sec as u64, time.tv_nsec as u32)
+        };
+        let used = read();
+        assert!(used < Duration::from_millis(50));
+        spin(Duration::from_millis(5));
+        let later = read();
+        assert!(later > used && later < Duration::from_millis(50));
+    })
+    .join()
+    .unwrap();
+}
+
+#[test]
+#[cfg(windows)]
+fn try_now_for_id() {
+    extern "system" {
+        fn GetCurrentThreadId() -> u32;
+    }
+
+    let id = unsafe { GetCurrentThreadId() };
+    let time = ThreadTime::now_for_id(id).unwrap();
+    assert!(ThreadTime::now_for_id(id).unwrap() >= time);
+    // Thread ids are multiples of 4
+    assert_eq!(
+        ThreadTime::now_for_id(u32::MAX - 2).unwrap_err(),
+        cpu_time::CpuTimeError::ProcessGone
+    );
+}
+
+#[test]
+#[cfg(windows)]
+fn handle() {
+    assert!(!ProcessTime::handle().is_null());
+    assert!(!ThreadTime::handle().is_null());
+}
+
+#[test]
+#[cfg(target_os = "emscripten")]
+fn emscripten_clocks_are_monotonic() {
+    let process = ProcessTime::now();
+    let thread = ThreadTime::now();
+    assert!(ProcessTime::now().duration() >= process.duration());
+    assert!(ThreadTime::now().duration() >= thread.duration());
+}
+
+#[test]
+#[cfg(target_os = "haiku")]
+fn haiku_times_advance() {
+    let process = ProcessTime::now();
+    let thread = ThreadTime::now();
+    spin(Duration::from_millis(1));
+    assert!(thread.elapsed() >= Duration::from_millis(1));
+    assert!(process.elapsed() >= thread.elapsed());
+}
+
+#[test]
+#[cfg(any(
+    target_os = "nto",
+    target_os = "vxworks",
+    target_os = "ios",
+    target_os = "tvos",
+    target_os = "watchos",
+    target_os = "hurd"
+))]
+fn times_advance() {
+    let process = ProcessTime::now();
+    let thread = ThreadTime::now();
+    spin(Duration::from_millis(1));
+    assert!(thread.elapsed() >= Duration::from_millis(1));
+    assert!(process.elapsed() >= Duration::from_millis(1));
+}
+
+#[test]
+fn capabilities() {
+    let caps = cpu_time::capabilities();
+    assert_eq!(caps.process_time(), ProcessTime::is_supported());
+    assert_eq!(caps.thread_time(), ThreadTime::is_supported());
+    assert_eq!(caps.wall_clock(), cfg!(target_os = "emscripten"));
+}
+
+#[test]
+#[cfg(miri)]
+fn miri_clock_advances() {
+    let process = ProcessTime::now();
+    let thread = ThreadTime::now();
+    assert_eq!(thread.elapsed(), Duration::from_micros(1));
+    assert!(process.elapsed() >= Duration::from_micros(2));
+}
+
+#[test]
+fn run_with_cpu_budget() {
+    use cpu_time::run_with_cpu_budget;
+
+    assert_eq!(run_with_cpu_budget(Duration::from_secs(60), |_| 42), Ok(42));
+    let error = run_with_cpu_budget(Duration::from_millis(5), |budget| {
+        while !budget.exceeded() {}
+        assert_eq!(budget.remaining(), Duration::from_secs(0));
+        assert!(budget.check().is_err());
+    })
+    .unwrap_err();
+    assert_eq!(error.budget, Duration::from_millis(5));
+    assert!(error.used > error.budget);
+}
+
+#[test]
+#[cfg(not(miri))]
+fn cpu_stopwatch() {
+    use cpu_time::CpuStopwatch;
+
+    let mut stopwatch = CpuStopwatch::thread();
+    assert!(!stopwatch.is_running());
+    assert_eq!(stopwatch.elapsed(), Duration::from_secs(0));
+    stopwatch.resume();
+    spin(Duration::from_millis(10));
+    stopwatch.pause();
+    let first = stopwatch.elapsed();
+    assert!(first >= Duration::from_millis(10));
+    spin(Duration::from_millis(10));
+    assert_eq!(stopwatch.elapsed(), first);
+    stopwatch.resume();
+    spin(Duration::from_millis(10));
+    assert!(stopwatch.is_running());
+    assert!(stopwatch.elapsed() >= first + Duration::from_millis(10));
+    stopwatch.start();
+    assert!(stopwatch.elapsed() < first);
+    stopwatch.reset();
+    assert_eq!(stopwatch, CpuStopwatch::default());
+
+    let mut process = CpuStopwatch::process();
+    process.start();
+    assert!(process.is_running());
+
+    let mut laps = CpuStopwatch::thread();
+    laps.start();
+    spin(Duration::from_millis(5));
+    let first = laps.lap("first");
+    laps.pause();
+    spin(Duration::from_millis(5));
+    laps.resume();
+    let second = laps.lap_indexed();
+    assert!(first >= Duration::from_millis(5));
+    assert!(second < Duration::from_millis(5));
+    assert_eq!(
+        laps.laps(),
+        &[("first".to_string(), first), ("1".to_string(), second)][..]
+    );
+    assert!(laps.elapsed() >= first + second);
+    laps.reset();
+    assert!(laps.laps().is_empty());
+}
+
+#[test]
+#[cfg(not(miri))]
+fn cpu_scope() {
+    use cpu_time::{accumulate, accumulated, set_reporter, CpuScope};
+    use std::cell::Cell;
+
+    let reported = Cell::new(None);
+    {
+        let scope = CpuScope::with_sink("spin", |label, time| {
+            assert_eq!(label, "spin");
+            reported.set(Some(time));
+        });
+        assert_eq!(scope.label(), "spin");
+        spin(Duration::from_millis(10));
+        assert!(scope.elapsed() >= Duration::from_millis(10));
+    }
+    assert!(reported.get().unwrap() >= Duration::from_millis(10));
+
+    let process = Cell::new(Duration::new(0, 0));
+    drop(CpuScope::process_with_sink("process", |_, time| {
+        process.set(time)
+    }));
+    assert!(process.get() < Duration::from_millis(10));
+
+    // Every test that reports globally sets this one
+    set_reporter(accumulate);
+    drop(CpuScope::new("test::global"));
+    drop(CpuScope::process("test::global"));
+    assert_eq!(accumulated("test::global").calls, 2);
+}
+
+#[test]
+#[cfg(not(miri))]
+fn measure() {
+    let ((), time) = ThreadTime::measure(|| spin(Duration::from_millis(10)));
+    assert!(time >= Duration::from_millis(10));
+    let (answer, time) = ProcessTime::measure(|| {
+        spin(Duration::from_millis(10));
+        42
+    });
+    assert_eq!(answer, 42);
+    assert!(time >= Duration::from_millis(10));
+}
+
+#[test]
+#[cfg(not(miri))]
+fn time_block() {
+    use cpu_time::{accumulate, accumulated, accumulators};
+
+    for _ in 0..3 {
+        let answer = cpu_time::time_block!("test::spin", {
+            spin(Duration::from_millis(5));
+            42
+        });
+        assert_eq!(answer, 42);
+    }
+    let spun = accumulated("test::spin");
+    assert_eq!(spun.calls, 3);
+    assert!(spun.total >= Duration::from_millis(15));
+    assert!(spun.mean() >= Duration::from_millis(5));
+
+    accumulate("test::manual", Duration::from_millis(1));
+    accumulate("test::manual", Duration::from_millis(2));
+    assert!(accumulators().contains(&(
+        "test::manual".to_string(),
+        cpu_time::Accumulated {
+            calls: 2,
+            total: Duration::from_millis(3),
+        }
+    )));
+    assert_eq!(accumulated("test::none"), Default::default());
+}
+
+#[cfg(feature = "macros")]
+#[cpu_time::cpu_time]
+fn spin_measured(time: Duration) -> Duration {
+    if time == Duration::from_secs(0) {
+        return time;
+    }
+    spin(time);
+    time
+}
+
+#[test]
+#[cfg(all(feature = "macros", not(miri)))]
+fn cpu_time_attribute() {
+    use cpu_time::{accumulate, accumulated, set_reporter};
+
+    set_reporter(accumulate);
+    assert_eq!(
+        spin_measured(Duration::from_millis(5)),
+        Duration::from_millis(5)
+    );
+    spin_measured(Duration::from_secs(0));
+    let measured = accumulated("time::spin_measured");
+    assert_eq!(measured.calls, 2);
+    assert!(measured.total >= Duration::from_millis(5));
+}
+
+#[test]
+#[cfg(not(miri))]
+fn profile_session() {
+    use cpu_time::profile::{region, Session};
+
+    drop(region("ignored"));
+    let session = Session::start();
+    {
+        let _outer = region("outer");
+        spin(Duration::from_millis(5));
+        for _ in 0..2 {
+            let _inner = region("inner");
+            spin(Duration::from_millis(5));
+        }
+    }
+    let _open = region("open");
+    let report = session.finish();
+    assert_eq!(report.regions.len(), 2);
+    let outer = report.get(&["outer"]).unwrap();
+    assert_eq!(outer.calls, 1);
+    assert!(outer.exclusive >= Duration::from_millis(5));
+    let inner = report.get(&["outer", "inner"]).unwrap();
+    assert_eq!(inner.calls, 2);
+    assert!(inner.inclusive >= Duration::from_millis(10));
+    assert_eq!(inner.exclusive, inner.inclusive);
+    assert_eq!(outer.inclusive, outer.exclusive + inner.inclusive);
+    assert!(report.get(&["open"]).is_some());
+    assert!(report.get(&["inner"]).is_none());
+    assert!(report.total >= outer.inclusive);
+    assert!(report.unaccounted() < report.total);
+}
+
+#[test]
+fn counter() {
+    use cpu_time::counter;
+
+    counter("test::counter").add(Duration::from_millis(2));
+    let mut counter = counter("test::counter");
+    counter += Duration::from_millis(3);
+    counter += Duration::from_millis(5);
+    assert_eq!(counter.label(), "test::counter");
+    assert_eq!(counter.get().calls, 3);
+    assert_eq!(counter.get().total, Duration::from_millis(10));
+}
+
+#[test]
+#[cfg(not(miri))]
+fn report_table() {
+    use cpu_time::profile::{region, Session};
+    use cpu_time::{Region, Report};
+
+    let session = Session::start_split();
+    {
+        let _outer = region("outer");
+        let _inner = region("inner");
+        spin(Duration::from_millis(5));
+    }
+    let report = session.finish();
+    // Without libc there is no `getrusage()` to split the time on Linux
+    let split = ThreadTime::try_now_split().is_ok();
+    assert_eq!(
+        report.get(&["outer", "inner"]).unwrap().split.is_some(),
+        split
+    );
+    let table = report.to_string();
+    let lines = table.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("label "));
+    assert_eq!(lines[0].ends_with(" user       system"), split);
+    assert!(lines[1].starts_with("outer "));
+    assert!(lines[2].starts_with("  inner "));
+    // Durations below a millisecond are printed in µs
+    let width = |line: &str| line.chars().count();
+    assert!(lines.iter().all(|line| width(line) == width(lines[0])));
+
+    let report = Report {
+        total: Duration::from_millis(40),
+        regions: vec![Region {
+            name: "parse".to_string(),
+            calls: 4,
+            inclusive: Duration::from_millis(10),
+            exclusive: Duration::from_millis(10),
+            split: None,
+            children: Vec::new(),
+        }],
+        events: Vec::new(),
+    };
+    assert_eq!(
+        report.to_string(),
+        "label      calls        total         mean       %\n\
+         parse          4     10.000ms      2.500ms   25.0%\n"
+    );
+
+    cpu_time::accumulate("test::report", Duration::from_millis(1));
+    assert!(Report::accumulators().get(&["test::report"]).is_some());
+}
+
+#[test]
+#[cfg(not(miri))]
+fn chrome_trace() {
+    use cpu_time::profile::{region, Session};
+
+    let session = Session::start();
+    {
+        let _outer = region("outer \"quoted\"");
+        spin(Duration::from_millis(1));
+        drop(region("inner"));
+    }
+    let report = session.finish();
+    let names = report
+        .events
+        .iter()
+        .map(|event| (event.name.as_str(), event.depth))
+        .collect::<Vec<_>>();
+    assert_eq!(names, [("outer \"quoted\"", 0), ("inner", 1)]);
+    let outer = &report.events[0];
+    let inner = &report.events[1];
+    assert!(inner.start >= outer.start + Duration::from_millis(1));
+    assert!(inner.start + inner.duration <= outer.start + outer.duration);
+
+    let trace = report.to_chrome_trace();
+    assert!(trace.starts_with("{\"displayTimeUnit\":\"ns\",\"traceEvents\":["));
+    assert!(
+        trace.contains("{\"name\":\"outer \\\"quoted\\\"\",\"cat\":\"cpu\",\"ph\":\"X\",\"ts\":")
+    );
+    assert!(trace.contains("{\"name\":\"inner\","));
+    assert!(trace.ends_with("]}\n"));
+}
+
+#[test]
+fn speedscope() {
+    use cpu_time::{Event, Report};
+
+    let event = |name: &str, depth, start, duration| Event {
+        name: name.to_string(),
+        depth,
+        start: Duration::from_nanos(start),
+        duration: Duration::from_nanos(duration),
+    };
+    let report = Report {
+        total: Duration::from_nanos(100),
+        regions: Vec::new(),
+        events: vec![
+            event("outer", 0, 0, 50),
+            event("inner", 1, 10, 20),
+            event("inner", 1, 30, 10),
+            event("outer", 0, 60, 10),
+        ],
+    };
+    let profile = report.to_speedscope();
+    assert!(profile.contains("\"frames\":[{\"name\":\"outer\"},{\"name\":\"inner\"}]"));
+    assert!(profile.contains("\"startValue\":0,\"endValue\":100,"));
+    assert!(profile.contains(
+        "\"events\":[\
+         {\"type\":\"O\",\"frame\":0,\"at\":0},\
+         {\"type\":\"O\",\"frame\":1,\"at\":10},\
+         {\"type\":\"C\",\"frame\":1,\"at\":30},\
+         {\"type\":\"O\",\"frame\":1,\"at\":30},\
+         {\"type\":\"C\",\"frame\":1,\"at\":40},\
+         {\"type\":\"C\",\"frame\":0,\"at\":50},\
+         {\"type\":\"O\",\"frame\":0,\"at\":60},\
+         {\"type\":\"C\",\"frame\":0,\"at\":70}]"
+    ));
+}
+
+#[test]
+fn merged_trace() {
+    use cpu_time::{Event, Report};
+
+    let event = |name: &str, start, duration| Event {
+        name: name.to_string(),
+        depth: 0,
+        start: Duration::from_nanos(start),
+        duration: Duration::from_nanos(duration),
+    };
+    let report = |name| Report {
+        total: Duration::from_nanos(100),
+        regions: Vec::new(),
+        events: vec![event(name, 10, 50)],
+    };
+    let mut combined = report("a");
+    combined.merge(&report("b"));
+    // The second session follows the first
+    assert_eq!(combined.events, [event("a", 10, 50), event("b", 110, 50)]);
+    assert!(combined.to_speedscope().contains(
+        "\"endValue\":200,\"events\":[\
+         {\"type\":\"O\",\"frame\":0,\"at\":10},\
+         {\"type\":\"C\",\"frame\":0,\"at\":60},\
+         {\"type\":\"O\",\"frame\":1,\"at\":110},\
+         {\"type\":\"C\",\"frame\":1,\"at\":160}]"
+    ));
+    assert!(combined.to_chrome_trace().contains("\"ts\":0.110"));
+}
+
+#[test]
+fn speedscope_equal_starts() {
+    use cpu_time::profile::{region, Session};
+    use cpu_time::{Event, Report};
+
+    // Like a coarse clock, which gives them all the same start
+    let event = |name: &str, depth| Event {
+        name: name.to_string(),
+        depth,
+        start: Duration::new(0, 0),
+        duration: Duration::new(0, 0),
+    };
+    let report = Report {
+        total: Duration::new(0, 0),
+        regions: Vec::new(),
+        events: vec![event("p", 0), event("c", 1), event("q", 0)],
+    };
+    assert!(report.to_speedscope().contains(
+        "\"events\":[\
+         {\"type\":\"O\",\"frame\":0,\"at\":0},\
+         {\"type\":\"O\",\"frame\":1,\"at\":0},\
+         {\"type\":\"C\",\"frame\":1,\"at\":0},\
+         {\"type\":\"C\",\"frame\":0,\"at\":0},\
+         {\"type\":\"O\",\"frame\":2,\"at\":0},\
+         {\"type\":\"C\",\"frame\":2,\"at\":0}]"
+    ));
+
+    // Sessions list them in the order they were entered, whatever the
+    // clock says
+    let session = Session::start();
+    {
+        let _p = region("p");
+        let _c = region("c");
+    }
+    drop(region("q"));
+    let names = session
+        .finish()
+        .events
+        .into_iter()
+        .map(|event| (event.name, event.depth))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [
+            ("p".to_string(), 0),
+            ("c".to_string(), 1),
+            ("q".to_string(), 0)
+        ]
+    );
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn report_json_and_csv() {
+    use cpu_time::{Event, Region, Report, SplitTime};
+
+    let report = Report {
+        total: Duration::from_nanos(100),
+        regions: vec![Region {
+            name: "a, \"b\"".to_string(),
+            calls: 1,
+            inclusive: Duration::from_nanos(50),
+            exclusive: Duration::from_nanos(30),
+            split: None,
+            children: vec![Region {
+                name: "c".to_string(),
+                calls: 2,
+                inclusive: Duration::from_nanos(20),
+                exclusive: Duration::from_nanos(20),
+                split: Some(SplitTime {
+                    user: Duration::from_nanos(15),
+                    system: Duration::from_nanos(5),
+                }),
+                children: Vec::new(),
+            }],
+        }],
+        events: vec![Event {
+            name: "c".to_string(),
+            depth: 1,
+            start: Duration::from_nanos(10),
+            duration: Duration::from_nanos(10),
+        }],
+    };
+    let json = report.to_json();
+    assert_eq!(json, serde_json::to_string(&report).unwrap());
+    assert_eq!(
+        json,
+        "{\"total\":100,\"regions\":[{\"name\":\"a, \\\"b\\\"\",\"calls\":1,\
+         \"inclusive\":50,\"exclusive\":30,\"split\":null,\"children\":[\
+         {\"name\":\"c\",\"calls\":2,\"inclusive\":20,\"exclusive\":20,\
+         \"split\":{\"user\":15,\"system\":5},\"children\":[]}]}],\
+         \"events\":[{\"name\":\"c\",\"depth\":1,\"start\":10,\"duration\":10}]}"
+    );
+    assert_eq!(serde_json::from_str::<Report>(&json).unwrap(), report);
+    assert_eq!(
+        report.to_csv(),
+        "path,calls,inclusive,exclusive,user,system\n\
+         \"a, \"\"b\"\"\",1,50,30,,\n\
+         \"a, \"\"b\"\"/c\",2,20,20,15,5\n"
+    );
+}
+
+#[test]
+fn report_merge() {
+    use cpu_time::{Region, Report, SplitTime};
+
+    fn region(name: &str, calls: u64, nanos: u64, children: Vec<Region>) -> Region {
+        Region {
+            name: name.to_string(),
+            calls,
+            inclusive: Duration::from_nanos(nanos),
+            exclusive: Duration::from_nanos(nanos),
+            split: Some(SplitTime {
+                user: Duration::from_nanos(nanos),
+                system: Duration::new(0, 0),
+            }),
+            children,
+        }
+    }
+
+    let mut combined = Report {
+        total: Duration::from_nanos(100),
+        regions: vec![region("a", 1, 50, vec![region("b", 2, 20, Vec::new())])],
+        events: Vec::new(),
+    };
+    let mut other = Report {
+        total: Duration::from_nanos(80),
+        regions: vec![
+            region("a", 3, 40, vec![region("c", 1, 10, Vec::new())]),
+            region("d", 1, 5, Vec::new()),
+        ],
+        events: Vec::new(),
+    };
+    other.regions[0].split = None;
+    combined.merge(&other);
+    assert_eq!(combined.total, Duration::from_nanos(180));
+    assert_eq!(combined.regions.len(), 2);
+    let a = combined.get(&["a"]).unwrap();
+    assert_eq!((a.calls, a.inclusive), (4, Duration::from_nanos(90)));
+    assert_eq!(a.split, None);
+    assert_eq!(combined.get(&["a", "b"]).unwrap().calls, 2);
+    assert_eq!(combined.get(&["a", "c"]).unwrap().calls, 1);
+    assert_eq!(
+        combined.get(&["d"]).unwrap().split.unwrap().user,
+        Duration::from_nanos(5)
+    );
+
+    #[cfg(feature = "serde")]
+    {
+        let json = serde_json::to_string(&combined).unwrap();
+        assert_eq!(serde_json::from_str::<Report>(&json).unwrap(), combined);
+        let ignored = "{\"version\":1,\"total\":5,\"regions\":[]}";
+        let report = serde_json::from_str::<Report>(ignored).unwrap();
+        assert_eq!(report.total, Duration::from_nanos(5));
+        assert!(serde_json::from_str::<Report>("{\"regions\":[]}").is_err());
+    }
+}
+
+#[test]
+#[cfg(all(unix, feature = "libc", not(miri)))]
+fn shared_counters() {
+    use cpu_time::SharedCounters;
+
+    let counters = SharedCounters::new(4).unwrap();
+    assert_eq!(counters.capacity(), 4);
+    let mut workers = Vec::new();
+    for _ in 0..3 {
+        let pid = unsafe { libc::fork() };
+        assert!(pid >= 0);
+        if pid == 0 {
+            // Only async-signal-safe calls in the child of a threaded process
+            for _ in 0..100 {
+                counters.add("request", Duration::from_micros(10));
+            }
+            counters.add("startup", Duration::from_millis(1));
+            unsafe { libc::_exit(0) };
+        }
+        workers.push(pid);
+    }
+    for pid in workers {
+        let mut status = 0;
+        assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+        assert_eq!(status, 0);
+    }
+    let request = counters.get("request");
+    assert_eq!(request.calls, 300);
+    assert_eq!(request.total, Duration::from_millis(3));
+    assert_eq!(counters.get("startup").calls, 3);
+
+    let long = "a label that is longer than the fifty-six bytes of a slot";
+    assert!(counters.add(long, Duration::from_millis(1)));
+    assert_eq!(counters.get(long).calls, 1);
+    assert!(counters.add("other", Duration::from_millis(1)));
+    assert!(!counters.add("full", Duration::from_millis(1)));
+    let report = counters.report();
+    assert_eq!(report.regions[0].name, "request");
+    assert_eq!(report.total, Duration::from_millis(8));
+}
+
+// Polls `future` to completion on the current thread
+#[allow(dead_code)]
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    use std::pin::pin;
+    use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+    fn clone(_: *const ()) -> RawWaker {
+        RawWaker::new(std::ptr::null(), &VTABLE)
+    }
+    fn noop(_: *const ()) {}
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+
+    let waker = unsafe { Waker::from_raw(clone(std::ptr::null())) };
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+// Spins for `time` and sleeps for `sleep` in each of `polls` polls
+#[allow(dead_code)]
+struct Spinning {
+    polls: u32,
+    time: Duration,
+    sleep: Duration,
+}
+
+impl std::future::Future for Spinning {
+    type Output = u32;
+
+    fn poll(
+        mut self: std::pin::Pin<&mut Self>,
+        _: &mut std::task::Context,
+    ) -> std::task::Poll<u32> {
+        spin(self.time);
+        sleep(self.sleep);
+        self.polls -= 1;
+        match self.polls {
+            0 => std::task::Poll::Ready(7),
+            _ => std::task::Poll::Pending,
+        }
+    }
+}
+
+#[test]
+#[cfg(not(miri))]
+fn cpu_timed() {
+    use cpu_time::FutureExt;
+
+    let (output, used) = block_on(
+        Spinning {
+            polls: 3,
+            time: Duration::from_millis(5),
+            sleep: Duration::new(0, 0),
+        }
+        .cpu_timed(),
+    );
+    assert_eq!(output, 7);
+    assert!(used >= Duration::from_millis(15));
+    assert!(used < Duration::from_millis(500));
+
+    // Waiting in a poll isn't counted
+    let (_, used) = block_on(
+        Spinning {
+            polls: 1,
+            time: Duration::new(0, 0),
+            sleep: Duration::from_millis(20),
+        }
+        .cpu_timed(),
+    );
+    assert!(used < Duration::from_millis(10));
+}
+
+#[test]
+#[cfg(not(miri))]
+fn instrumented_tasks() {
+    use cpu_time::task;
+    use std::task::Poll;
+
+    let mut seen = Vec::new();
+    let future = task::instrument(std::future::poll_fn(move |_| {
+        spin(Duration::from_millis(5));
+        seen.push(task::current().unwrap());
+        if seen.len() == 3 {
+            return Poll::Ready(seen.clone());
+        }
+        Poll::Pending
+    }));
+    let id = future.id();
+    assert_eq!(task::get(id), None);
+    assert_eq!(task::current(), None);
+    let seen = block_on(future);
+    assert!(seen.iter().all(|task| task.id == id));
+    assert_eq!(seen[2].polls, 2);
+    assert!(seen[2].total >= Duration::from_millis(10));
+    assert_eq!(task::get(id), None);
+    assert_ne!(task::instrument(std::future::ready(())).id(), id);
+}
+
+#[test]
+#[cfg(all(feature = "tokio", not(miri)))]
+fn tokio_tasks() {
+    use cpu_time::task;
+    use std::task::Poll;
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let _runtime = runtime.enter();
+    let mut seen = Vec::new();
+    let handle = task::spawn(std::future::poll_fn(move |cx| {
+        spin(Duration::from_millis(5));
+        // The polls before this one
+        seen.push(task::current().unwrap());
+        if seen.len() == 3 {
+            return Poll::Ready(seen.clone());
+        }
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }));
+    let id = handle.id().into();
+    assert_eq!(task::get(id), None);
+    let seen = runtime.block_on(handle).unwrap();
+    assert!(seen.iter().all(|task| task.id == id));
+    assert_eq!(seen[0].polls, 0);
+    assert_eq!(seen[2].polls, 2);
+    assert!(seen[2].total >= Duration::from_millis(10));
+    assert_eq!(task::get(id), None);
+    assert!(task::tasks().iter().all(|task| task.id != id));
+}
+
+#[test]
+#[cfg(all(feature = "tokio", tokio_unstable, not(miri)))]
+fn tokio_task_hooks() {
+    use cpu_time::task;
+    use std::task::Poll;
+
+    let runtime = task::hooks(&mut tokio::runtime::Builder::new_current_thread())
+        .build()
+        .unwrap();
+    let mut polls = 0;
+    let handle = runtime.spawn(std::future::poll_fn(move |cx| {
+        spin(Duration::from_millis(5));
+        polls += 1;
+        if polls == 3 {
+            return Poll::Ready(task::current().unwrap());
+        }
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }));
+    let id = handle.id().into();
+    let seen = runtime.block_on(handle).unwrap();
+    assert_eq!(seen.id, id);
+    assert_eq!(seen.polls, 2);
+    assert!(seen.total >= Duration::from_millis(10));
+    assert_eq!(task::get(id), None);
+}
+
+#[test]
+#[cfg(all(feature = "futures-core", not(miri)))]
+fn cpu_per_item() {
+    use cpu_time::StreamExt;
+    use futures_core::Stream;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    // Item `n` takes `n` polls of 2ms each
+    struct Items {
+        item: u32,
+        polls: u32,
+    }
+
+    impl Stream for Items {
+        type Item = u32;
+
+        fn poll_next(mut self: Pin<&mut Self>, _: &mut Context) -> Poll<Option<u32>> {
+            if self.item > 2 {
+                return Poll::Ready(None);
+            }
+            spin(Duration::from_millis(2));
+            self.polls += 1;
+            if self.polls < self.item {
+                return Poll::Pending;
+            }
+            self.polls = 0;
+            self.item += 1;
+            Poll::Ready(Some(self.item - 1))
+        }
+    }
+
+    fn next<S: Stream + Unpin>(items: &mut S) -> Option<S::Item> {
+        block_on(std::future::poll_fn(|cx| {
+            Pin::new(&mut *items).poll_next(cx)
+        }))
+    }
+
+    let mut items = Items { item: 1, polls: 0 }.cpu_per_item();
+    let (item, used) = next(&mut items).unwrap();
+    assert_eq!(item, 1);
+    assert!(used >= Duration::from_millis(2));
+    let (item, used) = next(&mut items).unwrap();
+    assert_eq!(item, 2);
+    assert!(used >= Duration::from_millis(4));
+    assert_eq!(next(&mut items), None);
+}
+
+#[test]
+#[cfg(all(feature = "tower", not(miri)))]
+fn cpu_time_layer() {
+    use cpu_time::{accumulate, accumulated, CpuTimeLayer, RequestCpuTime};
+    use std::task::{Context, Poll};
+    use tower_layer::Layer;
+    use tower_service::Service;
+
+    // Spins for the requested milliseconds in `call()` and in each of two
+    // polls of the response future
+    struct Spin;
+
+    struct Respond(Spinning);
+
+    impl std::future::Future for Respond {
+        type Output = Result<http::Response<()>, ()>;
+
+        fn poll(mut self: std::pin::Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+            std::pin::Pin::new(&mut self.0)
+                .poll(cx)
+                .map(|_| Ok(http::Response::new(())))
+        }
+    }
+
+    impl Service<u64> for Spin {
+        type Response = http::Response<()>;
+        type Error = ();
+        type Future = Respond;
+
+        fn poll_ready(&mut self, _: &mut Context) -> Poll<Result<(), ()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, millis: u64) -> Respond {
+            spin(Duration::from_millis(millis));
+            Respond(Spinning {
+                polls: 2,
+                time: Duration::from_millis(millis),
+                sleep: Duration::new(0, 0),
+            })
+        }
+    }
+
+    let mut service = CpuTimeLayer::with_sink("test::tower", accumulate).layer(Spin);
+    let response = block_on(service.call(3)).unwrap();
+    let RequestCpuTime(used) = *response.extensions().get().unwrap();
+    assert!(used >= Duration::from_millis(9));
+    let reported = accumulated("test::tower");
+    assert_eq!(reported.calls, 1);
+    assert_eq!(reported.total, used);
+}
+
+#[test]
+#[cfg(all(feature = "tower", not(miri)))]
+fn request_cpu_layer() {
+    use cpu_time::{accumulate, accumulated, RequestCpu, RequestCpuLayer, RequestCpuTime};
+    use std::task::{Context, Poll};
+    use tower_layer::Layer;
+    use tower_service::Service;
+
+    // Spins 5ms in each of two polls, returning what the request used
+    // during the second
+    struct Handler;
+
+    struct Handling(RequestCpu, u32);
+
+    impl std::future::Future for Handling {
+        type Output = Result<http::Response<Duration>, ()>;
+
+        fn poll(mut self: std::pin::Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+            spin(Duration::from_millis(5));
+            self.1 += 1;
+            if self.1 < 2 {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            Poll::Ready(Ok(http::Response::new(self.0.elapsed())))
+        }
+    }
+
+    impl Service<http::Request<()>> for Handler {
+        type Response = http::Response<Duration>;
+        type Error = ();
+        type Future = Handling;
+
+        fn poll_ready(&mut self, _: &mut Context) -> Poll<Result<(), ()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: http::Request<()>) -> Handling {
+            let cpu = request.extensions().get::<RequestCpu>().unwrap().clone();
+            Handling(cpu, 0)
+        }
+    }
+
+    let mut service = RequestCpuLayer::with_sink(accumulate).layer(Handler);
+    let request = http::Request::post("/test/request?q=1").body(()).unwrap();
+    let response = block_on(service.call(request)).unwrap();
+    let RequestCpuTime(used) = *response.extensions().get().unwrap();
+    // Mid-flight, the running poll included
+    assert!(*response.body() >= Duration::from_millis(10));
+    assert!(*response.body() <= used);
+    let header = response.headers()["x-cpu-time"].to_str().unwrap();
+    assert!(header.ends_with("ms"));
+    let millis = header.trim_end_matches("ms").parse::<f64>().unwrap();
+    assert_eq!(
+        millis,
+        (used.as_micros() as f64 / 1000.0 * 1000.0).round() / 1000.0
+    );
+    let reported = accumulated("POST /test/request");
+    assert_eq!(reported.calls, 1);
+    assert_eq!(reported.total, used);
+}
+
+#[test]
+#[cfg(feature = "tower")]
+fn request_cpu_other_thread() {
+    use cpu_time::{RequestCpu, RequestCpuLayer};
+    use std::task::{Context, Poll};
+    use tower_layer::Layer;
+    use tower_service::Service;
+
+    fn noop(_: &str, _: Duration) {}
+
+    // Returns what the request used as seen by the handler and, during
+    // the same poll, by a thread that used more CPU time than this one
+    struct Handler;
+
+    struct Handling(RequestCpu);
+
+    impl std::future::Future for Handling {
+        type Output = Result<http::Response<(Duration, Duration)>, ()>;
+
+        fn poll(self: std::pin::Pin<&mut Self>, _: &mut Context) -> Poll<Self::Output> {
+            spin(Duration::from_millis(5));
+            let cpu = self.0.clone();
+            let theirs = std::thread::spawn(move || {
+                spin(Duration::from_millis(50));
+                cpu.elapsed()
+            })
+            .join()
+            .unwrap();
+            Poll::Ready(Ok(http::Response::new((self.0.elapsed(), theirs))))
+        }
+    }
+
+    impl Service<http::Request<()>> for Handler {
+        type Response = http::Response<(Duration, Duration)>;
+        type Error = ();
+        type Future = Handling;
+
+        fn poll_ready(&mut self, _: &mut Context) -> Poll<Result<(), ()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: http::Request<()>) -> Handling {
+            Handling(request.extensions().get::<RequestCpu>().unwrap().clone())
+        }
+    }
+
+    let mut service = RequestCpuLayer::with_sink(noop).layer(Handler);
+    let request = http::Request::get("/").body(()).unwrap();
+    let response = block_on(service.call(request)).unwrap();
+    let (ours, theirs) = *response.body();
+    assert!(ours >= Duration::from_millis(5));
+    // Only `call()` finished
+    assert!(theirs < Duration::from_millis(5));
+}
+
+#[test]
+#[cfg(feature = "axum")]
+fn request_cpu_extractor() {
+    use axum_core::extract::FromRequestParts;
+    use cpu_time::{RequestCpu, RequestCpuLayer};
+    use std::task::{Context, Poll};
+    use tower_layer::Layer;
+    use tower_service::Service;
+
+    fn extract(request: http::Request<()>) -> Result<RequestCpu, http::StatusCode> {
+        let (mut parts, ()) = request.into_parts();
+        block_on(RequestCpu::from_request_parts(&mut parts, &())).map_err(|(status, _)| status)
+    }
+
+    struct Extract;
+
+    impl Service<http::Request<()>> for Extract {
+        type Response = http::Response<bool>;
+        type Error = ();
+        type Future = std::future::Ready<Result<http::Response<bool>, ()>>;
+
+        fn poll_ready(&mut self, _: &mut Context) -> Poll<Result<(), ()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: http::Request<()>) -> Self::Future {
+            std::future::ready(Ok(http::Response::new(extract(request).is_ok())))
+        }
+    }
+
+    assert_eq!(
+        extract(http::Request::new(())).unwrap_err(),
+        http::StatusCode::INTERNAL_SERVER_ERROR
+    );
+    let mut service = RequestCpuLayer::with_sink(|_, _| {}).layer(Extract);
+    let response = block_on(service.call(http::Request::new(()))).unwrap();
+    assert!(*response.body());
+}